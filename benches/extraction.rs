@@ -0,0 +1,34 @@
+//! Throughput of the telegram extraction and parsing on the captured corpora, run with `cargo bench --features bench`.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use homey_energy_dongle::bench::measure_throughput;
+
+/// Captured telegrams, every corpus is repeated to simulate a longer stream
+const CORPORA: [(&str, &[u8]); 2] = [
+	("dsmr5", include_bytes!("corpus/dsmr5.txt")),
+	("dsmr3", include_bytes!("corpus/dsmr3.txt")),
+];
+const REPEAT: usize = 100;
+
+fn extraction(c: &mut Criterion) {
+	let mut group = c.benchmark_group("extraction");
+	for (name, telegram) in CORPORA {
+		let corpus = telegram.repeat(REPEAT);
+		group.throughput(Throughput::Bytes(corpus.len() as u64));
+		// the frame sizes of the slow serial bridges, the dongle WebSocket messages and the whole corpus at once
+		for chunk_size in [64, 1024, corpus.len()] {
+			group.bench_with_input(BenchmarkId::new(name, chunk_size), &chunk_size, |b, &chunk_size| {
+				b.iter(|| {
+					let throughput = measure_throughput(black_box(&corpus), chunk_size);
+					assert_eq!(REPEAT, throughput.telegrams);
+				})
+			});
+		}
+	}
+	group.finish();
+}
+
+criterion_group!(benches, extraction);
+criterion_main!(benches);
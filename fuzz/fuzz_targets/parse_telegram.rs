@@ -0,0 +1,16 @@
+#![no_main]
+
+use homey_energy_dongle::parse_telegram;
+use homey_energy_dongle::reader::RawTelegramReader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = parse_telegram(data);
+	let mut reader = RawTelegramReader::new();
+	for chunk in data.chunks(64) {
+		for telegram in reader.feed(chunk) {
+			let _ = telegram.check_crc();
+			let _ = telegram.parse();
+		}
+	}
+});
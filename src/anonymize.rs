@@ -0,0 +1,188 @@
+//! Removal of the meter identifiers from telegrams before sharing them.
+//!
+//! Telegrams captured from a real meter contain the equipment identifiers of the electricity meter and of the M-Bus devices
+//! (0-n:96.1.0 and 0-n:96.1.1 objects) and the meter identification in the header. [Anonymizer] replaces them with placeholders
+//! and recomputes the CRC, so that the captures can be attached to bug reports without leaking the meter IDs.
+
+use std::collections::HashMap;
+use std::str;
+
+use crate::reader::{RawTelegram, crc16};
+use crate::telegram::Obis;
+
+/// Replaces the identifiers in telegrams and captures with placeholders.
+///
+/// Every distinct identifier gets a numbered placeholder of the same length and encoding, the same identifier is always replaced
+/// with the same placeholder, so the captures from several meters can still be told apart. The header keeps the manufacturer
+/// code and the baud rate character, only the identification after them is replaced. The CRC of the telegrams that have one is
+/// recalculated, the telegrams without CRC are left without it.
+///
+/// The input can be a single telegram or a capture of consecutive telegrams, the bytes outside of telegrams are copied as is.
+/// Every telegram must be passed to a single [Anonymizer::anonymize()] call, a telegram split between the calls doesn't get its
+/// CRC recalculated.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::anonymize::Anonymizer;
+/// use homey_energy_dongle::telegram::{Obis, parse_telegram};
+///
+/// let mut anonymizer = Anonymizer::new();
+/// let anonymized = anonymizer.anonymize(b"/ISk5\\2MT382-1000\r\n\r\n0-0:96.1.1(4B384547)\r\n!C6E3\r\n");
+/// let telegram = parse_telegram(&anonymized).unwrap();
+/// assert_eq!("ISk5\\20000000001", telegram.header);
+/// assert_eq!(Some("30303032"), telegram.value(Obis::new(0, 0, 96, 1, 1)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Anonymizer {
+	/// Placeholders of the already seen identifiers
+	placeholders: HashMap<String, String>,
+}
+
+impl Anonymizer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the copy of `bytes` with the identifiers replaced and the CRCs recalculated.
+	pub fn anonymize(&mut self, bytes: &[u8]) -> Vec<u8> {
+		let mut out = Vec::with_capacity(bytes.len());
+		// offset of the header of the current telegram in `out`
+		let mut telegram_start = None;
+		for line in bytes.split_inclusive(|&b| b == b'\n') {
+			let (contents, terminator) = split_terminator(line);
+			match contents.first() {
+				Some(b'/') => {
+					telegram_start = Some(out.len());
+					out.push(b'/');
+					out.extend_from_slice(self.anonymize_header(&contents[1..]).as_bytes());
+					out.extend_from_slice(terminator);
+				}
+				Some(b'!') => match telegram_start.take() {
+					Some(start) if is_crc(&contents[1..]) => {
+						out.push(b'!');
+						let crc = crc16(&out[start..]);
+						out.extend_from_slice(format!("{crc:04X}").as_bytes());
+						out.extend_from_slice(terminator);
+					}
+					_ => out.extend_from_slice(line),
+				},
+				_ if telegram_start.is_some() => match self.anonymize_object(contents) {
+					Some(contents) => {
+						out.extend_from_slice(contents.as_bytes());
+						out.extend_from_slice(terminator);
+					}
+					None => out.extend_from_slice(line),
+				},
+				_ => out.extend_from_slice(line),
+			}
+		}
+		out
+	}
+
+	/// Returns the copy of `telegram` with the identifiers replaced and the CRC recalculated.
+	pub fn anonymize_telegram(&mut self, telegram: &RawTelegram) -> RawTelegram {
+		RawTelegram {
+			contents: self.anonymize(&telegram.contents),
+		}
+	}
+
+	/// Replaces the identification in the header line without the leading "/".
+	fn anonymize_header(&mut self, header: &[u8]) -> String {
+		let header = String::from_utf8_lossy(header);
+		// "XXXn" manufacturer code and baud rate, optionally followed by "\" and the mode character
+		let prefix_len = match header.get(4..5) {
+			Some("\\") => 6,
+			_ => 4,
+		};
+		let Some((prefix, identification)) = header.split_at_checked(prefix_len) else {
+			return header.into_owned();
+		};
+		if identification.is_empty() {
+			return header.into_owned();
+		}
+		format!("{prefix}{}", self.placeholder(identification))
+	}
+
+	/// Replaces the value of the equipment identifier object, returns `None` for all other lines.
+	fn anonymize_object(&mut self, line: &[u8]) -> Option<String> {
+		let line = str::from_utf8(line).ok()?;
+		let (obis, rest) = line.split_at_checked(line.find('(')?)?;
+		let obis = obis.parse::<Obis>().ok()?;
+		if !(obis.a == 0 && obis.c == 96 && obis.d == 1 && obis.e <= 1) {
+			return None;
+		}
+		let (value, rest) = rest[1..].split_at_checked(rest.find(')')? - 1)?;
+		Some(format!("{obis}({}{rest}", self.placeholder(value)))
+	}
+
+	/// Returns the placeholder for `identifier`, hex-encoded if the identifier is.
+	fn placeholder(&mut self, identifier: &str) -> String {
+		let next = self.placeholders.len() + 1;
+		self
+			.placeholders
+			.entry(identifier.to_string())
+			.or_insert_with(|| {
+				if identifier.len() % 2 == 0 && identifier.bytes().all(|b| b.is_ascii_hexdigit()) {
+					format!("{next:0>len$}", len = identifier.len() / 2)
+						.bytes()
+						.map(|b| format!("{b:02X}"))
+						.collect()
+				} else {
+					format!("{next:0>len$}", len = identifier.len())
+				}
+			})
+			.clone()
+	}
+}
+
+fn split_terminator(line: &[u8]) -> (&[u8], &[u8]) {
+	let contents = line.strip_suffix(b"\n").unwrap_or(line);
+	let contents = contents.strip_suffix(b"\r").unwrap_or(contents);
+	line.split_at(contents.len())
+}
+
+fn is_crc(crc: &[u8]) -> bool {
+	crc.len() == 4 && crc.iter().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::reader::{CrcCheck, RawTelegramReader};
+	use crate::telegram::parse_telegram;
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_anonymize() {
+		let mut anonymizer = Anonymizer::new();
+		let capture = [b"noise\r\n".as_slice(), &with_crc(DSMR5), DSMR3.as_bytes(), &with_crc(DSMR5)].concat();
+		let anonymized = anonymizer.anonymize(&capture);
+		let rest = anonymized.strip_prefix(b"noise\r\n").unwrap();
+		let mut reader = RawTelegramReader::new();
+		let telegrams = reader.feed(rest);
+		assert_eq!(3, telegrams.len());
+		assert_eq!(CrcCheck::Valid, telegrams[0].check_crc());
+		assert_eq!(telegrams[0].contents, telegrams[2].contents);
+
+		let dsmr5 = parse_telegram(&telegrams[0].contents).unwrap();
+		assert_eq!("ISk5\\20000000001", dsmr5.header);
+		assert_eq!(
+			Some(format!("{}32", "30".repeat(15)).as_str()),
+			dsmr5.value(Obis::new(0, 0, 96, 1, 1))
+		);
+		assert_eq!(
+			Some(format!("{}33", "30".repeat(16)).as_str()),
+			dsmr5.value(Obis::new(0, 1, 96, 1, 0))
+		);
+		let dsmr3 = parse_telegram(&telegrams[1].contents).unwrap();
+		assert_eq!("ISk5\\20000000004", dsmr3.header);
+		assert_eq!(None, dsmr3.crc);
+		assert_eq!(
+			Some(format!("{}35", "30".repeat(15)).as_str()),
+			dsmr3.value(Obis::new(0, 0, 96, 1, 1))
+		);
+		// the gas reading is not touched
+		assert_eq!(vec!["00001.234"], dsmr3.get(Obis::new(0, 1, 24, 3, 0)).unwrap().values[6..]);
+		assert!(!str::from_utf8(&anonymized).unwrap().contains("4B384547"));
+	}
+}
@@ -0,0 +1,277 @@
+//! Measurement of the telegram extraction performance.
+//!
+//! Load-control loops that react to the meter readings care about the time between the arrival of the WebSocket frame and the
+//! delivery of the parsed telegram. [LatencyProbe] measures it on the live stream, [measure_throughput()] measures how fast the
+//! reader and the parser process a captured corpus, e.g. to compare the releases of this crate or the chunk sizes of your
+//! transport. The criterion benchmark in `benches/extraction.rs` runs [measure_throughput()] over the captured corpora in
+//! `benches/corpus`, run it with `cargo bench --features bench`.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use futures_util::Stream;
+use log::warn;
+
+use crate::reader::{RawTelegram, RawTelegramReader};
+use crate::telegram::{Telegram, parse_telegram};
+
+/// Collector of the latencies between the frame arrival and the delivery of the parsed telegram.
+///
+/// Wrap the stream of the received frames with [LatencyProbe::wrap()] and consume the parsed telegrams from the returned
+/// [ProbedStream], the latency of every delivered telegram is recorded in the probe. Clones of the probe share the recorded
+/// latencies, so you can keep one clone to take the snapshots while the stream is consumed elsewhere.
+///
+/// # Example
+/// ```
+/// use futures_util::{FutureExt, StreamExt, stream};
+/// use homey_energy_dongle::bench::LatencyProbe;
+///
+/// let probe = LatencyProbe::new(1000);
+/// let frames = stream::iter([b"/test\r\n\r\n".as_slice(), b"1-0:1.8.1(000123.456*kWh)\r\n!\r\n"]);
+/// let telegrams = probe.wrap(frames).collect::<Vec<_>>().now_or_never().unwrap();
+/// assert_eq!(1, telegrams.len());
+/// let snapshot = probe.snapshot().unwrap();
+/// assert_eq!(1, snapshot.samples);
+/// println!("p99 latency: {:?}", snapshot.p99);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LatencyProbe {
+	capacity: usize,
+	latencies: Arc<Mutex<VecDeque<Duration>>>,
+}
+
+impl LatencyProbe {
+	/// Creates the probe that keeps the last `capacity` latencies.
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity: capacity.max(1),
+			latencies: Arc::new(Mutex::new(VecDeque::new())),
+		}
+	}
+
+	/// Returns the stream of the parsed telegrams extracted from `frames` that records the latencies in this probe.
+	///
+	/// The telegrams that fail to parse are logged and skipped.
+	pub fn wrap<S: Stream<Item: AsRef<[u8]>> + Unpin>(&self, frames: S) -> ProbedStream<S> {
+		ProbedStream {
+			inner: frames,
+			reader: RawTelegramReader::new(),
+			ready_telegrams: VecDeque::new(),
+			probe: self.clone(),
+		}
+	}
+
+	/// Records a latency measured elsewhere, e.g. in your own pipeline.
+	pub fn record(&self, latency: Duration) {
+		let mut latencies = self.latencies.lock().unwrap_or_else(PoisonError::into_inner);
+		if latencies.len() == self.capacity {
+			latencies.pop_front();
+		}
+		latencies.push_back(latency);
+	}
+
+	/// Returns the statistics of the recorded latencies or `None` if nothing was recorded yet.
+	pub fn snapshot(&self) -> Option<LatencySnapshot> {
+		let mut latencies = self
+			.latencies
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.copied()
+			.collect::<Vec<_>>();
+		latencies.sort_unstable();
+		let samples = latencies.len();
+		if samples == 0 {
+			return None;
+		}
+		let percentile = |percentile: usize| latencies.get((samples * percentile).div_ceil(100).saturating_sub(1)).copied();
+		let mean = latencies.iter().sum::<Duration>() / u32::try_from(samples).unwrap_or(u32::MAX);
+		let variance = latencies
+			.iter()
+			.map(|latency| (latency.as_secs_f64() - mean.as_secs_f64()).powi(2))
+			.sum::<f64>()
+			/ samples as f64;
+		Some(LatencySnapshot {
+			samples,
+			min: latencies[0],
+			max: latencies[samples - 1],
+			mean,
+			p50: percentile(50)?,
+			p99: percentile(99)?,
+			jitter: Duration::from_secs_f64(variance.sqrt()),
+		})
+	}
+
+	/// Forgets all recorded latencies.
+	pub fn reset(&self) {
+		self.latencies.lock().unwrap_or_else(PoisonError::into_inner).clear();
+	}
+}
+
+/// Statistics of the latencies recorded by [LatencyProbe].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySnapshot {
+	/// Number of recorded latencies
+	pub samples: usize,
+	pub min: Duration,
+	pub max: Duration,
+	pub mean: Duration,
+	/// Median
+	pub p50: Duration,
+	/// 99th percentile
+	pub p99: Duration,
+	/// Standard deviation
+	pub jitter: Duration,
+}
+
+/// Stream of the parsed telegrams that measures their latency, created by [LatencyProbe::wrap()].
+///
+/// The latency of a telegram is measured from the moment the frame completing it is received from the inner stream to the moment
+/// the parsed telegram is yielded.
+pub struct ProbedStream<S> {
+	inner: S,
+	reader: RawTelegramReader,
+	/// Extracted telegrams with the arrival time of their last frame
+	ready_telegrams: VecDeque<(RawTelegram, Instant)>,
+	probe: LatencyProbe,
+}
+
+impl<S: Stream<Item: AsRef<[u8]>> + Unpin> Stream for ProbedStream<S> {
+	type Item = Telegram;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		loop {
+			while let Some((telegram, arrived)) = self.ready_telegrams.pop_front() {
+				match parse_telegram(&telegram.contents) {
+					Ok(telegram) => {
+						self.probe.record(arrived.elapsed());
+						return Poll::Ready(Some(telegram));
+					}
+					Err(err) => warn!("Skipping the telegram that failed to parse: {err}"),
+				}
+			}
+			let Some(bytes) = ready!(Pin::new(&mut self.inner).poll_next(cx)) else {
+				return Poll::Ready(None);
+			};
+			let arrived = Instant::now();
+			let telegrams = self.reader.feed(bytes.as_ref());
+			self
+				.ready_telegrams
+				.extend(telegrams.into_iter().map(|telegram| (telegram, arrived)));
+		}
+	}
+}
+
+impl<S> fmt::Debug for ProbedStream<S> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ProbedStream")
+			.field("buffered_len", &self.reader.buffered_len())
+			.field("ready_telegrams", &self.ready_telegrams.len())
+			.field("probe", &self.probe)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Result of [measure_throughput()].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throughput {
+	/// Number of processed bytes
+	pub bytes: usize,
+	/// Number of extracted and successfully parsed telegrams
+	pub telegrams: usize,
+	pub elapsed: Duration,
+}
+
+impl Throughput {
+	/// Returns the processed bytes per second.
+	pub fn bytes_per_second(&self) -> f64 {
+		self.bytes as f64 / self.elapsed.as_secs_f64()
+	}
+
+	/// Returns the parsed telegrams per second.
+	pub fn telegrams_per_second(&self) -> f64 {
+		self.telegrams as f64 / self.elapsed.as_secs_f64()
+	}
+}
+
+/// Feeds `corpus` to a [RawTelegramReader] in chunks of `chunk_size` bytes and parses the extracted telegrams.
+///
+/// The corpus is any captured traffic, e.g. concatenated telegrams or the bytes collected with
+/// [RawTelegramStream::with_tap()](crate::reader::RawTelegramStream::with_tap). The chunk size simulates the size of the frames
+/// of your transport.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::bench::measure_throughput;
+///
+/// let corpus = b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n".repeat(1000);
+/// let throughput = measure_throughput(&corpus, 64);
+/// assert_eq!(1000, throughput.telegrams);
+/// println!("{:.0} telegrams/s", throughput.telegrams_per_second());
+/// ```
+pub fn measure_throughput(corpus: &[u8], chunk_size: usize) -> Throughput {
+	let mut reader = RawTelegramReader::with_max_buffered_len(corpus.len().max(1));
+	let start = Instant::now();
+	let telegrams = corpus
+		.chunks(chunk_size.max(1))
+		.flat_map(|chunk| reader.feed(chunk))
+		.filter(|telegram| parse_telegram(&telegram.contents).is_ok())
+		.count();
+	Throughput {
+		bytes: corpus.len(),
+		telegrams,
+		elapsed: start.elapsed(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures_util::{FutureExt, StreamExt, stream};
+
+	use super::*;
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_latency_probe() {
+		let probe = LatencyProbe::new(3);
+		assert_eq!(None, probe.snapshot());
+		let corpus = [
+			with_crc(DSMR5),
+			DSMR3.as_bytes().to_vec(),
+			b"/broken\r\n1-0\r\n!\r\n".to_vec(),
+		]
+		.concat();
+		let frames = stream::iter(corpus.chunks(100).collect::<Vec<_>>());
+		let telegrams = probe.clone().wrap(frames).collect::<Vec<_>>().now_or_never().unwrap();
+		assert_eq!(2, telegrams.len());
+		assert_eq!(2, probe.snapshot().unwrap().samples);
+
+		for millis in [5, 1, 3, 2] {
+			probe.record(Duration::from_millis(millis));
+		}
+		let snapshot = probe.snapshot().unwrap();
+		assert_eq!(3, snapshot.samples);
+		assert_eq!(
+			(Duration::from_millis(1), Duration::from_millis(2), Duration::from_millis(3)),
+			(snapshot.min, snapshot.p50, snapshot.max)
+		);
+		assert_eq!(Duration::from_millis(2), snapshot.mean);
+		assert_eq!(Duration::from_millis(3), snapshot.p99);
+		probe.reset();
+		assert_eq!(None, probe.snapshot());
+	}
+
+	#[test]
+	fn test_measure_throughput() {
+		let corpus = [with_crc(DSMR5), DSMR3.as_bytes().to_vec()].concat().repeat(10);
+		for chunk_size in [0, 1, 64, corpus.len()] {
+			let throughput = measure_throughput(&corpus, chunk_size);
+			assert_eq!(20, throughput.telegrams);
+			assert_eq!(corpus.len(), throughput.bytes);
+		}
+	}
+}
@@ -0,0 +1,308 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use futures_util::Stream;
+use log::{debug, trace, warn};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::clock::SystemClock;
+use crate::discover::{EnergyDongleHostInfo, discover_devices_with_mdns};
+use crate::reader::RawTelegram;
+use crate::reconnect::{ConnectionState, ExponentialBackoff, ReconnectEvent, ReconnectPolicy, reconnect_loop};
+use crate::telegram::Telegram;
+use crate::websocket::{ConnectError, WebsocketEnergyDongle};
+
+type RecvFuture = Pin<Box<dyn Future<Output = (Result<RawTelegram, RecvError>, broadcast::Receiver<RawTelegram>)> + Send>>;
+
+/// The simplest way to read the meter: a single dongle connection that is kept alive in the background.
+///
+/// The client finds the dongle with mDNS, connects to it, reconnects according to the [ReconnectPolicy] when the connection is
+/// lost and parses the received telegrams. Use [DongleManager](crate::manager::DongleManager) to connect to several dongles at
+/// once.
+///
+/// The background task is stopped when the client is dropped. Must be created within the context of a Tokio runtime.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use homey_energy_dongle::prelude::*;
+///
+/// async fn example() {
+///     let client = EnergyDongleClient::connect_any().await.unwrap();
+///     let mut telegrams = client.telegrams();
+///     while let Some(telegram) = telegrams.next().await {
+///         if let Some(net) = telegram.net_metering() {
+///             println!("{} kW, {:?}", net.power_imported, client.state());
+///         }
+///     }
+/// }
+/// ```
+pub struct EnergyDongleClient {
+	info: EnergyDongleHostInfo,
+	state: Arc<Mutex<ConnectionState>>,
+	receiver: broadcast::Receiver<RawTelegram>,
+	task: JoinHandle<()>,
+}
+
+impl EnergyDongleClient {
+	/// Returns the builder to configure the client.
+	pub fn builder() -> EnergyDongleClientBuilder {
+		EnergyDongleClientBuilder {
+			discovery_timeout: Duration::from_secs(5),
+			reconnect_policy: Arc::new(ExponentialBackoff::default()),
+			channel_capacity: 64,
+		}
+	}
+
+	/// Connects to the first dongle found on the network with the default settings, see
+	/// [EnergyDongleClientBuilder::connect_any()].
+	pub async fn connect_any() -> Result<Self, ClientError> {
+		Self::builder().connect_any().await
+	}
+
+	/// Connects to the specified dongle with the default settings, see [EnergyDongleClientBuilder::connect()].
+	pub async fn connect(info: EnergyDongleHostInfo) -> Result<Self, ConnectError> {
+		Self::builder().connect(info).await
+	}
+
+	/// Returns a new stream of the parsed telegrams.
+	///
+	/// Only the telegrams received after this call are yielded, the telegrams that fail to parse are logged and skipped. The stream
+	/// finishes when the reconnect policy gives up. A stream that falls behind by more than the channel capacity loses the oldest
+	/// telegrams.
+	pub fn telegrams(&self) -> TelegramStream {
+		TelegramStream {
+			recv: Box::pin(recv(self.receiver.resubscribe())),
+		}
+	}
+
+	/// Returns the current state of the connection.
+	pub fn state(&self) -> ConnectionState {
+		*self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Returns the host information of the connected dongle.
+	pub fn info(&self) -> &EnergyDongleHostInfo {
+		&self.info
+	}
+}
+
+impl Drop for EnergyDongleClient {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+impl fmt::Debug for EnergyDongleClient {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EnergyDongleClient")
+			.field("info", &self.info)
+			.field("state", &self.state())
+			.finish_non_exhaustive()
+	}
+}
+
+/// Builder for [EnergyDongleClient], created by [EnergyDongleClient::builder()].
+pub struct EnergyDongleClientBuilder {
+	discovery_timeout: Duration,
+	reconnect_policy: Arc<dyn ReconnectPolicy>,
+	channel_capacity: usize,
+}
+
+impl EnergyDongleClientBuilder {
+	/// Maximum duration of the mDNS discovery in [EnergyDongleClientBuilder::connect_any()], 5 seconds by default.
+	pub fn discovery_timeout(mut self, timeout: Duration) -> Self {
+		self.discovery_timeout = timeout;
+		self
+	}
+
+	/// Policy that decides when to reconnect after the connection is lost, [ExponentialBackoff::default()] by default.
+	pub fn reconnect_policy(mut self, policy: impl ReconnectPolicy + 'static) -> Self {
+		self.reconnect_policy = Arc::new(policy);
+		self
+	}
+
+	/// Number of telegrams buffered for the slowest [TelegramStream], 64 by default.
+	pub fn channel_capacity(mut self, capacity: usize) -> Self {
+		self.channel_capacity = capacity;
+		self
+	}
+
+	/// Discovers the dongles on the network and connects to the first one found.
+	///
+	/// Must be called within the context of a Tokio runtime.
+	pub async fn connect_any(self) -> Result<EnergyDongleClient, ClientError> {
+		let info = discover_devices_with_mdns(self.discovery_timeout, 1)
+			.await
+			.map_err(ClientError::Discovery)?
+			.into_iter()
+			.next()
+			.ok_or(ClientError::NotFound)?;
+		debug!("Connecting to the discovered Homey Energy Dongle: {}", info.name);
+		self.connect(info).await.map_err(ClientError::Connect)
+	}
+
+	/// Connects to the dongle with the specified host information, the first connection attempt is not retried.
+	///
+	/// Must be called within the context of a Tokio runtime.
+	pub async fn connect(self, info: EnergyDongleHostInfo) -> Result<EnergyDongleClient, ConnectError> {
+		let dongle = connect(&info).await?;
+		let state = Arc::new(Mutex::new(ConnectionState::Connected));
+		let (sender, receiver) = broadcast::channel(self.channel_capacity.max(1));
+		let task = tokio::spawn(maintain_connection(
+			info.clone(),
+			Arc::clone(&state),
+			sender,
+			self.reconnect_policy,
+			dongle,
+		));
+		Ok(EnergyDongleClient {
+			info,
+			state,
+			receiver,
+			task,
+		})
+	}
+}
+
+impl fmt::Debug for EnergyDongleClientBuilder {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EnergyDongleClientBuilder")
+			.field("discovery_timeout", &self.discovery_timeout)
+			.field("channel_capacity", &self.channel_capacity)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Stream of the parsed telegrams received by [EnergyDongleClient], created by [EnergyDongleClient::telegrams()].
+pub struct TelegramStream {
+	recv: RecvFuture,
+}
+
+impl Stream for TelegramStream {
+	type Item = Telegram;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		loop {
+			let (res, receiver) = ready!(self.recv.as_mut().poll(cx));
+			self.recv = Box::pin(recv(receiver));
+			match res {
+				Ok(telegram) => match telegram.parse() {
+					Ok(telegram) => return Poll::Ready(Some(telegram)),
+					Err(err) => warn!("Skipping the telegram that failed to parse: {err}"),
+				},
+				Err(RecvError::Lagged(count)) => warn!("Telegram stream fell behind, {count} telegrams are lost"),
+				Err(RecvError::Closed) => return Poll::Ready(None),
+			}
+		}
+	}
+}
+
+impl fmt::Debug for TelegramStream {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TelegramStream").finish_non_exhaustive()
+	}
+}
+
+/// Possible error scenarios for [EnergyDongleClientBuilder::connect_any()].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ClientError {
+	/// mDNS discovery failed
+	Discovery(mdns_sd::Error),
+	/// No dongle was found within the discovery timeout
+	NotFound,
+	/// Connection to the found dongle failed
+	Connect(ConnectError),
+}
+
+impl fmt::Display for ClientError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Discovery(err) => write!(f, "Homey Energy Dongle discovery failed: {err}"),
+			Self::NotFound => write!(f, "No Homey Energy Dongle found on the network"),
+			Self::Connect(err) => write!(f, "Connection to Homey Energy Dongle failed: {err}"),
+		}
+	}
+}
+
+impl Error for ClientError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Discovery(err) => Some(err),
+			Self::NotFound => None,
+			Self::Connect(err) => Some(err),
+		}
+	}
+}
+
+async fn recv(
+	mut receiver: broadcast::Receiver<RawTelegram>,
+) -> (Result<RawTelegram, RecvError>, broadcast::Receiver<RawTelegram>) {
+	let res = receiver.recv().await;
+	(res, receiver)
+}
+
+async fn connect(info: &EnergyDongleHostInfo) -> Result<WebsocketEnergyDongle, ConnectError> {
+	WebsocketEnergyDongle::builder_with_addresses(info.socket_addresses(), &info.path)
+		.connect()
+		.await
+}
+
+async fn maintain_connection(
+	info: EnergyDongleHostInfo,
+	state: Arc<Mutex<ConnectionState>>,
+	sender: broadcast::Sender<RawTelegram>,
+	reconnect_policy: Arc<dyn ReconnectPolicy>,
+	dongle: WebsocketEnergyDongle,
+) {
+	let info = &info;
+	let sender = &sender;
+	let set_state = |new_state| *state.lock().unwrap_or_else(PoisonError::into_inner) = new_state;
+	reconnect_loop(
+		&reconnect_policy,
+		&SystemClock,
+		Some(dongle),
+		|| connect(info),
+		|dongle| async move {
+			let mut last_error: Option<Box<dyn Error + Send + Sync>> = None;
+			dongle
+				.dispatch(
+					|telegram| {
+						// sending fails only when there are no streams, which is fine
+						let _ = sender.send(telegram);
+					},
+					|err| last_error = Some(Box::new(err)),
+				)
+				.await;
+			last_error
+		},
+		|event| match event {
+			ReconnectEvent::Connecting { attempt } => {
+				debug!("Reconnecting to Homey Energy Dongle {}, attempt {attempt}", info.name);
+				set_state(ConnectionState::Connecting);
+			}
+			ReconnectEvent::Connected => set_state(ConnectionState::Connected),
+			ReconnectEvent::ConnectFailed(err) => {
+				trace!("Connection to Homey Energy Dongle {} failed: {err}", info.name);
+				set_state(ConnectionState::Disconnected);
+			}
+			ReconnectEvent::Disconnected => {
+				debug!("Connection to Homey Energy Dongle {} is closed", info.name);
+				set_state(ConnectionState::Disconnected);
+			}
+			ReconnectEvent::GaveUp { attempts } => {
+				debug!("Giving up on Homey Energy Dongle {} after {attempts} attempts", info.name);
+				set_state(ConnectionState::GaveUp);
+			}
+		},
+	)
+	.await;
+}
@@ -0,0 +1,125 @@
+//! Source of the time for the time-based components of this crate.
+//!
+//! The components take the moments of the events they track from a [Clock]: the receipt of the telegrams in [TelegramStats],
+//! [DriftMonitor], [SolarTracker] and [Topology], the appends to the [Journal], the status and the connection uptime in
+//! [DongleManager] and [ConnectionPool]. They use [SystemClock] by default, pass a different one to their `with_clock()`,
+//! `open_with_clock()`, `build_with_clock()` or `clock()` method, e.g. a [MockClock] to control the time in the tests.
+//!
+//! [TelegramStats]: crate::stats::TelegramStats
+//! [DriftMonitor]: crate::drift::DriftMonitor
+//! [SolarTracker]: crate::solar::SolarTracker
+//! [Topology]: crate::topology::Topology
+//! [Journal]: crate::journal::Journal
+//! [DongleManager]: crate::manager::DongleManager
+//! [ConnectionPool]: crate::pool::ConnectionPool
+
+#[cfg(feature = "test-util")]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "test-util")]
+use std::time::Duration;
+use std::time::{Instant, SystemTime};
+
+/// Source of the current time for the time-based components of this crate.
+///
+/// The default implementation is [SystemClock]. With the `test-util` feature enabled you can use [MockClock] to control the
+/// passage of time in your tests.
+pub trait Clock {
+	/// Returns the current monotonic time.
+	fn now(&self) -> Instant;
+
+	/// Returns the current wall clock time.
+	fn system_time(&self) -> SystemTime;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+	fn now(&self) -> Instant {
+		(**self).now()
+	}
+
+	fn system_time(&self) -> SystemTime {
+		(**self).system_time()
+	}
+}
+
+/// [Clock] implementation that returns the actual time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+
+	fn system_time(&self) -> SystemTime {
+		SystemTime::now()
+	}
+}
+
+/// [Clock] implementation that only moves forward when explicitly told to.
+///
+/// Clones of a [MockClock] share the same time, so you can pass one clone into the component under test and keep another one to
+/// advance the time.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+///
+/// use homey_energy_dongle::clock::{Clock, MockClock};
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(5));
+/// assert_eq!(Duration::from_secs(5), clock.now() - start);
+/// ```
+#[cfg(feature = "test-util")]
+#[derive(Debug, Clone)]
+pub struct MockClock {
+	start_instant: Instant,
+	start_system_time: SystemTime,
+	elapsed: Arc<Mutex<Duration>>,
+}
+
+#[cfg(feature = "test-util")]
+impl MockClock {
+	/// Creates a new [MockClock] instance starting at the current time.
+	pub fn new() -> Self {
+		Self::with_system_time(SystemTime::now())
+	}
+
+	/// Creates a new [MockClock] instance with the wall clock time starting at `system_time`.
+	pub fn with_system_time(system_time: SystemTime) -> Self {
+		Self {
+			start_instant: Instant::now(),
+			start_system_time: system_time,
+			elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+		}
+	}
+
+	/// Moves the time forward by `duration`.
+	pub fn advance(&self, duration: Duration) {
+		*self.elapsed.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+	}
+
+	/// Returns the time elapsed since the creation of the clock.
+	pub fn elapsed(&self) -> Duration {
+		*self.elapsed.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+#[cfg(feature = "test-util")]
+impl Default for MockClock {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "test-util")]
+impl Clock for MockClock {
+	fn now(&self) -> Instant {
+		self.start_instant + self.elapsed()
+	}
+
+	fn system_time(&self) -> SystemTime {
+		self.start_system_time + self.elapsed()
+	}
+}
@@ -0,0 +1,497 @@
+use std::fmt;
+
+use crate::telegram::{Obis, Telegram, Timestamp, VERSION};
+
+/// Check the telegram against the DSMR 5.0.2 P1 companion standard.
+///
+/// The check covers the presence of the mandatory data objects, the format and units of the values, the order of the data
+/// objects and the presence of the CRC. Data objects that are not described by the standard (e.g., manufacturer or
+/// country-specific ones) are ignored.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::conformance::{ConformanceIssue, check_dsmr5};
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::telegram::Obis;
+///
+/// let telegram = parse_telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n").unwrap();
+/// let report = check_dsmr5(&telegram);
+/// assert!(!report.is_conformant());
+/// assert!(report.issues.contains(&ConformanceIssue::MissingObject { obis: Obis::new(1, 3, 0, 2, 8) }));
+/// ```
+pub fn check_dsmr5(telegram: &Telegram) -> ConformanceReport {
+	let mut issues = vec![];
+
+	if telegram.crc.is_none() {
+		issues.push(ConformanceIssue::MissingCrc);
+	}
+	match telegram.value(VERSION) {
+		Some("50") | None => {}
+		Some(version) => issues.push(ConformanceIssue::UnsupportedVersion {
+			version: version.to_string(),
+		}),
+	}
+
+	for spec in ELECTRICITY_OBJECTS.iter().filter(|spec| spec.mandatory) {
+		if telegram.get(spec.obis).is_none() {
+			issues.push(ConformanceIssue::MissingObject { obis: spec.obis });
+		}
+	}
+
+	let mut seen = vec![];
+	let mut last_position: Option<(usize, Obis)> = None;
+	for object in &telegram.objects {
+		let Some((position, spec)) = find_spec(object.obis) else {
+			continue;
+		};
+		if seen.contains(&object.obis) {
+			issues.push(ConformanceIssue::DuplicateObject { obis: object.obis });
+			continue;
+		}
+		seen.push(object.obis);
+		if let Some((last_position, last_obis)) = last_position {
+			if position < last_position {
+				issues.push(ConformanceIssue::OutOfOrder {
+					obis: object.obis,
+					after: last_obis,
+				});
+			}
+		}
+		if last_position.is_none_or(|(last_position, _)| position > last_position) {
+			last_position = Some((position, object.obis));
+		}
+		check_values(object.obis, &object.values, spec.values, &mut issues);
+	}
+
+	for channel in 1..=4 {
+		let device_type = Obis::new(0, channel, 24, 1, 0);
+		if telegram.get(device_type).is_some() {
+			for obis in [Obis::new(0, channel, 96, 1, 0), Obis::new(0, channel, 24, 2, 1)] {
+				if telegram.get(obis).is_none() {
+					issues.push(ConformanceIssue::MissingObject { obis });
+				}
+			}
+		}
+	}
+
+	ConformanceReport { issues }
+}
+
+/// Result of the [check_dsmr5()] call.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConformanceReport {
+	/// All found violations of the standard in the order of their discovery
+	pub issues: Vec<ConformanceIssue>,
+}
+
+impl ConformanceReport {
+	/// Returns `true` if no violations of the standard were found.
+	pub fn is_conformant(&self) -> bool {
+		self.issues.is_empty()
+	}
+}
+
+impl fmt::Display for ConformanceReport {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.issues.is_empty() {
+			return write!(f, "Telegram conforms to DSMR 5.0.2");
+		}
+		write!(f, "Telegram doesn't conform to DSMR 5.0.2:")?;
+		for issue in &self.issues {
+			write!(f, "\n- {issue}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Single violation of the DSMR 5.0.2 standard found by [check_dsmr5()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConformanceIssue {
+	/// Telegram footer doesn't contain a CRC
+	MissingCrc,
+	/// Version information object (1-3:0.2.8) contains a version other than "50"
+	UnsupportedVersion { version: String },
+	/// Mandatory data object is not present
+	MissingObject { obis: Obis },
+	/// Data object is present more than once
+	DuplicateObject { obis: Obis },
+	/// Data object is not in the order specified by the standard, it comes after `after` while it should come before it
+	OutOfOrder { obis: Obis, after: Obis },
+	/// Data object has a wrong number of values
+	InvalidValueCount { obis: Obis, count: usize, expected: usize },
+	/// Value at `index` of the data object doesn't have the format specified by the standard, `expected` is the format in the
+	/// notation of the standard, e.g. "F9(3,3) kWh"
+	InvalidValue {
+		obis: Obis,
+		index: usize,
+		value: String,
+		expected: &'static str,
+	},
+}
+
+impl fmt::Display for ConformanceIssue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::MissingCrc => write!(f, "Missing CRC"),
+			Self::UnsupportedVersion { version } => write!(f, "Unsupported version: {version}"),
+			Self::MissingObject { obis } => write!(f, "Missing mandatory object: {obis}"),
+			Self::DuplicateObject { obis } => write!(f, "Duplicate object: {obis}"),
+			Self::OutOfOrder { obis, after } => write!(f, "Object {obis} must come before {after}"),
+			Self::InvalidValueCount { obis, count, expected } => {
+				write!(f, "Object {obis} has {count} values, expected: {expected}")
+			}
+			Self::InvalidValue {
+				obis,
+				index,
+				value,
+				expected,
+			} => write!(
+				f,
+				"Value {index} of object {obis} is \"{value}\", expected format: {expected}"
+			),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+	/// Fixed-point decimal with the total number of digits, number of decimals and the unit
+	Decimal {
+		digits: usize,
+		decimals: usize,
+		unit: Option<&'static str>,
+		notation: &'static str,
+	},
+	/// String of exactly `len` digits
+	Digits { len: usize, notation: &'static str },
+	/// Hex-encoded string of up to `max_len` hex digits
+	Octets { max_len: usize, notation: &'static str },
+	/// Timestamp in the "YYMMDDhhmmssX" format
+	Timestamp,
+	/// Gas volume, either F8(2,2) or F8(3,3)
+	Volume,
+	/// Power failure event log, see [check_power_failure_log]
+	PowerFailureLog,
+}
+
+impl Format {
+	fn notation(&self) -> &'static str {
+		match self {
+			Self::Decimal { notation, .. } | Self::Digits { notation, .. } | Self::Octets { notation, .. } => notation,
+			Self::Timestamp => "TST",
+			Self::Volume => "F8(2,2)/F8(3,3) m3",
+			Self::PowerFailureLog => "Buffer",
+		}
+	}
+
+	fn matches(&self, value: &str) -> bool {
+		match *self {
+			Self::Decimal {
+				digits, decimals, unit, ..
+			} => is_decimal(value, digits, decimals, unit),
+			Self::Digits { len, .. } => value.len() == len && value.bytes().all(|b| b.is_ascii_digit()),
+			Self::Octets { max_len, .. } => {
+				value.len() <= max_len && value.len() % 2 == 0 && value.bytes().all(|b| b.is_ascii_hexdigit())
+			}
+			Self::Timestamp => value.parse::<Timestamp>().is_ok_and(|timestamp| timestamp.dst.is_some()),
+			Self::Volume => is_decimal(value, 8, 2, Some("m3")) || is_decimal(value, 8, 3, Some("m3")),
+			Self::PowerFailureLog => true,
+		}
+	}
+}
+
+fn is_decimal(value: &str, digits: usize, decimals: usize, unit: Option<&str>) -> bool {
+	let number = match unit {
+		Some(unit) => match value.split_once('*') {
+			Some((number, value_unit)) if value_unit == unit => number,
+			_ => return false,
+		},
+		None => value,
+	};
+	let (integer, fraction) = if decimals == 0 {
+		(number, "")
+	} else {
+		match number.split_once('.') {
+			Some(parts) => parts,
+			None => return false,
+		}
+	};
+	integer.len() == digits - decimals
+		&& fraction.len() == decimals
+		&& integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+}
+
+const fn decimal(digits: usize, decimals: usize, unit: &'static str, notation: &'static str) -> Format {
+	Format::Decimal {
+		digits,
+		decimals,
+		unit: Some(unit),
+		notation,
+	}
+}
+
+const ENERGY: &[Format] = &[decimal(9, 3, "kWh", "F9(3,3) kWh")];
+const POWER: &[Format] = &[decimal(5, 3, "kW", "F5(3,3) kW")];
+const COUNTER: &[Format] = &[Format::Decimal {
+	digits: 5,
+	decimals: 0,
+	unit: None,
+	notation: "F5(0,0)",
+}];
+const VOLTAGE: &[Format] = &[decimal(4, 1, "V", "F4(1,1) V")];
+const CURRENT: &[Format] = &[decimal(3, 0, "A", "F3(0,0) A")];
+
+struct ObjectSpec {
+	obis: Obis,
+	mandatory: bool,
+	values: &'static [Format],
+}
+
+const fn spec(obis: Obis, mandatory: bool, values: &'static [Format]) -> ObjectSpec {
+	ObjectSpec { obis, mandatory, values }
+}
+
+/// Electricity data objects in the order specified by the standard
+const ELECTRICITY_OBJECTS: &[ObjectSpec] = &[
+	spec(VERSION, true, &[Format::Digits { len: 2, notation: "S2" }]),
+	spec(Obis::new(0, 0, 1, 0, 0), true, &[Format::Timestamp]),
+	spec(
+		Obis::new(0, 0, 96, 1, 1),
+		true,
+		&[Format::Octets {
+			max_len: 96,
+			notation: "Sn (n=0..96)",
+		}],
+	),
+	spec(Obis::new(1, 0, 1, 8, 1), true, ENERGY),
+	spec(Obis::new(1, 0, 1, 8, 2), true, ENERGY),
+	spec(Obis::new(1, 0, 2, 8, 1), true, ENERGY),
+	spec(Obis::new(1, 0, 2, 8, 2), true, ENERGY),
+	spec(Obis::new(0, 0, 96, 14, 0), true, &[Format::Digits { len: 4, notation: "S4" }]),
+	spec(Obis::new(1, 0, 1, 7, 0), true, POWER),
+	spec(Obis::new(1, 0, 2, 7, 0), true, POWER),
+	spec(Obis::new(0, 0, 96, 7, 21), true, COUNTER),
+	spec(Obis::new(0, 0, 96, 7, 9), true, COUNTER),
+	spec(Obis::new(1, 0, 99, 97, 0), true, &[Format::PowerFailureLog]),
+	spec(Obis::new(1, 0, 32, 32, 0), true, COUNTER),
+	spec(Obis::new(1, 0, 52, 32, 0), false, COUNTER),
+	spec(Obis::new(1, 0, 72, 32, 0), false, COUNTER),
+	spec(Obis::new(1, 0, 32, 36, 0), true, COUNTER),
+	spec(Obis::new(1, 0, 52, 36, 0), false, COUNTER),
+	spec(Obis::new(1, 0, 72, 36, 0), false, COUNTER),
+	spec(
+		Obis::new(0, 0, 96, 13, 0),
+		true,
+		&[Format::Octets {
+			max_len: 2048,
+			notation: "Sn (n=0..2048)",
+		}],
+	),
+	spec(Obis::new(1, 0, 32, 7, 0), true, VOLTAGE),
+	spec(Obis::new(1, 0, 52, 7, 0), false, VOLTAGE),
+	spec(Obis::new(1, 0, 72, 7, 0), false, VOLTAGE),
+	spec(Obis::new(1, 0, 31, 7, 0), true, CURRENT),
+	spec(Obis::new(1, 0, 51, 7, 0), false, CURRENT),
+	spec(Obis::new(1, 0, 71, 7, 0), false, CURRENT),
+	spec(Obis::new(1, 0, 21, 7, 0), true, POWER),
+	spec(Obis::new(1, 0, 41, 7, 0), false, POWER),
+	spec(Obis::new(1, 0, 61, 7, 0), false, POWER),
+	spec(Obis::new(1, 0, 22, 7, 0), true, POWER),
+	spec(Obis::new(1, 0, 42, 7, 0), false, POWER),
+	spec(Obis::new(1, 0, 62, 7, 0), false, POWER),
+];
+
+/// M-Bus data objects for channel "n", in the order specified by the standard
+const MBUS_OBJECTS: &[ObjectSpec] = &[
+	spec(
+		Obis::new(0, 0, 24, 1, 0),
+		true,
+		&[Format::Decimal {
+			digits: 3,
+			decimals: 0,
+			unit: None,
+			notation: "F3(0,0)",
+		}],
+	),
+	spec(
+		Obis::new(0, 0, 96, 1, 0),
+		true,
+		&[Format::Octets {
+			max_len: 96,
+			notation: "Sn (n=0..96)",
+		}],
+	),
+	spec(Obis::new(0, 0, 24, 2, 1), true, &[Format::Timestamp, Format::Volume]),
+];
+
+/// Returns the position of the object in the order specified by the standard and its specification
+fn find_spec(obis: Obis) -> Option<(usize, &'static ObjectSpec)> {
+	if let Some(position) = ELECTRICITY_OBJECTS.iter().position(|spec| spec.obis == obis) {
+		return Some((position, &ELECTRICITY_OBJECTS[position]));
+	}
+	if obis.a == 0 && (1..=4).contains(&obis.b) {
+		let channel_obis = Obis { b: 0, ..obis };
+		if let Some(index) = MBUS_OBJECTS.iter().position(|spec| spec.obis == channel_obis) {
+			let position = ELECTRICITY_OBJECTS.len() + usize::from(obis.b - 1) * MBUS_OBJECTS.len() + index;
+			return Some((position, &MBUS_OBJECTS[index]));
+		}
+	}
+	None
+}
+
+fn check_values(obis: Obis, values: &[String], formats: &[Format], issues: &mut Vec<ConformanceIssue>) {
+	if let [Format::PowerFailureLog] = formats {
+		check_power_failure_log(obis, values, issues);
+		return;
+	}
+	if values.len() != formats.len() {
+		issues.push(ConformanceIssue::InvalidValueCount {
+			obis,
+			count: values.len(),
+			expected: formats.len(),
+		});
+		return;
+	}
+	for (index, (value, format)) in values.iter().zip(formats).enumerate() {
+		if !format.matches(value) {
+			issues.push(ConformanceIssue::InvalidValue {
+				obis,
+				index,
+				value: value.clone(),
+				expected: format.notation(),
+			});
+		}
+	}
+}
+
+/// Power failure event log has the following format: "(count)(0-0:96.7.19)(end timestamp)(duration)..." with the timestamp and
+/// duration pair repeated `count` times
+fn check_power_failure_log(obis: Obis, values: &[String], issues: &mut Vec<ConformanceIssue>) {
+	const DURATION: Format = decimal(10, 0, "s", "F10(0,0) s");
+
+	let mut invalid_value = |index: usize, value: &str, expected: &'static str| {
+		issues.push(ConformanceIssue::InvalidValue {
+			obis,
+			index,
+			value: value.to_string(),
+			expected,
+		})
+	};
+	let [count, log_obis, events @ ..] = values else {
+		issues.push(ConformanceIssue::InvalidValueCount {
+			obis,
+			count: values.len(),
+			expected: 2,
+		});
+		return;
+	};
+	let count = count
+		.parse::<usize>()
+		.ok()
+		.filter(|_| count.bytes().all(|b| b.is_ascii_digit()));
+	if count.is_none() {
+		invalid_value(0, &values[0], "Integer");
+	}
+	if log_obis != "0-0:96.7.19" {
+		invalid_value(1, log_obis, "0-0:96.7.19");
+	}
+	for (index, value) in events.iter().enumerate() {
+		let format = if index % 2 == 0 {
+			Format::Timestamp
+		} else {
+			DURATION
+		};
+		if !format.matches(value) {
+			invalid_value(index + 2, value, format.notation());
+		}
+	}
+	if let Some(count) = count {
+		if events.len() % 2 != 0 || events.len() / 2 != count {
+			issues.push(ConformanceIssue::InvalidValueCount {
+				obis,
+				count: values.len(),
+				expected: count.saturating_mul(2).saturating_add(2),
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ConformanceIssue, check_dsmr5};
+	use crate::parse_telegram;
+	use crate::telegram::Obis;
+	use crate::test_telegrams::{DSMR5, with_crc};
+
+	#[test]
+	fn test_check_dsmr5() {
+		let telegram = parse_telegram(&with_crc(DSMR5)).unwrap();
+		let report = check_dsmr5(&telegram);
+		assert_eq!(Vec::<ConformanceIssue>::new(), report.issues);
+		assert!(report.is_conformant());
+
+		let telegram = DSMR5
+			.replace("1-3:0.2.8(50)", "1-3:0.2.8(42)")
+			.replace("1-0:1.8.2(123456.789*kWh)", "1-0:1.8.2(123456.789*Wh)")
+			.replace("1-0:31.7.0(001*A)\r\n", "")
+			.replace("1-0:32.7.0(220.1*V)", "1-0:32.7.0(220.1*V)(1)")
+			.replace("(101208151004W)(0000000301*s)", "(101208151004W)")
+			.replace(
+				"0-1:24.2.1(101209112500W)(12785.123*m3)",
+				"0-1:24.2.1(101209112500W)(1278.123*m3)",
+			)
+			.replace("1-0:2.7.0(00.000*kW)\r\n", "")
+			.replace("1-0:22.7.0(04.444*kW)", "1-0:22.7.0(04.444*kW)\r\n1-0:2.7.0(00.000*kW)")
+			.replace("0-0:96.14.0(0002)", "0-0:96.14.0(0002)\r\n0-0:96.14.0(0001)");
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		let report = check_dsmr5(&telegram);
+		assert!(!report.is_conformant());
+		assert_eq!(
+			vec![
+				ConformanceIssue::UnsupportedVersion {
+					version: "42".to_string()
+				},
+				ConformanceIssue::MissingObject {
+					obis: Obis::new(1, 0, 31, 7, 0)
+				},
+				ConformanceIssue::InvalidValue {
+					obis: Obis::new(1, 0, 1, 8, 2),
+					index: 0,
+					value: "123456.789*Wh".to_string(),
+					expected: "F9(3,3) kWh"
+				},
+				ConformanceIssue::DuplicateObject {
+					obis: Obis::new(0, 0, 96, 14, 0)
+				},
+				ConformanceIssue::InvalidValueCount {
+					obis: Obis::new(1, 0, 99, 97, 0),
+					count: 5,
+					expected: 6
+				},
+				ConformanceIssue::InvalidValueCount {
+					obis: Obis::new(1, 0, 32, 7, 0),
+					count: 2,
+					expected: 1
+				},
+				ConformanceIssue::OutOfOrder {
+					obis: Obis::new(1, 0, 2, 7, 0),
+					after: Obis::new(1, 0, 22, 7, 0)
+				},
+				ConformanceIssue::InvalidValue {
+					obis: Obis::new(0, 1, 24, 2, 1),
+					index: 1,
+					value: "1278.123*m3".to_string(),
+					expected: "F8(2,2)/F8(3,3) m3"
+				},
+			],
+			report.issues
+		);
+
+		let telegram = parse_telegram(b"/test\r\n\r\n0-1:24.1.0(003)\r\n!\r\n").unwrap();
+		let report = check_dsmr5(&telegram);
+		assert_eq!(Some(&ConformanceIssue::MissingCrc), report.issues.first());
+		assert!(report.issues.contains(&ConformanceIssue::MissingObject {
+			obis: Obis::new(0, 1, 24, 2, 1)
+		}));
+	}
+}
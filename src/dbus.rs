@@ -0,0 +1,238 @@
+use std::fmt;
+
+use log::trace;
+use zbus::connection::Builder;
+use zbus::object_server::{InterfaceRef, SignalEmitter};
+use zbus::{Connection, interface};
+
+use crate::reader::{CrcCheck, RawTelegram};
+use crate::telegram::{Tariff, Telegram};
+
+/// Well-known bus name requested by [DbusPublisher::session()] and [DbusPublisher::system()].
+pub const BUS_NAME: &str = "app.homey.EnergyDongle";
+/// Path of the object that exposes the `app.homey.EnergyDongle1` interface.
+pub const OBJECT_PATH: &str = "/app/homey/EnergyDongle";
+
+/// Publisher of the meter values on the D-Bus session or system bus.
+///
+/// The object at [OBJECT_PATH] implements the `app.homey.EnergyDongle1` interface with the following read-only properties, the
+/// change of every property is announced with the standard `PropertiesChanged` signal:
+/// * `PowerImported`, `PowerExported` (double): current power delivered to and by the client in kW
+/// * `EnergyImported`, `EnergyExported` (double): total energy delivered to and by the client over all tariffs in kWh
+/// * `Gas` (double): total gas consumption in m³, NaN if there is no gas meter
+/// * `Tariff` (byte): current tariff, 1 or 2, 0 if unknown
+///
+/// On every published telegram the `Telegram` signal is emitted with the arguments:
+/// * `contents` (string): contents of the telegram
+/// * `crc_valid` (boolean): whether the telegram CRC is valid, `false` also for the telegrams without CRC
+///
+/// # Example
+/// ```no_run
+/// use futures_util::{Stream, StreamExt};
+/// use homey_energy_dongle::dbus::DbusPublisher;
+/// use homey_energy_dongle::reader::RawTelegram;
+///
+/// async fn example(mut telegrams: impl Stream<Item = RawTelegram> + Unpin) {
+///     let publisher = DbusPublisher::session().await.unwrap();
+///     while let Some(telegram) = telegrams.next().await {
+///         publisher.publish(&telegram).await.unwrap();
+///     }
+/// }
+/// ```
+///
+/// The values can then be read with, e.g., `busctl --user get-property app.homey.EnergyDongle /app/homey/EnergyDongle
+/// app.homey.EnergyDongle1 PowerImported`.
+#[derive(Clone)]
+pub struct DbusPublisher {
+	connection: Connection,
+	meter: InterfaceRef<Meter>,
+}
+
+impl DbusPublisher {
+	/// Connects to the session bus and requests the [BUS_NAME].
+	pub async fn session() -> zbus::Result<Self> {
+		Self::with_builder(Builder::session()?.name(BUS_NAME)?).await
+	}
+
+	/// Connects to the system bus and requests the [BUS_NAME].
+	///
+	/// Owning a name on the system bus usually requires a policy file in `/etc/dbus-1/system.d`.
+	pub async fn system() -> zbus::Result<Self> {
+		Self::with_builder(Builder::system()?.name(BUS_NAME)?).await
+	}
+
+	/// Builds the connection from `builder` and serves the meter object on it, use it to request a different bus name or to
+	/// connect to a custom bus address.
+	pub async fn with_builder(builder: Builder<'_>) -> zbus::Result<Self> {
+		let connection = builder.serve_at(OBJECT_PATH, Meter::default())?.build().await?;
+		let meter = connection.object_server().interface(OBJECT_PATH).await?;
+		Ok(Self { connection, meter })
+	}
+
+	/// Returns the underlying D-Bus connection.
+	pub fn connection(&self) -> &Connection {
+		&self.connection
+	}
+
+	/// Updates the published values from `telegram` and emits the `Telegram` signal.
+	///
+	/// The telegrams that fail to parse don't update the values, but the signal is still emitted.
+	pub async fn publish(&self, telegram: &RawTelegram) -> zbus::Result<()> {
+		let emitter = self.meter.signal_emitter();
+		if let Ok(parsed) = telegram.parse() {
+			let mut meter = self.meter.get_mut().await;
+			let previous = meter.values;
+			meter.values = MeterValues::from_telegram(&parsed, previous);
+			let current = meter.values;
+			if previous.power_imported != current.power_imported {
+				meter.power_imported_changed(emitter).await?;
+			}
+			if previous.power_exported != current.power_exported {
+				meter.power_exported_changed(emitter).await?;
+			}
+			if previous.energy_imported != current.energy_imported {
+				meter.energy_imported_changed(emitter).await?;
+			}
+			if previous.energy_exported != current.energy_exported {
+				meter.energy_exported_changed(emitter).await?;
+			}
+			// NaN != NaN, so compare the bits to not announce the missing gas meter on every telegram
+			if previous.gas.to_bits() != current.gas.to_bits() {
+				meter.gas_changed(emitter).await?;
+			}
+			if previous.tariff != current.tariff {
+				meter.tariff_changed(emitter).await?;
+			}
+		} else {
+			trace!("Publishing the unparseable telegram without updating the values");
+		}
+		let contents = String::from_utf8_lossy(&telegram.contents);
+		Meter::telegram(emitter, &contents, telegram.check_crc() == CrcCheck::Valid).await
+	}
+}
+
+impl fmt::Debug for DbusPublisher {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DbusPublisher")
+			.field("connection", &self.connection)
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MeterValues {
+	power_imported: f64,
+	power_exported: f64,
+	energy_imported: f64,
+	energy_exported: f64,
+	gas: f64,
+	tariff: u8,
+}
+
+impl Default for MeterValues {
+	fn default() -> Self {
+		Self {
+			power_imported: 0.,
+			power_exported: 0.,
+			energy_imported: 0.,
+			energy_exported: 0.,
+			gas: f64::NAN,
+			tariff: 0,
+		}
+	}
+}
+
+impl MeterValues {
+	/// Returns the values from `telegram`, the values missing in the telegram are taken from `previous`.
+	fn from_telegram(telegram: &Telegram, previous: Self) -> Self {
+		let mut out = previous;
+		if let Some(net) = telegram.net_metering() {
+			out.power_imported = net.power_imported;
+			out.power_exported = net.power_exported;
+			out.energy_imported = net.imported;
+			out.energy_exported = net.exported;
+		}
+		if let Some(gas) = telegram.gas() {
+			out.gas = gas.volume;
+		}
+		if let Some(tariff) = telegram.tariff() {
+			out.tariff = match tariff {
+				Tariff::Tariff1 => 1,
+				Tariff::Tariff2 => 2,
+			};
+		}
+		out
+	}
+}
+
+#[derive(Debug, Default)]
+struct Meter {
+	values: MeterValues,
+}
+
+#[interface(name = "app.homey.EnergyDongle1")]
+impl Meter {
+	#[zbus(property)]
+	fn power_imported(&self) -> f64 {
+		self.values.power_imported
+	}
+
+	#[zbus(property)]
+	fn power_exported(&self) -> f64 {
+		self.values.power_exported
+	}
+
+	#[zbus(property)]
+	fn energy_imported(&self) -> f64 {
+		self.values.energy_imported
+	}
+
+	#[zbus(property)]
+	fn energy_exported(&self) -> f64 {
+		self.values.energy_exported
+	}
+
+	#[zbus(property)]
+	fn gas(&self) -> f64 {
+		self.values.gas
+	}
+
+	#[zbus(property)]
+	fn tariff(&self) -> u8 {
+		self.values.tariff
+	}
+
+	#[zbus(signal)]
+	async fn telegram(emitter: &SignalEmitter<'_>, contents: &str, crc_valid: bool) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parse_telegram;
+	use crate::test_telegrams::{DSMR5, with_crc};
+
+	#[test]
+	fn test_meter_values() {
+		let values = MeterValues::from_telegram(&parse_telegram(&with_crc(DSMR5)).unwrap(), MeterValues::default());
+		assert_eq!(
+			MeterValues {
+				power_imported: 1.193,
+				power_exported: 0.,
+				energy_imported: 246913.578,
+				energy_exported: 246913.578,
+				gas: 12785.123,
+				tariff: 2,
+			},
+			values
+		);
+
+		// the missing objects keep the previous values
+		let telegram = DSMR5.replace("0-1:24.2.1(101209112500W)(12785.123*m3)\r\n", "");
+		let telegram = telegram.replace("0-0:96.14.0(0002)\r\n", "");
+		let values = MeterValues::from_telegram(&parse_telegram(&with_crc(&telegram)).unwrap(), values);
+		assert_eq!(12785.123, values.gas);
+		assert_eq!(2, values.tariff);
+		assert!(MeterValues::default().gas.is_nan());
+	}
+}
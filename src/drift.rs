@@ -0,0 +1,219 @@
+//! Monitoring of the meter clock against the clock of the host.
+//!
+//! The meters are supposed to keep their clock synchronized, but some of them drift away by minutes over months. If you bill
+//! the consumption per interval using the telegram timestamps, a drifting meter clock assigns the energy to the wrong intervals.
+//! [DriftMonitor] measures how far the meter clock is from the host clock and how fast the difference grows.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::clock::{Clock, SystemClock};
+use crate::telegram::Telegram;
+
+type AlertCallback = Box<dyn FnMut(ClockDrift) + Send>;
+
+/// Tracks the offset of the meter clock from the host clock and the rate of its change.
+///
+/// Call [DriftMonitor::record()] for every received telegram and [DriftMonitor::drift()] to get the current estimate. The offset
+/// is the meter timestamp minus the time of receipt, it's positive when the meter clock is ahead. The meter timestamps have a
+/// resolution of one second and the telegrams arrive with a delay, so the estimate is fitted over all telegrams received within
+/// the `window` to smooth out the noise.
+///
+/// The meter timestamps are in the local time of the meter, they are converted to UTC using the offset set with
+/// [DriftMonitor::with_utc_offset()], which is the Central European Time by default.
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// use homey_energy_dongle::drift::DriftMonitor;
+/// use homey_energy_dongle::telegram::parse_telegram;
+///
+/// let mut monitor = DriftMonitor::new(Duration::from_secs(3600)).with_utc_offset(0);
+/// let telegram = parse_telegram(b"/test\r\n\r\n0-0:1.0.0(240101000010W)\r\n!\r\n").unwrap();
+/// monitor.record_at(&telegram, UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+/// assert_eq!(10., monitor.drift().unwrap().offset);
+/// ```
+pub struct DriftMonitor<C = SystemClock> {
+	clock: C,
+	window: Duration,
+	utc_offset: i32,
+	/// Time of receipt and the offset in seconds, both relative to the UNIX epoch
+	samples: VecDeque<(f64, f64)>,
+	alert: Option<(Duration, AlertCallback)>,
+	alerting: bool,
+}
+
+impl DriftMonitor {
+	/// Creates a new [DriftMonitor] instance that estimates the drift over the last `window` of time.
+	pub fn new(window: Duration) -> Self {
+		Self::with_clock(window, SystemClock)
+	}
+}
+
+impl<C: Clock> DriftMonitor<C> {
+	/// Creates a new [DriftMonitor] instance that uses `clock` as a source of time, see [clock](crate::clock).
+	pub fn with_clock(window: Duration, clock: C) -> Self {
+		Self {
+			clock,
+			window,
+			utc_offset: 3600,
+			samples: VecDeque::new(),
+			alert: None,
+			alerting: false,
+		}
+	}
+
+	/// Offset of the meter standard (winter) time from UTC in seconds, 3600 by default, see [Timestamp::to_unix_time()].
+	///
+	/// [Timestamp::to_unix_time()]: crate::telegram::Timestamp::to_unix_time
+	pub fn with_utc_offset(mut self, utc_offset: i32) -> Self {
+		self.utc_offset = utc_offset;
+		self
+	}
+
+	/// Calls `on_alert` when the absolute offset exceeds `threshold`, replaces the previously attached callback.
+	///
+	/// The callback is called once when the offset crosses the threshold and again only after the offset went back within the
+	/// threshold and crossed it once more.
+	pub fn with_alert(mut self, threshold: Duration, on_alert: impl FnMut(ClockDrift) + Send + 'static) -> Self {
+		self.alert = Some((threshold, Box::new(on_alert)));
+		self
+	}
+
+	/// Record the receipt of `telegram` at the current moment.
+	pub fn record(&mut self, telegram: &Telegram) {
+		self.record_at(telegram, self.clock.system_time());
+	}
+
+	/// Record the receipt of `telegram` at the specified moment, the telegrams without the timestamp are ignored.
+	///
+	/// `at` is expected to be non-decreasing between the calls.
+	pub fn record_at(&mut self, telegram: &Telegram, at: SystemTime) {
+		let Some(timestamp) = telegram.timestamp() else {
+			return;
+		};
+		let received = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+		let offset = timestamp.to_unix_time(self.utc_offset) as f64 - received;
+		self.samples.push_back((received, offset));
+		let window_start = received - self.window.as_secs_f64();
+		while self.samples.front().is_some_and(|(received, _)| *received < window_start) {
+			self.samples.pop_front();
+		}
+
+		let Some((threshold, on_alert)) = &mut self.alert else {
+			return;
+		};
+		let Some(drift) = estimate(&self.samples) else {
+			return;
+		};
+		let exceeded = drift.offset.abs() > threshold.as_secs_f64();
+		if exceeded && !self.alerting {
+			on_alert(drift);
+		}
+		self.alerting = exceeded;
+	}
+
+	/// Returns the current estimate of the drift or `None` if no telegrams with the timestamp were recorded.
+	pub fn drift(&self) -> Option<ClockDrift> {
+		estimate(&self.samples)
+	}
+}
+
+impl<C: fmt::Debug> fmt::Debug for DriftMonitor<C> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DriftMonitor")
+			.field("clock", &self.clock)
+			.field("window", &self.window)
+			.field("utc_offset", &self.utc_offset)
+			.field("samples", &self.samples.len())
+			.field("alert_threshold", &self.alert.as_ref().map(|(threshold, _)| threshold))
+			.finish()
+	}
+}
+
+/// Estimate of the meter clock drift produced by [DriftMonitor].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockDrift {
+	/// Offset of the meter clock from the host clock at the last telegram in seconds, positive if the meter clock is ahead
+	pub offset: f64,
+	/// Change of the offset in seconds per day, `None` if the telegrams within the window were all received at the same moment
+	pub rate: Option<f64>,
+	/// Number of telegrams the estimate is based on
+	pub samples: usize,
+}
+
+/// Fits a line through the samples with the least squares method.
+fn estimate(samples: &VecDeque<(f64, f64)>) -> Option<ClockDrift> {
+	let &(last_received, _) = samples.back()?;
+	let count = samples.len() as f64;
+	let mean_received = samples.iter().map(|(received, _)| received).sum::<f64>() / count;
+	let mean_offset = samples.iter().map(|(_, offset)| offset).sum::<f64>() / count;
+	let (covariance, variance) = samples.iter().fold((0., 0.), |(covariance, variance), (received, offset)| {
+		let received = received - mean_received;
+		(covariance + received * (offset - mean_offset), variance + received * received)
+	});
+	let slope = (variance > 0.).then(|| covariance / variance);
+	Some(ClockDrift {
+		offset: mean_offset + slope.unwrap_or(0.) * (last_received - mean_received),
+		rate: slope.map(|slope| slope * 86400.),
+		samples: samples.len(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex, PoisonError};
+
+	use super::*;
+	use crate::telegram::parse_telegram;
+
+	fn telegram(timestamp: &str) -> Telegram {
+		parse_telegram(format!("/test\r\n\r\n0-0:1.0.0({timestamp})\r\n!\r\n").as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn test_drift_monitor() {
+		let alerts = Arc::new(Mutex::new(vec![]));
+		let mut monitor = DriftMonitor::new(Duration::from_secs(3 * 3600)).with_alert(Duration::from_secs(5), {
+			let alerts = Arc::clone(&alerts);
+			move |drift| alerts.lock().unwrap_or_else(PoisonError::into_inner).push(drift)
+		});
+		assert_eq!(None, monitor.drift());
+		monitor.record_at(&parse_telegram(b"/test\r\n\r\n!\r\n").unwrap(), UNIX_EPOCH);
+		assert_eq!(None, monitor.drift());
+
+		// 2024-07-01 00:00:00 UTC, the meter gains 1 second per hour starting 2 seconds ahead
+		let start = UNIX_EPOCH + Duration::from_secs(1_719_792_000);
+		for (hour, timestamp) in ["240701020002S", "240701030003S", "240701040004S", "240701050005S"]
+			.into_iter()
+			.enumerate()
+		{
+			monitor.record_at(&telegram(timestamp), start + Duration::from_secs(hour as u64 * 3600));
+		}
+		let drift = monitor.drift().unwrap();
+		assert!((drift.offset - 5.).abs() < 1e-6, "{drift:?}");
+		assert!((drift.rate.unwrap() - 24.).abs() < 1e-6, "{drift:?}");
+		assert_eq!(4, drift.samples);
+		assert!(alerts.lock().unwrap_or_else(PoisonError::into_inner).is_empty());
+
+		monitor.record_at(&telegram("240701060007S"), start + Duration::from_secs(4 * 3600));
+		monitor.record_at(&telegram("240701070009S"), start + Duration::from_secs(5 * 3600));
+		assert_eq!(1, alerts.lock().unwrap_or_else(PoisonError::into_inner).len());
+		// the first 2 samples are out of the window
+		assert_eq!(4, monitor.drift().unwrap().samples);
+
+		// the same moment in the legacy format without the DST flag is 1 hour ahead with the default offset
+		let mut monitor = DriftMonitor::new(Duration::from_secs(60));
+		monitor.record_at(&telegram("240701020000"), start);
+		assert_eq!(
+			Some(ClockDrift {
+				offset: 3600.,
+				rate: None,
+				samples: 1,
+			}),
+			monitor.drift()
+		);
+	}
+}
@@ -0,0 +1,441 @@
+//! Append-only on-disk journal of telegrams.
+//!
+//! The journal stores every appended telegram together with a sequence number and the wall-clock time of the append. The
+//! records are written to segment files in a single directory, a new segment is started when the current one reaches the
+//! configured size. The consumers can replay the journal from any sequence number, e.g., to bridge the time when the downstream
+//! system (MQTT broker, database) was not available.
+//!
+//! Every segment file is named after the sequence number of its first record, e.g., `00000000000000000042.journal`. The record
+//! format is:
+//! * sequence number, u64 little endian
+//! * timestamp as milliseconds since the UNIX epoch, u64 little endian
+//! * length of the telegram contents, u32 little endian
+//! * telegram contents
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+
+use crate::clock::{Clock, SystemClock};
+use crate::reader::RawTelegram;
+
+const SEGMENT_EXTENSION: &str = "journal";
+const RECORD_HEADER_LEN: usize = 8 + 8 + 4;
+const DEFAULT_MAX_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Append-only journal of telegrams stored in segment files in a directory.
+///
+/// # Example
+/// ```no_run
+/// use homey_energy_dongle::journal::Journal;
+/// use homey_energy_dongle::reader::RawTelegram;
+///
+/// let mut journal = Journal::open("/var/lib/dongle/journal").unwrap();
+/// let sequence = journal
+///     .append(&RawTelegram {
+///         contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n".to_vec(),
+///     })
+///     .unwrap();
+/// for entry in journal.replay(sequence).unwrap() {
+///     let entry = entry.unwrap();
+///     println!("{}: {:?}", entry.sequence, entry.timestamp);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Journal<C = SystemClock> {
+	clock: C,
+	dir: PathBuf,
+	max_segment_size: u64,
+	/// Sequence numbers of the first records of the segments in ascending order
+	segments: Vec<u64>,
+	writer: Option<SegmentWriter>,
+	next_sequence: u64,
+}
+
+#[derive(Debug)]
+struct SegmentWriter {
+	file: BufWriter<File>,
+	len: u64,
+}
+
+impl Journal {
+	/// Opens the journal in `dir` creating the directory if it doesn't exist.
+	///
+	/// A partially written record at the end of the last segment, e.g., after a crash, is discarded.
+	pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+		Self::open_with_clock(dir, SystemClock)
+	}
+}
+
+impl<C: Clock> Journal<C> {
+	/// Opens the journal in `dir` that uses `clock` as a source of time, see [Journal::open()] and [clock](crate::clock).
+	pub fn open_with_clock(dir: impl AsRef<Path>, clock: C) -> io::Result<Self> {
+		let dir = dir.as_ref().to_path_buf();
+		fs::create_dir_all(&dir)?;
+		let segments = list_segments(&dir)?;
+		let mut next_sequence = 0;
+		if let Some(&last) = segments.last() {
+			let path = segment_path(&dir, last);
+			let mut reader = SegmentReader::open(&path)?;
+			let mut valid_len = 0;
+			next_sequence = last;
+			while let Some((sequence, _, contents)) = reader.read_record()? {
+				next_sequence = sequence + 1;
+				valid_len += (RECORD_HEADER_LEN + contents.len()) as u64;
+			}
+			if reader.truncated {
+				warn!("Discarding the partially written record at the end of {}", path.display());
+				OpenOptions::new().write(true).open(&path)?.set_len(valid_len)?;
+			}
+		}
+		Ok(Self {
+			clock,
+			dir,
+			max_segment_size: DEFAULT_MAX_SEGMENT_SIZE,
+			segments,
+			writer: None,
+			next_sequence,
+		})
+	}
+
+	/// Sets the size after which a new segment file is started, 16 MiB by default.
+	pub fn with_max_segment_size(mut self, max_segment_size: u64) -> Self {
+		self.max_segment_size = max_segment_size;
+		self
+	}
+
+	/// Returns the sequence number that the next appended telegram will get.
+	pub fn next_sequence(&self) -> u64 {
+		self.next_sequence
+	}
+
+	/// Returns the sequence number of the oldest telegram in the journal or `None` if the journal has no segments.
+	pub fn first_sequence(&self) -> Option<u64> {
+		self.segments.first().copied()
+	}
+
+	/// Appends `telegram` with the current time and returns its sequence number.
+	///
+	/// The record is flushed to the operating system before returning, but not synced to the disk.
+	pub fn append(&mut self, telegram: &RawTelegram) -> io::Result<u64> {
+		self.append_at(telegram, self.clock.system_time())
+	}
+
+	/// Appends `telegram` with the specified time and returns its sequence number.
+	pub fn append_at(&mut self, telegram: &RawTelegram, timestamp: SystemTime) -> io::Result<u64> {
+		let contents_len = u32::try_from(telegram.contents.len()).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+		let sequence = self.next_sequence;
+		let record_len = (RECORD_HEADER_LEN + telegram.contents.len()) as u64;
+		let writer = self.writer(record_len)?;
+		let millis = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+		let res = writer
+			.file
+			.write_all(&sequence.to_le_bytes())
+			.and_then(|_| writer.file.write_all(&millis.to_le_bytes()))
+			.and_then(|_| writer.file.write_all(&contents_len.to_le_bytes()))
+			.and_then(|_| writer.file.write_all(&telegram.contents))
+			.and_then(|_| writer.file.flush());
+		if let Err(err) = res {
+			self.discard_partial_record();
+			return Err(err);
+		}
+		writer.len += record_len;
+		self.next_sequence += 1;
+		Ok(sequence)
+	}
+
+	/// Returns the iterator over the journal entries starting from the sequence number `from`.
+	///
+	/// If `from` is older than the oldest retained entry, the replay starts from the oldest entry. The entries appended after this
+	/// call are not included.
+	pub fn replay(&self, from: u64) -> io::Result<JournalReplay> {
+		let first_segment = self.segments.partition_point(|&first| first <= from).saturating_sub(1);
+		Ok(JournalReplay {
+			segments: self.segments[first_segment..]
+				.iter()
+				.map(|&first| segment_path(&self.dir, first))
+				.collect(),
+			reader: None,
+			from,
+			until: self.next_sequence,
+		})
+	}
+
+	/// Removes the segments that only contain the entries older than `sequence`, returns the number of removed segments.
+	///
+	/// The segment that is currently written to is never removed.
+	pub fn remove_before(&mut self, sequence: u64) -> io::Result<usize> {
+		let mut removed = 0;
+		while self.segments.len() > 1 && self.segments[1] <= sequence {
+			fs::remove_file(segment_path(&self.dir, self.segments[0]))?;
+			self.segments.remove(0);
+			removed += 1;
+		}
+		Ok(removed)
+	}
+
+	/// Truncates the current segment to the last complete record after a failed write and drops the writer.
+	///
+	/// Otherwise the following records would be appended after the partial one and the segment would be unreadable past it.
+	fn discard_partial_record(&mut self) {
+		let Some(writer) = self.writer.take() else {
+			return;
+		};
+		// the buffered part of the record is discarded without flushing
+		let (file, _) = writer.file.into_parts();
+		if let Err(err) = file.set_len(writer.len) {
+			warn!("Failed to discard the partially written record: {err}");
+		}
+	}
+
+	fn writer(&mut self, record_len: u64) -> io::Result<&mut SegmentWriter> {
+		let rotate = self
+			.writer
+			.as_ref()
+			.is_some_and(|writer| writer.len > 0 && writer.len + record_len > self.max_segment_size);
+		if rotate || self.writer.is_none() {
+			let reuse_last = !rotate && self.segments.last().is_some();
+			let first = if reuse_last {
+				self.segments[self.segments.len() - 1]
+			} else {
+				self.next_sequence
+			};
+			let file = OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(segment_path(&self.dir, first))?;
+			let len = file.metadata()?.len();
+			if !reuse_last {
+				self.segments.push(first);
+			}
+			self.writer = Some(SegmentWriter {
+				file: BufWriter::new(file),
+				len,
+			});
+			// the reopened last segment can be already full
+			if reuse_last && len > 0 && len + record_len > self.max_segment_size {
+				return self.writer(record_len);
+			}
+		}
+		Ok(self.writer.as_mut().expect("writer is initialized above"))
+	}
+}
+
+/// Telegram read from [Journal].
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+	pub sequence: u64,
+	/// Time of the append with millisecond precision
+	pub timestamp: SystemTime,
+	pub telegram: RawTelegram,
+}
+
+/// Iterator over the entries of [Journal], created by [Journal::replay()].
+#[derive(Debug)]
+pub struct JournalReplay {
+	/// Paths of the segments that are not yet opened
+	segments: Vec<PathBuf>,
+	reader: Option<SegmentReader>,
+	from: u64,
+	until: u64,
+}
+
+impl JournalReplay {
+	fn next_entry(&mut self) -> io::Result<Option<JournalEntry>> {
+		loop {
+			let Some(reader) = &mut self.reader else {
+				if self.segments.is_empty() {
+					return Ok(None);
+				}
+				self.reader = Some(SegmentReader::open(&self.segments.remove(0))?);
+				continue;
+			};
+			match reader.read_record()? {
+				Some((sequence, _, _)) if sequence < self.from => {}
+				Some((sequence, _, _)) if sequence >= self.until => return Ok(None),
+				Some((sequence, millis, contents)) => {
+					return Ok(Some(JournalEntry {
+						sequence,
+						timestamp: UNIX_EPOCH + Duration::from_millis(millis),
+						telegram: RawTelegram { contents },
+					}));
+				}
+				None => self.reader = None,
+			}
+		}
+	}
+}
+
+impl Iterator for JournalReplay {
+	type Item = io::Result<JournalEntry>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let out = self.next_entry().transpose();
+		if matches!(out, Some(Err(_))) {
+			self.segments.clear();
+			self.reader = None;
+		}
+		out
+	}
+}
+
+#[derive(Debug)]
+struct SegmentReader {
+	file: BufReader<File>,
+	/// Whether the segment ends with a partially written record
+	truncated: bool,
+}
+
+impl SegmentReader {
+	fn open(path: &Path) -> io::Result<Self> {
+		Ok(Self {
+			file: BufReader::new(File::open(path)?),
+			truncated: false,
+		})
+	}
+
+	/// Reads the next record returning its sequence number, timestamp in milliseconds and contents.
+	fn read_record(&mut self) -> io::Result<Option<(u64, u64, Vec<u8>)>> {
+		let mut header = [0; RECORD_HEADER_LEN];
+		let read = self.read_full(&mut header)?;
+		if read < header.len() {
+			self.truncated = read > 0;
+			return Ok(None);
+		}
+		let sequence = u64::from_le_bytes(header[..8].try_into().expect("static size"));
+		let millis = u64::from_le_bytes(header[8..16].try_into().expect("static size"));
+		let len = u32::from_le_bytes(header[16..].try_into().expect("static size"));
+		// the length can be corrupted, so the buffer only grows with the data actually present in the file
+		let mut contents = vec![];
+		(&mut self.file).take(u64::from(len)).read_to_end(&mut contents)?;
+		if contents.len() < len as usize {
+			self.truncated = true;
+			return Ok(None);
+		}
+		Ok(Some((sequence, millis, contents)))
+	}
+
+	/// Reads into `buf` until it's full or the end of the file is reached, returns the number of bytes read.
+	fn read_full(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut read = 0;
+		while read < buf.len() {
+			match self.file.read(&mut buf[read..]) {
+				Ok(0) => break,
+				Ok(n) => read += n,
+				Err(err) if err.kind() == ErrorKind::Interrupted => {}
+				Err(err) => return Err(err),
+			}
+		}
+		Ok(read)
+	}
+}
+
+fn segment_path(dir: &Path, first_sequence: u64) -> PathBuf {
+	dir.join(format!("{first_sequence:020}.{SEGMENT_EXTENSION}"))
+}
+
+fn list_segments(dir: &Path) -> io::Result<Vec<u64>> {
+	let mut out = vec![];
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		if path.extension().is_some_and(|ext| ext == SEGMENT_EXTENSION) {
+			if let Some(first) = path.file_stem().and_then(|stem| stem.to_str()?.parse().ok()) {
+				out.push(first);
+			}
+		}
+	}
+	out.sort_unstable();
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct TempDir(PathBuf);
+
+	impl TempDir {
+		fn new(name: &str) -> Self {
+			let dir = std::env::temp_dir().join(format!("homey-energy-dongle-{name}-{}", std::process::id()));
+			let _ = fs::remove_dir_all(&dir);
+			Self(dir)
+		}
+	}
+
+	impl Drop for TempDir {
+		fn drop(&mut self) {
+			let _ = fs::remove_dir_all(&self.0);
+		}
+	}
+
+	fn telegram(n: u8) -> RawTelegram {
+		RawTelegram {
+			contents: vec![b'/', n, b'\r', b'\n', b'!'],
+		}
+	}
+
+	fn sequences(journal: &Journal, from: u64) -> Vec<u64> {
+		journal.replay(from).unwrap().map(|entry| entry.unwrap().sequence).collect()
+	}
+
+	#[test]
+	fn test_journal_replay() {
+		let dir = TempDir::new("replay");
+		let mut journal = Journal::open(&dir.0).unwrap().with_max_segment_size(50);
+		assert_eq!(None, journal.first_sequence());
+		let at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+		for n in 0..10 {
+			assert_eq!(u64::from(n), journal.append_at(&telegram(n), at).unwrap());
+		}
+		// 25 bytes per record, 2 records per segment
+		assert_eq!(5, list_segments(&dir.0).unwrap().len());
+		assert_eq!((0..10).collect::<Vec<_>>(), sequences(&journal, 0));
+		assert_eq!((5..10).collect::<Vec<_>>(), sequences(&journal, 5));
+		assert!(sequences(&journal, 10).is_empty());
+
+		let entry = journal.replay(3).unwrap().next().unwrap().unwrap();
+		assert_eq!(3, entry.sequence);
+		assert_eq!(at, entry.timestamp);
+		assert_eq!(telegram(3).contents, entry.telegram.contents);
+
+		assert_eq!(2, journal.remove_before(5).unwrap());
+		assert_eq!(Some(4), journal.first_sequence());
+		assert_eq!((4..10).collect::<Vec<_>>(), sequences(&journal, 0));
+	}
+
+	#[test]
+	fn test_journal_reopen() {
+		let dir = TempDir::new("reopen");
+		let mut journal = Journal::open(&dir.0).unwrap();
+		for n in 0..3 {
+			journal.append(&telegram(n)).unwrap();
+		}
+		drop(journal);
+
+		// simulate a crash in the middle of writing a record
+		let segment = segment_path(&dir.0, 0);
+		let mut file = OpenOptions::new().append(true).open(&segment).unwrap();
+		file.write_all(&3u64.to_le_bytes()).unwrap();
+		drop(file);
+
+		let mut journal = Journal::open(&dir.0).unwrap();
+		assert_eq!(3, journal.next_sequence());
+		assert_eq!(3, journal.append(&telegram(3)).unwrap());
+		assert_eq!((0..4).collect::<Vec<_>>(), sequences(&journal, 0));
+		drop(journal);
+
+		// corrupted length of the last record
+		let mut file = OpenOptions::new().append(true).open(&segment).unwrap();
+		file.write_all(&4u64.to_le_bytes()).unwrap();
+		file.write_all(&0u64.to_le_bytes()).unwrap();
+		file.write_all(&u32::MAX.to_le_bytes()).unwrap();
+		file.write_all(b"/4").unwrap();
+		drop(file);
+		let journal = Journal::open(&dir.0).unwrap();
+		assert_eq!(4, journal.next_sequence());
+		assert_eq!((0..4).collect::<Vec<_>>(), sequences(&journal, 0));
+	}
+}
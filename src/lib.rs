@@ -4,7 +4,23 @@
 //! how to enable it in your dongle.
 //!
 //! The crate includes the mDNS discovery with the `discover` feature and the local API access with the `websocket` feature. Both
-//! features are disabled by default.
+//! features are disabled by default. The `tokio` feature additionally enables [WebsocketEnergyDongle::spawn()] that reads the
+//! telegrams in a background task, the `client` feature enables [EnergyDongleClient] that hides the whole workflow below behind a
+//! few methods, the `manager` feature enables [DongleManager] that maintains the connections to all
+//! dongles on the network and the `pool` feature enables [ConnectionPool] that keeps health-checked connections for relays and
+//! aggregators.
+//!
+//! The `webhook` feature enables [WebhookSink] that POSTs the telegrams as JSON to an HTTP or HTTPS endpoint (with the rustls TLS
+//! backend) and the `dbus` feature enables [DbusPublisher] that publishes the meter values on the D-Bus session or system bus.
+//! The `otel` feature enables the [OpenTelemetry instrumentation](otel) of the meter readings and the connection lifecycle. The
+//! `serde` feature implements `serde::Serialize` and `serde::Deserialize` for [RawTelegram] to store it or to send it over JSON
+//! APIs, and `serde::Serialize` for the [energy report](report) summaries.
+//!
+//! Additional features that help with testing the code built on top of this crate:
+//! * `arbitrary` implements `arbitrary::Arbitrary` for [RawTelegram] to enable property-based testing and fuzzing
+//! * `test-util` adds [MockClock] for deterministic tests of the time-based components and [SimulatedMeter] that generates
+//!   realistic telegrams in virtual time
+//! * `bench` adds the [latency probe and the throughput measurement](bench) of the telegram extraction
 //!
 //! The general workflow with this crate is as follows:
 //! 1. Discover the present Homey Energy Dongles on the network using mDNS using [discover_devices_with_mdns()] or supply
@@ -16,8 +32,11 @@
 //!    always contain a complete DSMR telegram, so you'll need some kind of buffer to store the intermediate bytes. For this you
 //!    can use a more low-level [RawTelegramReader] with its [RawTelegramReader::feed()] method
 //!    or a more easy-to-use [RawTelegramStream] wrapper which implements [Stream] over [RawTelegram].
-//! 4. Use a DSMR parsing library (e.g., [dsmr5](https://crates.io/crates/dsmr5)) to parse the [RawTelegram] and get a readable
-//!    DSMR telegram.
+//! 4. Parse the [RawTelegram] with [parse_telegram()] to get the data objects of the DSMR telegram. Alternatively, use a DSMR
+//!    parsing library (e.g., [dsmr5](https://crates.io/crates/dsmr5)) to get a readable DSMR telegram.
+//!
+//! The [prelude] re-exports the types needed for this workflow. To share the captured telegrams, e.g., in a bug report, remove
+//! the meter identifiers from them with the [Anonymizer].
 //!
 //! # Example
 //! ```no_run
@@ -46,16 +65,57 @@
 //! [discover_devices_with_mdns()]: discover::discover_devices_with_mdns
 //! [WebsocketEnergyDongle::connect()]: websocket::WebsocketEnergyDongle::connect
 //! [WebsocketEnergyDongle]: websocket::WebsocketEnergyDongle
+//! [WebsocketEnergyDongle::spawn()]: websocket::WebsocketEnergyDongle::spawn
+//! [EnergyDongleClient]: client::EnergyDongleClient
+//! [DongleManager]: manager::DongleManager
+//! [ConnectionPool]: pool::ConnectionPool
+//! [WebhookSink]: webhook::WebhookSink
+//! [DbusPublisher]: dbus::DbusPublisher
 //! [Stream]: futures_util::Stream
 //! [RawTelegramReader]: reader::RawTelegramReader
 //! [RawTelegramReader::feed()]: reader::RawTelegramReader::feed
 //! [RawTelegramStream]: reader::RawTelegramStream
 //! [RawTelegram]: reader::RawTelegram
+//! [MockClock]: clock::MockClock
+//! [SimulatedMeter]: simulation::SimulatedMeter
+//! [Anonymizer]: anonymize::Anonymizer
 
 pub use bytes::Bytes;
+pub use telegram::parse_telegram;
 
+pub mod anonymize;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod clock;
+pub mod conformance;
+#[cfg(feature = "dbus")]
+pub mod dbus;
 #[cfg(feature = "discover")]
 pub mod discover;
+pub mod drift;
+pub mod journal;
+#[cfg(feature = "manager")]
+pub mod manager;
+#[cfg(feature = "otel")]
+pub mod otel;
+#[cfg(feature = "pool")]
+pub mod pool;
+pub mod prelude;
+pub mod pulse;
 pub mod reader;
+pub mod reconnect;
+pub mod report;
+#[cfg(feature = "test-util")]
+pub mod simulation;
+pub mod solar;
+pub mod stats;
+pub mod telegram;
+#[cfg(test)]
+mod test_telegrams;
+pub mod topology;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 #[cfg(feature = "websocket")]
 pub mod websocket;
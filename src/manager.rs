@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use log::{debug, trace, warn};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::clock::{Clock, SystemClock};
+use crate::discover::{EnergyDongleHostInfo, discover_devices_with_mdns};
+use crate::reader::RawTelegram;
+pub use crate::reconnect::ConnectionState;
+use crate::reconnect::{ExponentialBackoff, ReconnectEvent, ReconnectPolicy, reconnect_loop};
+use crate::websocket::{ConnectError, WebsocketEnergyDongle};
+
+type DongleFilter = Arc<dyn Fn(&EnergyDongleHostInfo) -> bool + Send + Sync>;
+
+/// Actor that maintains the connections to all Homey Energy Dongles on the local network.
+///
+/// The manager periodically runs the mDNS discovery, connects to every found dongle that passes the filter and reconnects when a
+/// connection is lost. When the discovery reports that a dongle moved to a different address, e.g., after a DHCP lease change,
+/// the manager reconnects to the new address right away instead of waiting for the old connection to time out. The telegrams
+/// from all dongles are published to the subscribers created with [DongleManager::subscribe()], the state of each connection can
+/// be queried with [DongleManager::status()].
+///
+/// The background tasks are stopped when the manager is dropped. Must be created within the context of a Tokio runtime.
+///
+/// # Example
+/// ```no_run
+/// use homey_energy_dongle::manager::DongleManager;
+///
+/// async fn example() {
+///     let manager = DongleManager::builder()
+///         .filter(|dongle| dongle.name.contains("Building A"))
+///         .start();
+///     let mut telegrams = manager.subscribe();
+///     while let Ok(telegram) = telegrams.recv().await {
+///         println!("{}: {} bytes", telegram.dongle, telegram.telegram.contents.len());
+///     }
+/// }
+/// ```
+pub struct DongleManager {
+	shared: Arc<Shared>,
+	discovery_task: JoinHandle<()>,
+}
+
+impl DongleManager {
+	/// Returns the builder to configure and start the manager.
+	pub fn builder() -> DongleManagerBuilder {
+		DongleManagerBuilder {
+			discovery_interval: Duration::from_secs(60),
+			discovery_timeout: Duration::from_secs(5),
+			reconnect_policy: Arc::new(ExponentialBackoff::default()),
+			clock: Arc::new(SystemClock),
+			channel_capacity: 64,
+			filter: Arc::new(|_| true),
+		}
+	}
+
+	/// Returns a new receiver of the telegrams from all managed dongles.
+	///
+	/// Only the telegrams received after the subscription are delivered. A receiver that falls behind by more than the channel
+	/// capacity loses the oldest telegrams, see [broadcast::Receiver::recv()].
+	pub fn subscribe(&self) -> broadcast::Receiver<DongleTelegram> {
+		self.shared.telegrams.subscribe()
+	}
+
+	/// Returns the status of the dongle with the specified mDNS name.
+	pub fn status(&self, name: &str) -> Option<DongleStatus> {
+		self.shared.dongles().get(name).map(|dongle| dongle.status.clone())
+	}
+
+	/// Returns the statuses of all managed dongles keyed by their mDNS names.
+	pub fn statuses(&self) -> HashMap<String, DongleStatus> {
+		self
+			.shared
+			.dongles()
+			.iter()
+			.map(|(name, dongle)| (name.clone(), dongle.status.clone()))
+			.collect()
+	}
+}
+
+impl Drop for DongleManager {
+	fn drop(&mut self) {
+		self.discovery_task.abort();
+		for dongle in self.shared.dongles().values() {
+			dongle.task.abort();
+		}
+	}
+}
+
+impl fmt::Debug for DongleManager {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DongleManager").field("statuses", &self.statuses()).finish()
+	}
+}
+
+/// Builder for [DongleManager], created by [DongleManager::builder()].
+pub struct DongleManagerBuilder {
+	discovery_interval: Duration,
+	discovery_timeout: Duration,
+	reconnect_policy: Arc<dyn ReconnectPolicy>,
+	clock: Arc<dyn Clock + Send + Sync>,
+	channel_capacity: usize,
+	filter: DongleFilter,
+}
+
+impl DongleManagerBuilder {
+	/// Interval between the mDNS discovery runs, 60 seconds by default.
+	pub fn discovery_interval(mut self, interval: Duration) -> Self {
+		self.discovery_interval = interval;
+		self
+	}
+
+	/// Duration of a single mDNS discovery run, 5 seconds by default.
+	pub fn discovery_timeout(mut self, timeout: Duration) -> Self {
+		self.discovery_timeout = timeout;
+		self
+	}
+
+	/// Policy that decides when to reconnect to a dongle after the connection is lost, [ExponentialBackoff::default()] by default.
+	///
+	/// When the policy gives up, the dongle is put into [ConnectionState::GaveUp] and is retried after the next discovery run
+	/// finds it.
+	pub fn reconnect_policy(mut self, policy: impl ReconnectPolicy + 'static) -> Self {
+		self.reconnect_policy = Arc::new(policy);
+		self
+	}
+
+	/// Source of the time for the status timestamps and the connection uptime passed to the reconnect policy, [SystemClock] by
+	/// default, see [clock](crate::clock).
+	pub fn clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Number of telegrams buffered for the slowest subscriber, 64 by default.
+	pub fn channel_capacity(mut self, capacity: usize) -> Self {
+		self.channel_capacity = capacity;
+		self
+	}
+
+	/// Only manage the dongles for which `filter` returns `true`, all discovered dongles are managed by default.
+	pub fn filter(mut self, filter: impl Fn(&EnergyDongleHostInfo) -> bool + Send + Sync + 'static) -> Self {
+		self.filter = Arc::new(filter);
+		self
+	}
+
+	/// Starts the background tasks and returns the manager.
+	///
+	/// Must be called within the context of a Tokio runtime.
+	pub fn start(self) -> DongleManager {
+		let (telegrams, _) = broadcast::channel(self.channel_capacity.max(1));
+		let shared = Arc::new(Shared {
+			dongles: Mutex::new(HashMap::new()),
+			telegrams,
+			reconnect_policy: self.reconnect_policy,
+			clock: self.clock,
+		});
+		let discovery_task = tokio::spawn(discover(
+			Arc::clone(&shared),
+			self.discovery_interval,
+			self.discovery_timeout,
+			self.filter,
+		));
+		DongleManager { shared, discovery_task }
+	}
+}
+
+/// Telegram received by [DongleManager].
+#[derive(Debug, Clone)]
+pub struct DongleTelegram {
+	/// mDNS name of the dongle that sent the telegram
+	pub dongle: Arc<str>,
+	pub telegram: RawTelegram,
+}
+
+/// Status of a dongle managed by [DongleManager].
+#[derive(Debug, Clone)]
+pub struct DongleStatus {
+	/// Host information from the last discovery
+	pub info: EnergyDongleHostInfo,
+	pub state: ConnectionState,
+	/// Time of the last change of `state`
+	pub state_since: Instant,
+	/// Number of telegrams received from the dongle
+	pub telegrams: u64,
+	/// Time of the last received telegram
+	pub last_telegram: Option<Instant>,
+	/// Description of the last connection error
+	pub last_error: Option<String>,
+	/// Number of the connection attempts after the first one
+	pub reconnects: u64,
+	/// Address of the established connection, `None` when not connected
+	pub peer_addr: Option<SocketAddr>,
+}
+
+struct Shared {
+	dongles: Mutex<HashMap<String, ManagedDongle>>,
+	telegrams: broadcast::Sender<DongleTelegram>,
+	reconnect_policy: Arc<dyn ReconnectPolicy>,
+	clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl Shared {
+	fn dongles(&self) -> std::sync::MutexGuard<'_, HashMap<String, ManagedDongle>> {
+		self.dongles.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Updates the status of the dongle unless the connection task of `generation` was already replaced with a new one.
+	fn update_status(&self, name: &str, generation: u64, update: impl FnOnce(&mut DongleStatus)) {
+		if let Some(dongle) = self.dongles().get_mut(name) {
+			if dongle.generation == generation {
+				update(&mut dongle.status);
+			}
+		}
+	}
+
+	fn set_state(&self, name: &str, generation: u64, state: ConnectionState) {
+		self.update_status(name, generation, |status| {
+			status.state = state;
+			status.state_since = self.clock.now();
+			if state != ConnectionState::Connected {
+				status.peer_addr = None;
+			}
+		});
+	}
+}
+
+impl DongleStatus {
+	/// Whether the newly discovered `info` makes the current connection or the pending connection attempt obsolete.
+	///
+	/// The established connection is only dropped when its address is no longer announced, new additional addresses don't
+	/// interrupt it. The pending attempt is restarted on any change of the addresses.
+	fn address_changed(&self, info: &EnergyDongleHostInfo) -> bool {
+		match self.state {
+			ConnectionState::Connected => self
+				.peer_addr
+				.is_some_and(|peer_addr| !info.socket_addresses().any(|addr| addr == peer_addr)),
+			ConnectionState::Connecting | ConnectionState::Disconnected => {
+				self.info.addresses != info.addresses || self.info.port != info.port
+			}
+			ConnectionState::GaveUp => false,
+		}
+	}
+}
+
+struct ManagedDongle {
+	status: DongleStatus,
+	task: JoinHandle<()>,
+	/// Incremented when the connection task is restarted, the aborted task can still be in the middle of a poll and must not
+	/// update the status anymore
+	generation: u64,
+}
+
+async fn discover(shared: Arc<Shared>, interval: Duration, timeout: Duration, filter: DongleFilter) {
+	loop {
+		match discover_devices_with_mdns(timeout, 0).await {
+			Ok(found) => {
+				let mut dongles = shared.dongles();
+				for info in found.into_iter().filter(|info| filter(info)) {
+					if let Some(dongle) = dongles.get_mut(&info.name) {
+						let address_changed = dongle.status.address_changed(&info);
+						dongle.status.info = info;
+						if address_changed {
+							debug!(
+								"Homey Energy Dongle {} moved to {:?}, reconnecting",
+								dongle.status.info.name, dongle.status.info.addresses
+							);
+							dongle.task.abort();
+						} else if dongle.status.state == ConnectionState::GaveUp {
+							debug!("Retrying the rediscovered Homey Energy Dongle: {}", dongle.status.info.name);
+						} else {
+							continue;
+						}
+						dongle.status.state = ConnectionState::Connecting;
+						dongle.status.state_since = shared.clock.now();
+						dongle.status.peer_addr = None;
+						dongle.generation += 1;
+						dongle.task = tokio::spawn(maintain_connection(
+							Arc::clone(&shared),
+							Arc::from(dongle.status.info.name.as_str()),
+							dongle.generation,
+						));
+						continue;
+					}
+					debug!("Managing new Homey Energy Dongle: {}", info.name);
+					let name = info.name.clone();
+					let task = tokio::spawn(maintain_connection(Arc::clone(&shared), Arc::from(name.as_str()), 0));
+					let status = DongleStatus {
+						info,
+						state: ConnectionState::Connecting,
+						state_since: shared.clock.now(),
+						telegrams: 0,
+						last_telegram: None,
+						last_error: None,
+						reconnects: 0,
+						peer_addr: None,
+					};
+					dongles.insert(
+						name,
+						ManagedDongle {
+							status,
+							task,
+							generation: 0,
+						},
+					);
+				}
+			}
+			Err(err) => warn!("Homey Energy Dongle discovery failed: {err}"),
+		}
+		tokio::time::sleep(interval).await;
+	}
+}
+
+async fn maintain_connection(shared: Arc<Shared>, name: Arc<str>, generation: u64) {
+	let shared = &*shared;
+	let name = &name;
+	reconnect_loop(
+		&shared.reconnect_policy,
+		&*shared.clock,
+		None,
+		|| async move {
+			// the addresses can change between the discovery runs, so take the fresh ones for every attempt
+			let Some(info) = shared.dongles().get(&**name).map(|dongle| dongle.status.info.clone()) else {
+				return Err(ConnectError::NoAddresses);
+			};
+			WebsocketEnergyDongle::builder_with_addresses(info.socket_addresses(), &info.path)
+				.connect()
+				.await
+		},
+		|dongle| async move {
+			let peer_addr = dongle.peer_addr();
+			shared.update_status(name, generation, |status| status.peer_addr = Some(peer_addr));
+			let mut last_error: Option<Box<dyn Error + Send + Sync>> = None;
+			dongle
+				.dispatch(
+					|telegram| {
+						shared.update_status(name, generation, |status| {
+							status.telegrams += 1;
+							status.last_telegram = Some(shared.clock.now());
+						});
+						// sending fails only when there are no subscribers, which is fine
+						let _ = shared.telegrams.send(DongleTelegram {
+							dongle: Arc::clone(name),
+							telegram,
+						});
+					},
+					|err| {
+						shared.update_status(name, generation, |status| status.last_error = Some(err.to_string()));
+						last_error = Some(Box::new(err));
+					},
+				)
+				.await;
+			last_error
+		},
+		|event| match event {
+			ReconnectEvent::Connecting { attempt } => {
+				if attempt > 0 {
+					shared.update_status(name, generation, |status| status.reconnects += 1);
+				}
+				shared.set_state(name, generation, ConnectionState::Connecting);
+			}
+			ReconnectEvent::Connected => shared.set_state(name, generation, ConnectionState::Connected),
+			ReconnectEvent::ConnectFailed(err) => {
+				trace!("Connection to Homey Energy Dongle {name} failed: {err}");
+				shared.update_status(name, generation, |status| status.last_error = Some(err.to_string()));
+				shared.set_state(name, generation, ConnectionState::Disconnected);
+			}
+			ReconnectEvent::Disconnected => {
+				debug!("Connection to Homey Energy Dongle {name} is closed");
+				shared.set_state(name, generation, ConnectionState::Disconnected);
+			}
+			ReconnectEvent::GaveUp { attempts } => {
+				debug!("Giving up on Homey Energy Dongle {name} after {attempts} attempts");
+				shared.set_state(name, generation, ConnectionState::GaveUp);
+			}
+		},
+	)
+	.await;
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use std::net::IpAddr;
+
+	use super::*;
+
+	fn info(addresses: &[&str]) -> EnergyDongleHostInfo {
+		EnergyDongleHostInfo {
+			name: "dongle".to_string(),
+			hostname: "dongle.local.".to_string(),
+			addresses: addresses
+				.iter()
+				.map(|addr| addr.parse::<IpAddr>().unwrap())
+				.collect::<HashSet<_>>(),
+			port: 80,
+			path: "/ws".to_string(),
+			version: "1".to_string(),
+		}
+	}
+
+	fn connected_status() -> DongleStatus {
+		DongleStatus {
+			info: info(&["192.168.1.10"]),
+			state: ConnectionState::Connected,
+			state_since: Instant::now(),
+			telegrams: 0,
+			last_telegram: None,
+			last_error: None,
+			reconnects: 0,
+			peer_addr: Some("192.168.1.10:80".parse().unwrap()),
+		}
+	}
+
+	#[test]
+	fn test_address_changed() {
+		let mut status = connected_status();
+		assert!(!status.address_changed(&info(&["192.168.1.10"])));
+		assert!(!status.address_changed(&info(&["192.168.1.10", "fe80::1"])));
+		assert!(status.address_changed(&info(&["192.168.1.11"])));
+
+		status.state = ConnectionState::Disconnected;
+		status.peer_addr = None;
+		assert!(!status.address_changed(&info(&["192.168.1.10"])));
+		assert!(status.address_changed(&info(&["192.168.1.10", "fe80::1"])));
+
+		status.state = ConnectionState::GaveUp;
+		assert!(!status.address_changed(&info(&["192.168.1.11"])));
+	}
+
+	#[tokio::test]
+	async fn test_stale_task_status() {
+		let shared = Shared {
+			dongles: Mutex::new(HashMap::new()),
+			telegrams: broadcast::channel(1).0,
+			reconnect_policy: Arc::new(ExponentialBackoff::default()),
+			clock: Arc::new(SystemClock),
+		};
+		shared.dongles().insert(
+			"dongle".to_string(),
+			ManagedDongle {
+				status: connected_status(),
+				task: tokio::spawn(async {}),
+				generation: 1,
+			},
+		);
+		// the aborted task of the previous generation can't overwrite the status of the restarted one
+		shared.set_state("dongle", 0, ConnectionState::Disconnected);
+		shared.update_status("dongle", 0, |status| status.telegrams += 1);
+		let status = shared.dongles()["dongle"].status.clone();
+		assert_eq!(ConnectionState::Connected, status.state);
+		assert_eq!(0, status.telegrams);
+		assert!(status.peer_addr.is_some());
+
+		shared.set_state("dongle", 1, ConnectionState::Disconnected);
+		assert_eq!(ConnectionState::Disconnected, shared.dongles()["dongle"].status.state);
+	}
+}
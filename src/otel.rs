@@ -0,0 +1,274 @@
+//! OpenTelemetry instrumentation.
+//!
+//! [TelegramMetrics] records the meter readings as OpenTelemetry metrics and [connect_traced()] traces the lifecycle of the
+//! dongle connection. The crate only uses the OpenTelemetry API, so the data goes to the globally installed meter and tracer
+//! providers. Install the OTLP exporter from the `opentelemetry-otlp` crate in your application to send the data to Grafana
+//! Cloud, Datadog or any other OpenTelemetry-compatible backend.
+
+use core::slice;
+
+use opentelemetry::metrics::{Counter, Gauge, Meter};
+use opentelemetry::{KeyValue, global};
+#[cfg(feature = "websocket")]
+pub use traced::{TracedDongle, connect_traced};
+
+use crate::reader::RawTelegram;
+use crate::telegram::{Obis, parse_quantity};
+
+/// Name of the instrumentation scope of the meter and the tracer.
+pub const SCOPE_NAME: &str = "homey-energy-dongle";
+
+const PHASES: [(&str, Obis, Obis, Obis, Obis); 3] = [
+	(
+		"L1",
+		Obis::new(1, 0, 21, 7, 0),
+		Obis::new(1, 0, 22, 7, 0),
+		Obis::new(1, 0, 32, 7, 0),
+		Obis::new(1, 0, 31, 7, 0),
+	),
+	(
+		"L2",
+		Obis::new(1, 0, 41, 7, 0),
+		Obis::new(1, 0, 42, 7, 0),
+		Obis::new(1, 0, 52, 7, 0),
+		Obis::new(1, 0, 51, 7, 0),
+	),
+	(
+		"L3",
+		Obis::new(1, 0, 61, 7, 0),
+		Obis::new(1, 0, 62, 7, 0),
+		Obis::new(1, 0, 72, 7, 0),
+		Obis::new(1, 0, 71, 7, 0),
+	),
+];
+
+/// Recorder of the meter readings as OpenTelemetry metrics.
+///
+/// The following instruments are created:
+/// * `energy_dongle.telegrams` (counter): number of received telegrams, the `crc` attribute is one of `valid`, `mismatch`,
+///   `missing` or `malformed`
+/// * `energy_dongle.power` (gauge, kW): current power, the `direction` attribute is `import` or `export`
+/// * `energy_dongle.energy` (gauge, kWh): total energy over all tariffs, the `direction` attribute is `import` or `export`
+/// * `energy_dongle.phase.power` (gauge, kW): current power per phase, with the `phase` (`L1`, `L2`, `L3`) and `direction`
+///   attributes
+/// * `energy_dongle.phase.voltage` (gauge, V) and `energy_dongle.phase.current` (gauge, A): with the `phase` attribute
+/// * `energy_dongle.gas` (gauge, m³): total gas consumption
+///
+/// # Example
+/// ```no_run
+/// use futures_util::{Stream, StreamExt};
+/// use homey_energy_dongle::otel::TelegramMetrics;
+/// use homey_energy_dongle::reader::RawTelegram;
+///
+/// async fn example(mut telegrams: impl Stream<Item = RawTelegram> + Unpin) {
+///     // install the OTLP exporter as the global meter provider before this call
+///     let metrics = TelegramMetrics::new();
+///     while let Some(telegram) = telegrams.next().await {
+///         metrics.record(&telegram);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TelegramMetrics {
+	telegrams: Counter<u64>,
+	power: Gauge<f64>,
+	energy: Gauge<f64>,
+	phase_power: Gauge<f64>,
+	phase_voltage: Gauge<f64>,
+	phase_current: Gauge<f64>,
+	gas: Gauge<f64>,
+}
+
+impl TelegramMetrics {
+	/// Creates the instruments using the global meter provider.
+	pub fn new() -> Self {
+		Self::with_meter(&global::meter(SCOPE_NAME))
+	}
+
+	/// Creates the instruments using `meter`.
+	pub fn with_meter(meter: &Meter) -> Self {
+		Self {
+			telegrams: meter
+				.u64_counter("energy_dongle.telegrams")
+				.with_description("Number of received telegrams")
+				.build(),
+			power: meter
+				.f64_gauge("energy_dongle.power")
+				.with_description("Current power")
+				.with_unit("kW")
+				.build(),
+			energy: meter
+				.f64_gauge("energy_dongle.energy")
+				.with_description("Total energy over all tariffs")
+				.with_unit("kWh")
+				.build(),
+			phase_power: meter
+				.f64_gauge("energy_dongle.phase.power")
+				.with_description("Current power per phase")
+				.with_unit("kW")
+				.build(),
+			phase_voltage: meter
+				.f64_gauge("energy_dongle.phase.voltage")
+				.with_description("Current voltage per phase")
+				.with_unit("V")
+				.build(),
+			phase_current: meter
+				.f64_gauge("energy_dongle.phase.current")
+				.with_description("Current current per phase")
+				.with_unit("A")
+				.build(),
+			gas: meter
+				.f64_gauge("energy_dongle.gas")
+				.with_description("Total gas consumption")
+				.with_unit("m3")
+				.build(),
+		}
+	}
+
+	/// Records the readings from `telegram`, the readings missing in the telegram are not recorded.
+	pub fn record(&self, telegram: &RawTelegram) {
+		let crc = telegram.check_crc().as_str();
+		self.telegrams.add(1, &[KeyValue::new("crc", crc)]);
+		let Ok(telegram) = telegram.parse() else {
+			return;
+		};
+		let import = KeyValue::new("direction", "import");
+		let export = KeyValue::new("direction", "export");
+		if let Some(net) = telegram.net_metering() {
+			self.power.record(net.power_imported, slice::from_ref(&import));
+			self.power.record(net.power_exported, slice::from_ref(&export));
+			self.energy.record(net.imported, slice::from_ref(&import));
+			self.energy.record(net.exported, slice::from_ref(&export));
+		}
+		let quantity = |obis, unit| telegram.value(obis).and_then(|value| parse_quantity(value, unit));
+		for (phase, power_imported, power_exported, voltage, current) in PHASES {
+			let phase = KeyValue::new("phase", phase);
+			if let Some(power) = quantity(power_imported, "kW") {
+				self.phase_power.record(power, &[phase.clone(), import.clone()]);
+			}
+			if let Some(power) = quantity(power_exported, "kW") {
+				self.phase_power.record(power, &[phase.clone(), export.clone()]);
+			}
+			if let Some(voltage) = quantity(voltage, "V") {
+				self.phase_voltage.record(voltage, slice::from_ref(&phase));
+			}
+			if let Some(current) = quantity(current, "A") {
+				self.phase_current.record(current, &[phase]);
+			}
+		}
+		if let Some(gas) = telegram.gas() {
+			self.gas.record(gas.volume, &[]);
+		}
+	}
+}
+
+impl Default for TelegramMetrics {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "websocket")]
+mod traced {
+	use core::pin::Pin;
+	use core::task::{Context, Poll, ready};
+
+	use futures_util::{Stream, StreamExt};
+	use opentelemetry::global::BoxedSpan;
+	use opentelemetry::trace::{Span, Status, Tracer};
+	use opentelemetry::{KeyValue, global};
+
+	use super::SCOPE_NAME;
+	use crate::Bytes;
+	use crate::websocket::{ConnectError, StreamError, WebsocketEnergyDongle, WebsocketEnergyDongleBuilder};
+
+	/// Connects to the dongle recording the attempt as the `energy_dongle.connect` span.
+	///
+	/// The returned [TracedDongle] records the lifetime of the established connection as the `energy_dongle.connection` span.
+	/// Both spans are created with the global tracer provider and have the `server.address` and `url.path` attributes, the
+	/// failures are recorded as span errors with the `error.code` attribute, see [ConnectError::code()].
+	pub async fn connect_traced(builder: WebsocketEnergyDongleBuilder) -> Result<TracedDongle, ConnectError> {
+		let tracer = global::tracer(SCOPE_NAME);
+		let attributes = vec![
+			KeyValue::new(
+				"server.address",
+				builder
+					.addrs()
+					.iter()
+					.map(|addr| addr.to_string())
+					.collect::<Vec<_>>()
+					.join(","),
+			),
+			KeyValue::new("url.path", builder.path().to_string()),
+		];
+		let mut span = tracer.start("energy_dongle.connect");
+		span.set_attributes(attributes.clone());
+		match builder.connect().await {
+			Ok(dongle) => {
+				span.set_status(Status::Ok);
+				span.end();
+				let mut span = tracer.start("energy_dongle.connection");
+				span.set_attributes(attributes);
+				Ok(TracedDongle {
+					inner: dongle,
+					span: Some(span),
+				})
+			}
+			Err(err) => {
+				span.record_error(&err);
+				span.set_attribute(KeyValue::new("error.code", i64::from(err.code())));
+				span.set_status(Status::error(err.to_string()));
+				span.end();
+				Err(err)
+			}
+		}
+	}
+
+	/// Wrapper for [WebsocketEnergyDongle] that records its lifetime as an OpenTelemetry span, created by [connect_traced()].
+	///
+	/// Implements [Stream] the same way as the wrapped connection. Every stream error is recorded as a span event, the span ends
+	/// when the stream finishes or when the wrapper is dropped.
+	pub struct TracedDongle {
+		inner: WebsocketEnergyDongle,
+		span: Option<BoxedSpan>,
+	}
+
+	impl TracedDongle {
+		/// Returns the wrapped connection.
+		pub fn get_ref(&self) -> &WebsocketEnergyDongle {
+			&self.inner
+		}
+
+		/// Ends the span and returns the wrapped connection.
+		pub fn into_inner(self) -> WebsocketEnergyDongle {
+			if let Some(mut span) = self.span {
+				span.end();
+			}
+			self.inner
+		}
+	}
+
+	impl Stream for TracedDongle {
+		type Item = Result<Bytes, StreamError>;
+
+		fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+			let res = ready!(self.inner.poll_next_unpin(cx));
+			if let Some(span) = &mut self.span {
+				match &res {
+					Some(Ok(_)) => {}
+					Some(Err(err)) => {
+						span.record_error(err);
+						span.set_attribute(KeyValue::new("error.code", i64::from(err.code())));
+						span.set_status(Status::error(err.to_string()));
+					}
+					None => {
+						span.add_event("energy_dongle.closed", vec![]);
+						span.end();
+						self.span = None;
+					}
+				}
+			}
+			Poll::Ready(res)
+		}
+	}
+}
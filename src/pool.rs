@@ -0,0 +1,680 @@
+use std::error::Error;
+use std::fmt;
+use std::net::SocketAddr;
+use std::pin::{Pin, pin};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_util::future::{Either, select};
+use futures_util::{SinkExt, Stream, StreamExt};
+use log::{debug, trace, warn};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::Bytes;
+use crate::clock::{Clock, SystemClock};
+use crate::reader::{RawTelegram, RawTelegramReader};
+use crate::reconnect::{ExponentialBackoff, ReconnectEvent, ReconnectPolicy, reconnect_loop};
+use crate::telegram::Telegram;
+use crate::websocket::{Message, StreamError, WebsocketEnergyDongle};
+
+/// Address of a Homey Energy Dongle served by [ConnectionPool].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolEndpoint {
+	/// Socket addresses of the dongle, they are raced when connecting, see
+	/// [WebsocketEnergyDongle::builder_with_addresses()]
+	pub addrs: Vec<SocketAddr>,
+	/// WebSocket URL path, usually "/ws"
+	pub path: String,
+}
+
+impl PoolEndpoint {
+	pub fn new(addrs: impl IntoIterator<Item = SocketAddr>, path: impl Into<String>) -> Self {
+		Self {
+			addrs: addrs.into_iter().collect(),
+			path: path.into(),
+		}
+	}
+}
+
+#[cfg(feature = "discover")]
+impl From<crate::discover::EnergyDongleHostInfo> for PoolEndpoint {
+	fn from(info: crate::discover::EnergyDongleHostInfo) -> Self {
+		Self {
+			addrs: info.socket_addresses().collect(),
+			path: info.path,
+		}
+	}
+}
+
+/// Pool of warm connections to one or more Homey Energy Dongles for relay and aggregator deployments.
+///
+/// The pool keeps the configured number of connections open, spreading them across the endpoints. Every connection is checked
+/// periodically: the dongle must answer the WebSocket ping and keep sending the telegrams. A connection that fails the check is
+/// closed and reopened to the next endpoint, and its subscribers are moved to the healthy connection with the least subscribers.
+///
+/// The background tasks are stopped when the pool is dropped. Must be created within the context of a Tokio runtime.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use homey_energy_dongle::pool::{ConnectionPool, PoolEndpoint};
+///
+/// async fn example() {
+///     let pool = ConnectionPool::builder([
+///         PoolEndpoint::new(["192.168.1.10:80".parse().unwrap()], "/ws"),
+///         PoolEndpoint::new(["192.168.1.11:80".parse().unwrap()], "/ws"),
+///     ])
+///     .start();
+///     let mut telegrams = pool.subscribe();
+///     while let Some(telegram) = telegrams.next().await {
+///         dbg!(telegram);
+///     }
+/// }
+/// ```
+pub struct ConnectionPool {
+	shared: Arc<Shared>,
+	tasks: Vec<JoinHandle<()>>,
+}
+
+impl ConnectionPool {
+	/// Returns the builder to configure and start the pool.
+	pub fn builder(endpoints: impl IntoIterator<Item = PoolEndpoint>) -> ConnectionPoolBuilder {
+		let endpoints = endpoints.into_iter().collect::<Vec<_>>();
+		ConnectionPoolBuilder {
+			size: endpoints.len(),
+			endpoints,
+			health_check_interval: Duration::from_secs(10),
+			ping_timeout: Duration::from_secs(5),
+			max_telegram_age: Duration::from_secs(30),
+			reconnect_policy: Arc::new(ExponentialBackoff::default()),
+			clock: Arc::new(SystemClock),
+			subscriber_capacity: 16,
+		}
+	}
+
+	/// Returns a new subscription to the telegrams.
+	///
+	/// The subscription is served by the healthy connection with the least subscribers. Until there is a healthy connection, no
+	/// telegrams are delivered.
+	pub fn subscribe(&self) -> PoolSubscription {
+		self.add_subscriber(None)
+	}
+
+	/// Returns a new subscription that only receives the telegrams accepted by `filter`.
+	///
+	/// The filter runs in the connection task, so the rejected telegrams don't wake the subscriber. This keeps the low-priority
+	/// consumers idle until something they are interested in changes.
+	///
+	/// # Example
+	/// ```no_run
+	/// use futures_util::StreamExt;
+	/// use homey_energy_dongle::pool::{ConnectionPool, PoolEndpoint, TelegramFilter};
+	///
+	/// async fn example(pool: ConnectionPool) {
+	///     // only wake up when the power changes by more than 50 W
+	///     let mut telegrams = pool.subscribe_filtered(TelegramFilter::power_change(0.05));
+	///     while let Some(telegram) = telegrams.next().await {
+	///         dbg!(telegram);
+	///     }
+	/// }
+	/// ```
+	pub fn subscribe_filtered(&self, filter: TelegramFilter) -> PoolSubscription {
+		self.add_subscriber(Some(filter))
+	}
+
+	fn add_subscriber(&self, filter: Option<TelegramFilter>) -> PoolSubscription {
+		let (sender, receiver) = mpsc::channel(self.shared.subscriber_capacity);
+		let mut state = self.shared.state();
+		state.subscribers.push(Subscriber {
+			sender,
+			connection: None,
+			filter: filter.map(|filter| Arc::new(Mutex::new(filter))),
+		});
+		state.rebalance();
+		PoolSubscription { receiver }
+	}
+
+	/// Returns the status of every connection in the pool.
+	pub fn connections(&self) -> Vec<PooledConnectionStatus> {
+		let state = self.shared.state();
+		state
+			.connections
+			.iter()
+			.enumerate()
+			.map(|(i, connection)| PooledConnectionStatus {
+				endpoint: self.shared.endpoints[connection.endpoint].clone(),
+				healthy: connection.healthy,
+				last_telegram: connection.last_telegram,
+				subscribers: state.subscriber_count(i),
+			})
+			.collect()
+	}
+}
+
+impl Drop for ConnectionPool {
+	fn drop(&mut self) {
+		for task in &self.tasks {
+			task.abort();
+		}
+	}
+}
+
+/// Builder for [ConnectionPool], created by [ConnectionPool::builder()].
+#[derive(Clone)]
+pub struct ConnectionPoolBuilder {
+	endpoints: Vec<PoolEndpoint>,
+	size: usize,
+	health_check_interval: Duration,
+	ping_timeout: Duration,
+	max_telegram_age: Duration,
+	reconnect_policy: Arc<dyn ReconnectPolicy>,
+	clock: Arc<dyn Clock + Send + Sync>,
+	subscriber_capacity: usize,
+}
+
+impl ConnectionPoolBuilder {
+	/// Number of the connections to keep open, one per endpoint by default.
+	///
+	/// Keep in mind that the Homey Energy Dongle supports a maximum of 2 concurrent connections.
+	pub fn size(mut self, size: usize) -> Self {
+		self.size = size;
+		self
+	}
+
+	/// Interval between the health checks of every connection, 10 seconds by default.
+	pub fn health_check_interval(mut self, interval: Duration) -> Self {
+		self.health_check_interval = interval;
+		self
+	}
+
+	/// Time for the dongle to answer the ping, 5 seconds by default.
+	pub fn ping_timeout(mut self, timeout: Duration) -> Self {
+		self.ping_timeout = timeout;
+		self
+	}
+
+	/// Maximum time since the last telegram for the connection to be considered healthy, 30 seconds by default.
+	pub fn max_telegram_age(mut self, age: Duration) -> Self {
+		self.max_telegram_age = age;
+		self
+	}
+
+	/// Policy that decides when to reopen a closed connection, [ExponentialBackoff::default()] by default.
+	///
+	/// When the policy gives up, the connection stays closed.
+	pub fn reconnect_policy(mut self, policy: impl ReconnectPolicy + 'static) -> Self {
+		self.reconnect_policy = Arc::new(policy);
+		self
+	}
+
+	/// Source of the time for the telegram freshness check and the connection uptime passed to the reconnect policy, [SystemClock]
+	/// by default, see [clock](crate::clock).
+	pub fn clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Number of telegrams buffered for every subscriber, 16 by default. When the buffer is full, new telegrams are dropped.
+	pub fn subscriber_capacity(mut self, capacity: usize) -> Self {
+		self.subscriber_capacity = capacity;
+		self
+	}
+
+	/// Starts the background tasks and returns the pool.
+	///
+	/// Must be called within the context of a Tokio runtime. The pool doesn't open any connections if there are no endpoints.
+	pub fn start(self) -> ConnectionPool {
+		let size = if self.endpoints.is_empty() {
+			0
+		} else {
+			self.size
+		};
+		let shared = Arc::new(Shared {
+			state: Mutex::new(PoolState {
+				connections: (0..size)
+					.map(|i| ConnectionSlot {
+						endpoint: i % self.endpoints.len(),
+						healthy: false,
+						last_telegram: None,
+					})
+					.collect(),
+				subscribers: vec![],
+			}),
+			endpoints: self.endpoints,
+			health_check_interval: self.health_check_interval,
+			ping_timeout: self.ping_timeout,
+			max_telegram_age: self.max_telegram_age,
+			reconnect_policy: self.reconnect_policy,
+			clock: self.clock,
+			subscriber_capacity: self.subscriber_capacity.max(1),
+		});
+		let tasks = (0..size)
+			.map(|slot| tokio::spawn(maintain_connection(Arc::clone(&shared), slot)))
+			.collect();
+		ConnectionPool { shared, tasks }
+	}
+}
+
+/// Status of a connection in [ConnectionPool].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PooledConnectionStatus {
+	/// Endpoint that the connection is currently using
+	pub endpoint: PoolEndpoint,
+	/// Whether the connection passed the last health check
+	pub healthy: bool,
+	/// Time of the last telegram received over the connection
+	pub last_telegram: Option<Instant>,
+	/// Number of the subscriptions served by the connection
+	pub subscribers: usize,
+}
+
+/// Subscription to the telegrams from [ConnectionPool], implements [Stream] over [RawTelegram].
+///
+/// The stream finishes when the pool is dropped.
+#[derive(Debug)]
+pub struct PoolSubscription {
+	receiver: mpsc::Receiver<RawTelegram>,
+}
+
+impl Stream for PoolSubscription {
+	type Item = RawTelegram;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.receiver.poll_recv(cx)
+	}
+}
+
+/// Field-level filter of the telegrams delivered to a subscription, see [ConnectionPool::subscribe_filtered()].
+///
+/// The filter receives the parsed telegrams, the telegrams that fail to parse are not delivered to the filtered subscriptions.
+pub struct TelegramFilter {
+	accept: Box<dyn FnMut(&Telegram) -> bool + Send>,
+}
+
+impl TelegramFilter {
+	/// Creates the filter that delivers the telegrams for which `accept` returns `true`.
+	///
+	/// The closure can keep state, e.g. the last delivered value, it's called for every telegram in the order of reception.
+	pub fn new(accept: impl FnMut(&Telegram) -> bool + Send + 'static) -> Self {
+		Self {
+			accept: Box::new(accept),
+		}
+	}
+
+	/// Delivers the telegrams where the net power differs from the last delivered one by more than `threshold` kW.
+	///
+	/// The first telegram with the power readings is always delivered.
+	pub fn power_change(threshold: f64) -> Self {
+		let mut last_power = None;
+		Self::new(move |telegram| {
+			let Some(power) = telegram.net_metering().map(|net| net.net_power()) else {
+				return false;
+			};
+			if last_power.is_some_and(|last_power: f64| (power - last_power).abs() <= threshold) {
+				return false;
+			}
+			last_power = Some(power);
+			true
+		})
+	}
+
+	/// Delivers the telegrams with a new gas meter reading, the gas meter usually reports once per 5 minutes or once per hour.
+	pub fn gas_updates() -> Self {
+		let mut last_reading = None;
+		Self::new(move |telegram| {
+			let reading = telegram.gas();
+			if reading.is_none() || reading == last_reading {
+				return false;
+			}
+			last_reading = reading;
+			true
+		})
+	}
+
+	fn accept(&mut self, telegram: &Telegram) -> bool {
+		(self.accept)(telegram)
+	}
+}
+
+impl fmt::Debug for TelegramFilter {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TelegramFilter").finish_non_exhaustive()
+	}
+}
+
+struct Shared {
+	state: Mutex<PoolState>,
+	endpoints: Vec<PoolEndpoint>,
+	health_check_interval: Duration,
+	ping_timeout: Duration,
+	max_telegram_age: Duration,
+	reconnect_policy: Arc<dyn ReconnectPolicy>,
+	clock: Arc<dyn Clock + Send + Sync>,
+	subscriber_capacity: usize,
+}
+
+impl Shared {
+	fn state(&self) -> MutexGuard<'_, PoolState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+#[derive(Debug)]
+struct PoolState {
+	connections: Vec<ConnectionSlot>,
+	subscribers: Vec<Subscriber>,
+}
+
+impl PoolState {
+	fn subscriber_count(&self, connection: usize) -> usize {
+		self
+			.subscribers
+			.iter()
+			.filter(|subscriber| subscriber.connection == Some(connection))
+			.count()
+	}
+
+	fn set_healthy(&mut self, connection: usize, healthy: bool) {
+		if self.connections[connection].healthy != healthy {
+			self.connections[connection].healthy = healthy;
+			self.rebalance();
+		}
+	}
+
+	/// Moves the subscribers of the unhealthy connections to the healthy connections with the least subscribers.
+	fn rebalance(&mut self) {
+		self.remove_closed();
+		for i in 0..self.subscribers.len() {
+			if let Some(connection) = self.subscribers[i].connection {
+				if self.connections[connection].healthy {
+					continue;
+				}
+			}
+			let least_loaded = (0..self.connections.len())
+				.filter(|&connection| self.connections[connection].healthy)
+				.min_by_key(|&connection| self.subscriber_count(connection));
+			if least_loaded.is_some() && self.subscribers[i].connection != least_loaded {
+				debug!(
+					"Moving pool subscriber from connection {:?} to {least_loaded:?}",
+					self.subscribers[i].connection
+				);
+			}
+			self.subscribers[i].connection = least_loaded;
+		}
+	}
+
+	/// Marks `connection` as alive after it received a telegram and returns a copy of its subscribers.
+	fn received(&mut self, connection: usize, clock: &dyn Clock) -> Vec<Subscriber> {
+		self.connections[connection].last_telegram = Some(clock.now());
+		self.set_healthy(connection, true);
+		self
+			.subscribers
+			.iter()
+			.filter(|subscriber| subscriber.connection == Some(connection))
+			.cloned()
+			.collect()
+	}
+
+	fn remove_closed(&mut self) {
+		self.subscribers.retain(|subscriber| !subscriber.sender.is_closed());
+	}
+}
+
+#[derive(Debug)]
+struct ConnectionSlot {
+	/// Index of the endpoint in [Shared::endpoints]
+	endpoint: usize,
+	healthy: bool,
+	last_telegram: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+struct Subscriber {
+	sender: mpsc::Sender<RawTelegram>,
+	connection: Option<usize>,
+	/// Shared with the copies of the subscriber list that the connection tasks deliver to
+	filter: Option<Arc<Mutex<TelegramFilter>>>,
+}
+
+/// Delivers the telegrams received by `connection` to its subscribers.
+///
+/// The lock is only held to copy the subscriber list, so the parsing and the filters don't block the other connections.
+fn deliver(state: &Mutex<PoolState>, clock: &dyn Clock, connection: usize, telegrams: Vec<RawTelegram>) {
+	if telegrams.is_empty() {
+		return;
+	}
+	let subscribers = state
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.received(connection, clock);
+	let filtered = subscribers.iter().any(|subscriber| subscriber.filter.is_some());
+	for telegram in telegrams {
+		// parse only once for all filtered subscribers
+		let parsed = filtered.then(|| telegram.parse().ok()).flatten();
+		for subscriber in &subscribers {
+			if let Some(filter) = &subscriber.filter {
+				let mut filter = filter.lock().unwrap_or_else(PoisonError::into_inner);
+				if !parsed.as_ref().is_some_and(|parsed| filter.accept(parsed)) {
+					continue;
+				}
+			}
+			if let Err(mpsc::error::TrySendError::Full(_)) = subscriber.sender.try_send(telegram.clone()) {
+				trace!("Pool subscriber is lagging, dropping the telegram");
+			}
+		}
+	}
+	if subscribers.iter().any(|subscriber| subscriber.sender.is_closed()) {
+		state.lock().unwrap_or_else(PoisonError::into_inner).remove_closed();
+	}
+}
+
+async fn maintain_connection(shared: Arc<Shared>, slot: usize) {
+	let shared = &*shared;
+	reconnect_loop(
+		&shared.reconnect_policy,
+		&*shared.clock,
+		None,
+		|| {
+			let endpoint = &shared.endpoints[shared.state().connections[slot].endpoint];
+			WebsocketEnergyDongle::builder_with_addresses(endpoint.addrs.iter().copied(), &endpoint.path).connect()
+		},
+		|dongle| async move {
+			run_connection(shared, slot, dongle)
+				.await
+				.map(|err| Box::new(err) as Box<dyn Error + Send + Sync>)
+		},
+		|event| match event {
+			ReconnectEvent::Connecting { attempt } => {
+				if attempt > 0 {
+					debug!("Reconnecting pooled connection {slot}, attempt {attempt}");
+				}
+			}
+			ReconnectEvent::Connected => {}
+			ReconnectEvent::ConnectFailed(err) => {
+				let endpoint = &shared.endpoints[shared.state().connections[slot].endpoint];
+				warn!("Pooled connection to {:?} failed: {err}", endpoint.addrs);
+				switch_endpoint(shared, slot);
+			}
+			ReconnectEvent::Disconnected => switch_endpoint(shared, slot),
+			ReconnectEvent::GaveUp { attempts } => warn!("Giving up on the pooled connection after {attempts} attempts"),
+		},
+	)
+	.await;
+}
+
+/// Marks the connection as unhealthy and moves it to the next endpoint to spread the load when a dongle misbehaves.
+fn switch_endpoint(shared: &Shared, slot: usize) {
+	let mut state = shared.state();
+	state.set_healthy(slot, false);
+	let connection = &mut state.connections[slot];
+	connection.endpoint = (connection.endpoint + 1) % shared.endpoints.len();
+	connection.last_telegram = None;
+}
+
+/// Reads the telegrams until the connection fails or closes, returns the error that caused it if any.
+async fn run_connection(shared: &Shared, slot: usize, mut dongle: WebsocketEnergyDongle) -> Option<StreamError> {
+	let mut reader = RawTelegramReader::new();
+	let connected_at = shared.clock.now();
+	let mut ping_sent = None;
+	let mut health_check = tokio::time::interval_at(
+		tokio::time::Instant::now() + shared.health_check_interval,
+		shared.health_check_interval,
+	);
+	loop {
+		let event = match select(dongle.next(), pin!(health_check.tick())).await {
+			Either::Left((item, _)) => Either::Left(item),
+			Either::Right((now, _)) => Either::Right(now.into_std()),
+		};
+		match event {
+			Either::Left(Some(Ok(bytes))) => {
+				deliver(&shared.state, &*shared.clock, slot, reader.feed(&bytes));
+			}
+			Either::Left(Some(Err(err))) => {
+				warn!("Pooled connection failed: {err}");
+				return Some(err);
+			}
+			Either::Left(None) => {
+				debug!("Pooled connection is closed by the dongle");
+				return None;
+			}
+			Either::Right(now) => {
+				let last_telegram = shared.state().connections[slot].last_telegram.unwrap_or(connected_at);
+				let fresh = shared.clock.now().saturating_duration_since(last_telegram) <= shared.max_telegram_age;
+				// the pong is timestamped by the connection itself, so the ping is timed with the runtime clock
+				let unanswered_ping = ping_sent.filter(|&sent| dongle.last_pong().is_none_or(|pong| pong < sent));
+				let responsive = unanswered_ping.is_none_or(|sent| now.saturating_duration_since(sent) < shared.ping_timeout);
+				if !fresh || !responsive {
+					warn!("Pooled connection failed the health check, telegrams fresh: {fresh}, ping answered: {responsive}");
+					return None;
+				}
+				if unanswered_ping.is_none() {
+					if let Err(err) = dongle.send(Message::Ping(Bytes::new())).await {
+						warn!("Failed to ping the dongle: {err}");
+						return Some(err);
+					}
+					ping_sent = Some(now);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_telegrams::{DSMR5, with_crc};
+
+	fn state(healthy: &[bool]) -> PoolState {
+		PoolState {
+			connections: healthy
+				.iter()
+				.map(|&healthy| ConnectionSlot {
+					endpoint: 0,
+					healthy,
+					last_telegram: None,
+				})
+				.collect(),
+			subscribers: vec![],
+		}
+	}
+
+	fn subscribe(state: &mut PoolState) -> mpsc::Receiver<RawTelegram> {
+		subscribe_filtered(state, None)
+	}
+
+	fn subscribe_filtered(state: &mut PoolState, filter: Option<TelegramFilter>) -> mpsc::Receiver<RawTelegram> {
+		let (sender, receiver) = mpsc::channel(4);
+		state.subscribers.push(Subscriber {
+			sender,
+			connection: None,
+			filter: filter.map(|filter| Arc::new(Mutex::new(filter))),
+		});
+		state.rebalance();
+		receiver
+	}
+
+	#[test]
+	fn test_rebalance() {
+		let mut state = state(&[false, false]);
+		let _receivers = (0..4).map(|_| subscribe(&mut state)).collect::<Vec<_>>();
+		assert!(state.subscribers.iter().all(|subscriber| subscriber.connection.is_none()));
+
+		state.set_healthy(0, true);
+		assert_eq!(4, state.subscriber_count(0));
+
+		// new subscribers go to the least loaded connection, the existing ones stay
+		state.set_healthy(1, true);
+		let _receiver = subscribe(&mut state);
+		assert_eq!(4, state.subscriber_count(0));
+		assert_eq!(1, state.subscriber_count(1));
+
+		state.set_healthy(0, false);
+		assert_eq!(0, state.subscriber_count(0));
+		assert_eq!(5, state.subscriber_count(1));
+
+		state.set_healthy(1, false);
+		assert!(state.subscribers.iter().all(|subscriber| subscriber.connection.is_none()));
+	}
+
+	#[test]
+	fn test_deliver() {
+		let mut state = state(&[false, true]);
+		let mut receiver = subscribe(&mut state);
+		let dropped = subscribe(&mut state);
+		drop(dropped);
+		let state = Mutex::new(state);
+		let telegram = || RawTelegram {
+			contents: b"/ABC5\r\n\r\n!".to_vec(),
+		};
+
+		deliver(&state, &SystemClock, 0, vec![telegram()]);
+		assert!(state.lock().unwrap().connections[0].healthy);
+		assert!(state.lock().unwrap().connections[0].last_telegram.is_some());
+		assert!(receiver.try_recv().is_err());
+
+		deliver(&state, &SystemClock, 1, vec![telegram()]);
+		assert!(receiver.try_recv().is_ok());
+		assert_eq!(1, state.lock().unwrap().subscribers.len());
+	}
+
+	#[test]
+	fn test_deliver_filtered() {
+		let mut state = state(&[true]);
+		let mut power = subscribe_filtered(&mut state, Some(TelegramFilter::power_change(0.05)));
+		let mut gas = subscribe_filtered(&mut state, Some(TelegramFilter::gas_updates()));
+		let mut all = subscribe(&mut state);
+		let state = Mutex::new(state);
+		let telegram = |power: &str, gas: &str| RawTelegram {
+			contents: with_crc(&DSMR5.replace("01.193*kW", power).replace("12785.123*m3", gas)),
+		};
+
+		deliver(
+			&state,
+			&SystemClock,
+			0,
+			vec![
+				telegram("01.193*kW", "12785.123*m3"),
+				telegram("01.213*kW", "12785.123*m3"),
+				telegram("01.293*kW", "12785.123*m3"),
+			],
+		);
+		deliver(&state, &SystemClock, 0, vec![telegram("01.293*kW", "12786.000*m3")]);
+		let count = |receiver: &mut mpsc::Receiver<RawTelegram>| std::iter::from_fn(|| receiver.try_recv().ok()).count();
+		assert_eq!(4, count(&mut all));
+
+		// the unparseable telegrams are only delivered to the subscribers without a filter
+		deliver(
+			&state,
+			&SystemClock,
+			0,
+			vec![RawTelegram {
+				contents: b"/ABC5\r\n\r\n!".to_vec(),
+			}],
+		);
+		assert_eq!(1, count(&mut all));
+		assert_eq!(2, count(&mut power));
+		assert_eq!(2, count(&mut gas));
+	}
+}
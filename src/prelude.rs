@@ -0,0 +1,16 @@
+//! Re-exports of the most commonly used types, `use homey_energy_dongle::prelude::*;` brings them into scope.
+//!
+//! The items are only included when the feature that provides them is enabled.
+
+#[cfg(feature = "client")]
+pub use crate::client::{ClientError, EnergyDongleClient, TelegramStream};
+#[cfg(feature = "discover")]
+pub use crate::discover::{EnergyDongleHostInfo, discover_devices_with_mdns};
+#[cfg(feature = "manager")]
+pub use crate::manager::{DongleManager, DongleTelegram};
+pub use crate::parse_telegram;
+pub use crate::reader::{RawTelegram, RawTelegramReader, RawTelegramStream};
+pub use crate::reconnect::{ConnectionState, ExponentialBackoff, ReconnectPolicy};
+pub use crate::telegram::{Obis, ParseError, Telegram};
+#[cfg(feature = "websocket")]
+pub use crate::websocket::{ConnectError, StreamError, WebsocketEnergyDongle};
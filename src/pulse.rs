@@ -0,0 +1,69 @@
+//! Counters read outside of the P1 port.
+//!
+//! Water meters and older gas meters are often not connected to the M-Bus of the electricity meter, their consumption is read as
+//! pulses from the S0 or dry-contact output by another device. [PulseCounter] converts the pulse count to a [CounterReading] that
+//! can be recorded in the [EnergyReport](crate::report::EnergyReport) together with the telegrams.
+
+use crate::telegram::Timestamp;
+
+/// Quantity measured by an external counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Medium {
+	/// Gas volume in m³
+	Gas,
+	/// Water volume in m³
+	Water,
+}
+
+/// Cumulative reading of an external counter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterReading {
+	pub medium: Medium,
+	/// Total volume in m³
+	pub value: f64,
+	/// Time of the reading in the local time of the meter
+	pub timestamp: Timestamp,
+}
+
+/// Converts the pulse count of an S0 or dry-contact counter to the meter reading.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::pulse::{Medium, PulseCounter};
+///
+/// let counter = PulseCounter::new(Medium::Water, 1000.).with_offset(123.);
+/// let reading = counter.reading(2500, "240101120000W".parse().unwrap());
+/// assert_eq!(125.5, reading.value);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PulseCounter {
+	medium: Medium,
+	pulses_per_unit: f64,
+	offset: f64,
+}
+
+impl PulseCounter {
+	/// Creates the counter of `medium` that sends `pulses_per_unit` pulses per m³, e.g. 1000 for the common water meters.
+	pub fn new(medium: Medium, pulses_per_unit: f64) -> Self {
+		Self {
+			medium,
+			pulses_per_unit,
+			offset: 0.,
+		}
+	}
+
+	/// Meter reading at the moment the pulse count was 0, so that the readings match the meter display, 0 by default.
+	pub fn with_offset(mut self, offset: f64) -> Self {
+		self.offset = offset;
+		self
+	}
+
+	/// Returns the reading for the total `pulses` counted at `timestamp`.
+	pub fn reading(&self, pulses: u64, timestamp: Timestamp) -> CounterReading {
+		CounterReading {
+			medium: self.medium,
+			value: self.offset + pulses as f64 / self.pulses_per_unit,
+			timestamp,
+		}
+	}
+}
@@ -1,28 +1,246 @@
 use std::collections::VecDeque;
+use std::io::IoSlice;
 use std::pin::Pin;
+use std::str;
 use std::task::{Context, Poll, ready};
 
 use futures_util::Stream;
-
-use crate::Bytes;
+use log::warn;
 
 /// Raw bytes of a single DSMR telegram exposed in the public `contents` field.
 ///
 /// Instances of [RawTelegram] produced by [RawTelegramStream] are guaranteed to contain only bytes of a single telegram. This
-/// includes the header ("/ID") and footer with CRC and terminating CRLF ("/CRC\r\n").
+/// includes the header ("/ID") and footer with CRC and terminating CRLF ("!CRC\r\n"). Legacy DSMR 2.2 and 3.0 telegrams don't
+/// include the CRC, so their footer is just "!\r\n".
 ///
 /// [RawTelegram] implements `AsRef<[u8]>` for a convenient usage as a byte slice.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RawTelegram {
 	pub contents: Vec<u8>,
 }
 
+impl RawTelegram {
+	/// Verify the CRC from the telegram footer against the telegram contents.
+	///
+	/// The CRC is CRC16/ARC calculated over all bytes starting from the "/" of the header up to and including the "!" of the
+	/// footer.
+	pub fn check_crc(&self) -> CrcCheck {
+		let Some(footer_offset) = find_line_starting_with(&self.contents, b'!') else {
+			return CrcCheck::Malformed;
+		};
+		let (checked, footer) = self.contents.split_at(footer_offset + 1);
+		let crc = footer.strip_suffix(b"\r\n").unwrap_or(footer);
+		if crc.is_empty() {
+			return CrcCheck::Missing;
+		}
+		if crc.len() != 4 {
+			return CrcCheck::Malformed;
+		}
+		let Some(expected) = str::from_utf8(crc).ok().and_then(|crc| u16::from_str_radix(crc, 16).ok()) else {
+			return CrcCheck::Malformed;
+		};
+		if crc16(checked) == expected {
+			CrcCheck::Valid
+		} else {
+			CrcCheck::Mismatch
+		}
+	}
+}
+
 impl AsRef<[u8]> for RawTelegram {
 	fn as_ref(&self) -> &[u8] {
 		&self.contents
 	}
 }
 
+/// Generates a DSMR 5 telegram with the correct CRC and a random selection of the common electricity and gas objects.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for RawTelegram {
+	fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+		fn digits(u: &mut arbitrary::Unstructured, count: usize) -> arbitrary::Result<String> {
+			(0..count).map(|_| u.int_in_range(b'0'..=b'9').map(char::from)).collect()
+		}
+
+		fn timestamp(u: &mut arbitrary::Unstructured) -> arbitrary::Result<String> {
+			Ok(format!(
+				"{}{:02}{:02}{:02}{:02}{:02}{}",
+				digits(u, 2)?,
+				u.int_in_range(1..=12)?,
+				u.int_in_range(1..=28)?,
+				u.int_in_range(0..=23)?,
+				u.int_in_range(0..=59)?,
+				u.int_in_range(0..=59)?,
+				if u.arbitrary()? {
+					'S'
+				} else {
+					'W'
+				},
+			))
+		}
+
+		fn equipment_id(u: &mut arbitrary::Unstructured) -> arbitrary::Result<String> {
+			let len = u.int_in_range(0..=34)?;
+			Ok(digits(u, len)?.bytes().map(|digit| format!("{digit:02X}")).collect())
+		}
+
+		let manufacturer = (0..3)
+			.map(|_| u.int_in_range(b'A'..=b'Z').map(char::from))
+			.collect::<arbitrary::Result<String>>()?;
+		let ident_len = u.int_in_range(1..=16)?;
+		let mut lines = vec![
+			format!("/{manufacturer}5{}", digits(u, ident_len)?),
+			String::new(),
+			"1-3:0.2.8(50)".to_string(),
+			format!("0-0:1.0.0({})", timestamp(u)?),
+			format!("0-0:96.1.1({})", equipment_id(u)?),
+		];
+		for register in ["1-0:1.8.1", "1-0:1.8.2", "1-0:2.8.1", "1-0:2.8.2"] {
+			lines.push(format!("{register}({}.{}*kWh)", digits(u, 6)?, digits(u, 3)?));
+		}
+		lines.push(format!("0-0:96.14.0({:04})", u.int_in_range(1..=2)?));
+		for register in ["1-0:1.7.0", "1-0:2.7.0"] {
+			lines.push(format!("{register}({}.{}*kW)", digits(u, 2)?, digits(u, 3)?));
+		}
+		let phases = if u.arbitrary()? {
+			3
+		} else {
+			1
+		};
+		for phase in 1..=phases {
+			lines.push(format!(
+				"1-0:{}.7.0({}.{}*V)",
+				12 + phase * 20,
+				u.int_in_range(200..=260)?,
+				digits(u, 1)?
+			));
+			lines.push(format!("1-0:{}.7.0({}*A)", 11 + phase * 20, digits(u, 3)?));
+		}
+		if u.arbitrary()? {
+			lines.push("0-1:24.1.0(003)".to_string());
+			lines.push(format!("0-1:96.1.0({})", equipment_id(u)?));
+			lines.push(format!(
+				"0-1:24.2.1({})({}.{}*m3)",
+				timestamp(u)?,
+				digits(u, 5)?,
+				digits(u, 3)?
+			));
+		}
+		lines.push("!".to_string());
+		let mut contents = lines.join("\r\n");
+		let crc = crc16(contents.as_bytes());
+		contents.push_str(&format!("{crc:04X}\r\n"));
+		Ok(RawTelegram {
+			contents: contents.into_bytes(),
+		})
+	}
+}
+
+/// Serialized as a string when the contents are valid ASCII, which is always the case for the well-formed telegrams. Otherwise,
+/// it's serialized as a map with a single `base64` key holding the Base64-encoded contents. The non-human-readable formats store
+/// the contents as bytes.
+#[cfg(feature = "serde")]
+impl serde::Serialize for RawTelegram {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		use base64::Engine;
+		use serde::ser::SerializeMap;
+
+		if !serializer.is_human_readable() {
+			serializer.serialize_bytes(&self.contents)
+		} else if self.contents.is_ascii() {
+			serializer.serialize_str(str::from_utf8(&self.contents).expect("ASCII is valid UTF-8"))
+		} else {
+			let mut map = serializer.serialize_map(Some(1))?;
+			map.serialize_entry("base64", &base64::engine::general_purpose::STANDARD.encode(&self.contents))?;
+			map.end()
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RawTelegram {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use std::fmt;
+
+		use base64::Engine;
+		use serde::de::{self, MapAccess, Visitor};
+
+		struct RawTelegramVisitor;
+
+		impl<'de> Visitor<'de> for RawTelegramVisitor {
+			type Value = RawTelegram;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a telegram string, bytes or a map with the base64 key")
+			}
+
+			fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+				Ok(RawTelegram {
+					contents: v.as_bytes().to_vec(),
+				})
+			}
+
+			fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+				Ok(RawTelegram { contents: v.to_vec() })
+			}
+
+			fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+				Ok(RawTelegram { contents: v })
+			}
+
+			fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+				let mut contents = None;
+				while let Some(key) = map.next_key::<String>()? {
+					if key != "base64" {
+						return Err(de::Error::unknown_field(&key, &["base64"]));
+					}
+					if contents.is_some() {
+						return Err(de::Error::duplicate_field("base64"));
+					}
+					let encoded = map.next_value::<String>()?;
+					contents = Some(
+						base64::engine::general_purpose::STANDARD
+							.decode(encoded)
+							.map_err(de::Error::custom)?,
+					);
+				}
+				let contents = contents.ok_or_else(|| de::Error::missing_field("base64"))?;
+				Ok(RawTelegram { contents })
+			}
+		}
+
+		if deserializer.is_human_readable() {
+			deserializer.deserialize_any(RawTelegramVisitor)
+		} else {
+			deserializer.deserialize_byte_buf(RawTelegramVisitor)
+		}
+	}
+}
+
+/// Result of the [RawTelegram::check_crc()] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrcCheck {
+	/// CRC in the footer matches the telegram contents
+	Valid,
+	/// CRC in the footer doesn't match the telegram contents
+	Mismatch,
+	/// Telegram footer doesn't contain a CRC, this is normal for DSMR versions before 4.0
+	Missing,
+	/// Telegram footer is not in the expected format
+	Malformed,
+}
+
+impl CrcCheck {
+	/// Returns the lowercase name of the result, e.g. "valid", for use in the labels and serialized formats.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Valid => "valid",
+			Self::Mismatch => "mismatch",
+			Self::Missing => "missing",
+			Self::Malformed => "malformed",
+		}
+	}
+}
+
 /// Buffered DSMR telegram extractor from the partial byte buffers.
 ///
 /// By repeatedly calling [RawTelegramReader::feed] with new bytes, the extractor will return a `Vec` with all new complete
@@ -43,6 +261,7 @@ impl AsRef<[u8]> for RawTelegram {
 #[derive(Default)]
 pub struct RawTelegramReader {
 	partial_telegram: Vec<u8>,
+	max_buffered_len: Option<usize>,
 }
 
 impl RawTelegramReader {
@@ -50,6 +269,19 @@ impl RawTelegramReader {
 	pub fn new() -> Self {
 		RawTelegramReader {
 			partial_telegram: vec![],
+			max_buffered_len: None,
+		}
+	}
+
+	/// Creates a new [RawTelegramReader] instance that buffers at most `max_buffered_len` bytes of the incomplete telegram.
+	///
+	/// If the incomplete telegram grows beyond that limit, the buffered bytes are discarded. This protects against the unbounded
+	/// memory growth when the device keeps sending data without the telegram footer. Make sure that the limit is large enough for
+	/// the longest expected telegram, a DSMR 5 telegram with a full text message is around 3 KB.
+	pub fn with_max_buffered_len(max_buffered_len: usize) -> Self {
+		RawTelegramReader {
+			partial_telegram: vec![],
+			max_buffered_len: Some(max_buffered_len),
 		}
 	}
 
@@ -84,33 +316,138 @@ impl RawTelegramReader {
 		} else {
 			self.partial_telegram.drain(..self.partial_telegram.len() - rest.len());
 		}
+		if let Some(max_buffered_len) = self.max_buffered_len {
+			if self.partial_telegram.len() > max_buffered_len {
+				warn!(
+					"Discarding {} buffered bytes of the incomplete telegram, the limit is {max_buffered_len} bytes",
+					self.partial_telegram.len()
+				);
+				self.partial_telegram.clear();
+			}
+		}
 		out
 	}
+
+	/// Add the bytes of all `bufs` in order and return a `Vec` of all found complete DSMR telegrams.
+	///
+	/// The result is the same as calling [RawTelegramReader::feed()] for every buffer. The buffers are not joined upfront, but the
+	/// incomplete telegram at the end of a buffer is copied into the internal buffer and the next buffers are appended to it
+	/// until the telegram completes, so the telegrams spanning several buffers are still concatenated. Use it for the transports
+	/// that receive the data into scatter/gather buffers or ring-buffer segments.
+	///
+	/// # Example
+	/// ```
+	/// use std::io::IoSlice;
+	///
+	/// use homey_energy_dongle::reader::RawTelegramReader;
+	///
+	/// let mut reader = RawTelegramReader::new();
+	/// let telegrams = reader.feed_vectored(&[IoSlice::new(b"/test\r\n"), IoSlice::new(b"!AAAA\r\n/test2")]);
+	/// assert_eq!(1, telegrams.len());
+	/// assert_eq!(6, reader.buffered_len());
+	/// ```
+	pub fn feed_vectored(&mut self, bufs: &[IoSlice]) -> Vec<RawTelegram> {
+		let mut out = vec![];
+		for buf in bufs {
+			out.append(&mut self.feed(buf));
+		}
+		out
+	}
+
+	/// Discard all buffered bytes of the incomplete telegram.
+	///
+	/// Call this after reconnecting to the dongle so that the leftover bytes from the previous session don't get stitched
+	/// together with the bytes of the new one.
+	pub fn reset(&mut self) {
+		self.partial_telegram.clear();
+	}
+
+	/// Returns the number of bytes currently held in the internal buffer waiting for the telegram to complete.
+	pub fn buffered_len(&self) -> usize {
+		self.partial_telegram.len()
+	}
+
+	/// Returns the bytes currently held in the internal buffer waiting for the telegram to complete.
+	pub fn peek_partial(&self) -> &[u8] {
+		&self.partial_telegram
+	}
 }
 
-/// Wrapper that converts a [Stream] of [Bytes] into a [Stream] of [RawTelegram].
+type BytesTap = Box<dyn FnMut(&[u8]) + Send + Sync>;
+
+/// Wrapper that converts a [Stream] of byte buffers into a [Stream] of [RawTelegram].
 ///
 /// Can be used in conjunction with [crate::websocket::WebsocketEnergyDongle] to convert separate [Bytes] buffers into parsable
-/// DSMR telegrams.
+/// DSMR telegrams. The inner stream can yield any buffer type that implements `AsRef<[u8]>`, e.g. [Bytes], `Vec<u8>`,
+/// `&[u8]`, `Cow<[u8]>` or `String`, so it works with the transports that don't use [Bytes] without extra conversions. For
+/// the message types of other WebSocket libraries map the items to their payload first, e.g., with
+/// `tungstenite::Message::into_data()`.
 ///
 /// See the [crate-level documentation](crate) for more details and examples.
+///
+/// [Bytes]: crate::Bytes
 pub struct RawTelegramStream<S> {
 	reader: RawTelegramReader,
 	ready_telegrams: VecDeque<RawTelegram>,
 	inner: S,
+	tap: Option<BytesTap>,
 }
 
-impl<S: Stream<Item = Bytes>> RawTelegramStream<S> {
+impl<S: Stream<Item: AsRef<[u8]>>> RawTelegramStream<S> {
 	pub fn new(inner: S) -> Self {
+		Self::with_reader(inner, RawTelegramReader::new())
+	}
+
+	/// Creates a new [RawTelegramStream] that uses the supplied `reader`, e.g. one created with
+	/// [RawTelegramReader::with_max_buffered_len()].
+	pub fn with_reader(inner: S, reader: RawTelegramReader) -> Self {
 		RawTelegramStream {
-			reader: RawTelegramReader::new(),
+			reader,
 			ready_telegrams: VecDeque::new(),
 			inner,
+			tap: None,
 		}
 	}
+
+	/// Attaches a tap that is called with every buffer received from the inner stream before the telegrams are extracted
+	/// from it.
+	///
+	/// Use it to capture the raw traffic or to measure the bandwidth without splitting the inner stream manually. The tap
+	/// replaces the previously attached one. The tap must be [Sync], so that the stream stays shareable between the threads.
+	///
+	/// # Example
+	/// ```
+	/// use std::sync::Arc;
+	/// use std::sync::atomic::{AtomicUsize, Ordering};
+	///
+	/// use futures_util::{StreamExt, stream};
+	/// use homey_energy_dongle::Bytes;
+	/// use homey_energy_dongle::reader::RawTelegramStream;
+	///
+	/// # #[tokio::main]
+	/// # async fn main() {
+	/// let received = Arc::new(AtomicUsize::new(0));
+	/// let buffers = stream::iter([Bytes::from_static(b"/test\r\n\r\n"), Bytes::from_static(b"!\r\n")]);
+	/// let telegrams = RawTelegramStream::new(buffers)
+	///     .with_tap({
+	///         let received = Arc::clone(&received);
+	///         move |bytes| {
+	///             received.fetch_add(bytes.len(), Ordering::Relaxed);
+	///         }
+	///     })
+	///     .collect::<Vec<_>>()
+	///     .await;
+	/// assert_eq!(1, telegrams.len());
+	/// assert_eq!(12, received.load(Ordering::Relaxed));
+	/// # }
+	/// ```
+	pub fn with_tap(mut self, tap: impl FnMut(&[u8]) + Send + Sync + 'static) -> Self {
+		self.tap = Some(Box::new(tap));
+		self
+	}
 }
 
-impl<S: Stream<Item = Bytes> + Unpin> Stream for RawTelegramStream<S> {
+impl<S: Stream<Item: AsRef<[u8]>> + Unpin> Stream for RawTelegramStream<S> {
 	type Item = RawTelegram;
 
 	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
@@ -121,7 +458,10 @@ impl<S: Stream<Item = Bytes> + Unpin> Stream for RawTelegramStream<S> {
 			let Some(bytes) = ready!(Pin::new(&mut self.inner).poll_next(cx)) else {
 				return Poll::Ready(None);
 			};
-			let telegrams = self.reader.feed(&bytes);
+			if let Some(tap) = &mut self.tap {
+				tap(bytes.as_ref());
+			}
+			let telegrams = self.reader.feed(bytes.as_ref());
 			if !telegrams.is_empty() {
 				let mut telegrams = telegrams.into_iter();
 				let out = telegrams.next();
@@ -157,6 +497,21 @@ fn extract_telegram(bytes: &[u8]) -> (Option<&[u8]>, &[u8]) {
 	(res.map(|(telegram, _)| telegram), res.map_or(&[], |(_, rest)| rest))
 }
 
+/// CRC16/ARC as used by DSMR 4.0 and later.
+pub(crate) fn crc16(bytes: &[u8]) -> u16 {
+	bytes.iter().fold(0, |crc, &byte| {
+		let mut crc = crc ^ u16::from(byte);
+		for _ in 0..8 {
+			crc = if crc & 1 == 0 {
+				crc >> 1
+			} else {
+				(crc >> 1) ^ 0xA001
+			};
+		}
+		crc
+	})
+}
+
 fn find_line_starting_with(bytes: &[u8], start: u8) -> Option<usize> {
 	let start_line = [b'\n', start];
 	let start_line = start_line.as_slice();
@@ -179,7 +534,9 @@ where
 
 #[cfg(test)]
 mod tests {
-	use super::RawTelegramReader;
+	use std::io::IoSlice;
+
+	use super::{CrcCheck, RawTelegram, RawTelegramReader};
 
 	#[test]
 	fn test_telegram_reader() {
@@ -213,4 +570,160 @@ mod tests {
 			assert_eq!(1, telegrams.len());
 		}
 	}
+
+	#[test]
+	fn test_telegram_reader_vectored() {
+		let mut reader = RawTelegramReader::new();
+		assert!(reader.feed_vectored(&[]).is_empty());
+		let telegrams = reader.feed_vectored(&[
+			IoSlice::new(b"DDDD\r\n/te"),
+			IoSlice::new(b""),
+			IoSlice::new(b"st\r\n!AAAA\r\n/test2\r\n"),
+			IoSlice::new(b"!BBBB\r\n/test3"),
+		]);
+		assert_eq!(2, telegrams.len());
+		assert_eq!(b"/test\r\n!AAAA\r\n", telegrams[0].as_ref());
+		assert_eq!(b"/test2\r\n!BBBB\r\n", telegrams[1].as_ref());
+		assert_eq!(b"/test3", reader.peek_partial());
+	}
+
+	#[test]
+	fn test_telegram_reader_legacy() {
+		let mut reader = RawTelegramReader::new();
+		let telegrams = reader.feed(crate::test_telegrams::DSMR3.as_bytes());
+		assert_eq!(1, telegrams.len());
+		assert_eq!(CrcCheck::Missing, telegrams[0].check_crc());
+		assert_eq!(0, reader.buffered_len());
+		let telegrams = reader.feed(b"/test\r\n1-0:1.8.1(1)\r\n!\r\n/test2\r\n!\r");
+		assert_eq!(1, telegrams.len());
+		let telegrams = reader.feed(b"\n");
+		assert_eq!(1, telegrams.len());
+		assert_eq!(b"/test2\r\n!\r\n", telegrams[0].as_ref());
+	}
+
+	#[test]
+	fn test_telegram_reader_reset() {
+		let mut reader = RawTelegramReader::new();
+		assert_eq!(0, reader.buffered_len());
+		let telegrams = reader.feed(b"/test\r\n1-0:1.8.1(");
+		assert!(telegrams.is_empty());
+		assert_eq!(b"/test\r\n1-0:1.8.1(", reader.peek_partial());
+		assert_eq!(17, reader.buffered_len());
+		reader.reset();
+		assert_eq!(0, reader.buffered_len());
+		assert!(reader.peek_partial().is_empty());
+		let telegrams = reader.feed(b"/test2\r\n!AAAA\r\n");
+		assert_eq!(1, telegrams.len());
+		assert_eq!(b"/test2\r\n!AAAA\r\n", telegrams[0].as_ref());
+	}
+
+	#[test]
+	fn test_telegram_reader_max_buffered_len() {
+		let mut reader = RawTelegramReader::with_max_buffered_len(20);
+		assert!(reader.feed(b"/test\r\n1-0:1.8.1(").is_empty());
+		assert_eq!(17, reader.buffered_len());
+		assert!(reader.feed(b"000123.456").is_empty());
+		assert_eq!(0, reader.buffered_len());
+		let telegrams = reader.feed(b"*kWh)\r\n!AAAA\r\n/test2\r\n!AAAA\r\n");
+		assert_eq!(1, telegrams.len());
+		assert_eq!(b"/test2\r\n!AAAA\r\n", telegrams[0].as_ref());
+	}
+
+	#[test]
+	fn test_telegram_stream_items() {
+		use std::borrow::Cow;
+		use std::task::{Context, Poll, Waker};
+
+		use futures_util::{StreamExt, stream};
+
+		use super::RawTelegramStream;
+
+		let mut cx = Context::from_waker(Waker::noop());
+		let mut telegrams = RawTelegramStream::new(stream::iter([b"/test\r\n".to_vec(), b"\r\n!\r\n".to_vec()]));
+		assert!(
+			matches!(telegrams.poll_next_unpin(&mut cx), Poll::Ready(Some(telegram)) if telegram.as_ref() == b"/test\r\n\r\n!\r\n")
+		);
+		assert!(matches!(telegrams.poll_next_unpin(&mut cx), Poll::Ready(None)));
+
+		let mut telegrams = RawTelegramStream::new(stream::iter([
+			Cow::Borrowed(b"/test\r\n!".as_slice()),
+			Cow::Owned(b"\r\n".to_vec()),
+		]));
+		assert!(
+			matches!(telegrams.poll_next_unpin(&mut cx), Poll::Ready(Some(telegram)) if telegram.as_ref() == b"/test\r\n!\r\n")
+		);
+
+		fn assert_send_sync<T: Send + Sync>(_: &T) {}
+		assert_send_sync(&RawTelegramStream::new(stream::iter([b"/test\r\n!\r\n"])).with_tap(|_| {}));
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_serde() {
+		let telegram = RawTelegram {
+			contents: b"/test\r\n\r\n!\r\n".to_vec(),
+		};
+		let json = serde_json::to_string(&telegram).unwrap();
+		assert_eq!(r#""/test\r\n\r\n!\r\n""#, json);
+		assert_eq!(
+			telegram.contents,
+			serde_json::from_str::<RawTelegram>(&json).unwrap().contents
+		);
+
+		let telegram = RawTelegram {
+			contents: b"/test\xff\r\n\r\n!\r\n".to_vec(),
+		};
+		let json = serde_json::to_string(&telegram).unwrap();
+		assert_eq!(r#"{"base64":"L3Rlc3T/DQoNCiENCg=="}"#, json);
+		assert_eq!(
+			telegram.contents,
+			serde_json::from_str::<RawTelegram>(&json).unwrap().contents
+		);
+
+		assert!(serde_json::from_str::<RawTelegram>(r#"{"hex":"00"}"#).is_err());
+		assert!(serde_json::from_str::<RawTelegram>(r#"{"base64":"!"}"#).is_err());
+	}
+
+	#[test]
+	fn test_check_crc() {
+		let telegram = |contents: &[u8]| RawTelegram {
+			contents: contents.to_vec(),
+		};
+		assert_eq!(
+			CrcCheck::Valid,
+			telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n").check_crc()
+		);
+		assert_eq!(
+			CrcCheck::Mismatch,
+			telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BE\r\n").check_crc()
+		);
+		assert_eq!(
+			CrcCheck::Missing,
+			telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!\r\n").check_crc()
+		);
+		assert_eq!(
+			CrcCheck::Malformed,
+			telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!ZZZZ\r\n").check_crc()
+		);
+		assert_eq!(CrcCheck::Malformed, telegram(b"/test\r\n").check_crc());
+	}
+
+	#[cfg(feature = "arbitrary")]
+	#[test]
+	fn test_arbitrary_telegram() {
+		use arbitrary::{Arbitrary, Unstructured};
+
+		for seed in 0..64u8 {
+			let data = (0..512)
+				.map(|i| seed.wrapping_mul(31).wrapping_add(i as u8))
+				.collect::<Vec<_>>();
+			let telegram = RawTelegram::arbitrary(&mut Unstructured::new(&data)).unwrap();
+			assert_eq!(CrcCheck::Valid, telegram.check_crc());
+			assert!(telegram.parse().is_ok());
+			let mut reader = RawTelegramReader::new();
+			let telegrams = reader.feed(&telegram.contents);
+			assert_eq!(1, telegrams.len());
+			assert_eq!(telegram.contents, telegrams[0].contents);
+		}
+	}
 }
@@ -0,0 +1,434 @@
+//! Policies that decide when to reconnect to the dongle after the connection is lost or can't be established.
+//!
+//! The policies are used by [DongleManager], [EnergyDongleClient] and [ConnectionPool], you can also use them in your own
+//! reconnection loop or implement [ReconnectPolicy] to supply a custom one.
+//!
+//! [DongleManager]: crate::manager::DongleManager
+//! [EnergyDongleClient]: crate::client::EnergyDongleClient
+//! [ConnectionPool]: crate::pool::ConnectionPool
+
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+#[cfg(any(feature = "client", feature = "manager", feature = "pool"))]
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(any(feature = "client", feature = "manager", feature = "pool"))]
+use crate::clock::Clock;
+
+/// Decides how long to wait before the next connection attempt.
+pub trait ReconnectPolicy: Send + Sync {
+	/// Returns the delay before the next attempt or `None` to give up.
+	///
+	/// `attempt` is the number of the consecutive reconnection attempts since the last established connection, it starts from 1.
+	/// `last_error` is the error that caused the reconnection if there was one and `uptime` is how long the previous connection
+	/// was up, it's zero if the connection couldn't be established.
+	fn next_delay(&self, attempt: u32, last_error: Option<&(dyn Error + 'static)>, uptime: Duration) -> Option<Duration>;
+}
+
+impl<P: ReconnectPolicy + ?Sized> ReconnectPolicy for Box<P> {
+	fn next_delay(&self, attempt: u32, last_error: Option<&(dyn Error + 'static)>, uptime: Duration) -> Option<Duration> {
+		(**self).next_delay(attempt, last_error, uptime)
+	}
+}
+
+impl<P: ReconnectPolicy + ?Sized> ReconnectPolicy for Arc<P> {
+	fn next_delay(&self, attempt: u32, last_error: Option<&(dyn Error + 'static)>, uptime: Duration) -> Option<Duration> {
+		(**self).next_delay(attempt, last_error, uptime)
+	}
+}
+
+/// State of a connection that is maintained using a [ReconnectPolicy], reported by [DongleManager] and [EnergyDongleClient].
+///
+/// [EnergyDongleClient]: crate::client::EnergyDongleClient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+	Connecting,
+	Connected,
+	/// Connection is lost, waiting to reconnect
+	Disconnected,
+	/// The reconnect policy gave up, [DongleManager] retries the dongle after the next discovery run finds it
+	GaveUp,
+}
+
+/// Waits the same time before every attempt.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+///
+/// use homey_energy_dongle::reconnect::{FixedDelay, ReconnectPolicy};
+///
+/// let policy = FixedDelay::new(Duration::from_secs(5)).with_max_attempts(2);
+/// assert_eq!(Some(Duration::from_secs(5)), policy.next_delay(1, None, Duration::ZERO));
+/// assert_eq!(Some(Duration::from_secs(5)), policy.next_delay(2, None, Duration::ZERO));
+/// assert_eq!(None, policy.next_delay(3, None, Duration::ZERO));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedDelay {
+	delay: Duration,
+	max_attempts: Option<u32>,
+}
+
+impl FixedDelay {
+	pub fn new(delay: Duration) -> Self {
+		Self {
+			delay,
+			max_attempts: None,
+		}
+	}
+
+	/// Give up after `max_attempts` attempts, never gives up by default.
+	pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+		self.max_attempts = Some(max_attempts);
+		self
+	}
+}
+
+impl ReconnectPolicy for FixedDelay {
+	fn next_delay(&self, attempt: u32, _last_error: Option<&(dyn Error + 'static)>, _uptime: Duration) -> Option<Duration> {
+		within_attempts(attempt, self.max_attempts).then_some(self.delay)
+	}
+}
+
+/// Multiplies the delay by a constant factor after every attempt up to the maximum, with an optional random jitter.
+///
+/// The jitter spreads the reconnections of many clients over time, so that they don't overload the dongle at the same moment.
+/// With the jitter `j` the delay is randomly reduced by up to `j` times its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+	initial: Duration,
+	max: Duration,
+	multiplier: f64,
+	jitter: f64,
+	max_attempts: Option<u32>,
+}
+
+impl ExponentialBackoff {
+	/// Creates the policy that starts with `initial` delay and doubles it up to `max`, the jitter is 0.5.
+	pub fn new(initial: Duration, max: Duration) -> Self {
+		Self {
+			initial,
+			max,
+			multiplier: 2.,
+			jitter: 0.5,
+			max_attempts: None,
+		}
+	}
+
+	/// Factor that the delay is multiplied by after every attempt, 2 by default.
+	pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+		self.multiplier = multiplier.max(1.);
+		self
+	}
+
+	/// Fraction of the delay that can be randomly subtracted from it, clamped to 0..=1, 0.5 by default.
+	pub fn with_jitter(mut self, jitter: f64) -> Self {
+		self.jitter = jitter.clamp(0., 1.);
+		self
+	}
+
+	/// Give up after `max_attempts` attempts, never gives up by default.
+	pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+		self.max_attempts = Some(max_attempts);
+		self
+	}
+
+	/// Returns the delay for `attempt` before the jitter is applied.
+	fn base_delay(&self, attempt: u32) -> Duration {
+		let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+		let delay = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+		Duration::try_from_secs_f64(delay).unwrap_or(self.max).min(self.max)
+	}
+}
+
+impl Default for ExponentialBackoff {
+	/// From 1 second to 1 minute.
+	fn default() -> Self {
+		Self::new(Duration::from_secs(1), Duration::from_secs(60))
+	}
+}
+
+impl ReconnectPolicy for ExponentialBackoff {
+	fn next_delay(&self, attempt: u32, _last_error: Option<&(dyn Error + 'static)>, _uptime: Duration) -> Option<Duration> {
+		if !within_attempts(attempt, self.max_attempts) {
+			return None;
+		}
+		let delay = self.base_delay(attempt);
+		Some(delay.mul_f64(1. - self.jitter * random_fraction()))
+	}
+}
+
+/// Increases the delay following the Fibonacci sequence (1, 1, 2, 3, 5, 8, ... times the unit) up to the maximum.
+///
+/// The delay grows slower than with [ExponentialBackoff] with the multiplier of 2.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+///
+/// use homey_energy_dongle::reconnect::{FibonacciBackoff, ReconnectPolicy};
+///
+/// let policy = FibonacciBackoff::new(Duration::from_secs(1), Duration::from_secs(10));
+/// let delays = (1..=7)
+///     .map(|attempt| policy.next_delay(attempt, None, Duration::ZERO).unwrap().as_secs())
+///     .collect::<Vec<_>>();
+/// assert_eq!(vec![1, 1, 2, 3, 5, 8, 10], delays);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FibonacciBackoff {
+	unit: Duration,
+	max: Duration,
+	max_attempts: Option<u32>,
+}
+
+impl FibonacciBackoff {
+	pub fn new(unit: Duration, max: Duration) -> Self {
+		Self {
+			unit,
+			max,
+			max_attempts: None,
+		}
+	}
+
+	/// Give up after `max_attempts` attempts, never gives up by default.
+	pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+		self.max_attempts = Some(max_attempts);
+		self
+	}
+}
+
+impl ReconnectPolicy for FibonacciBackoff {
+	fn next_delay(&self, attempt: u32, _last_error: Option<&(dyn Error + 'static)>, _uptime: Duration) -> Option<Duration> {
+		if !within_attempts(attempt, self.max_attempts) {
+			return None;
+		}
+		let (mut prev, mut current) = (0u32, 1u32);
+		for _ in 1..attempt {
+			(prev, current) = (current, prev.saturating_add(current));
+			if self.unit.saturating_mul(current) >= self.max {
+				break;
+			}
+		}
+		Some(self.unit.saturating_mul(current).min(self.max))
+	}
+}
+
+/// Event of the connection maintained by [reconnect_loop()].
+#[cfg(any(feature = "client", feature = "manager", feature = "pool"))]
+#[derive(Debug)]
+pub(crate) enum ReconnectEvent<'e, E> {
+	/// Connection attempt is starting, `attempt` is the number of the consecutive reconnection attempt or 0 for the very first
+	/// connection
+	Connecting {
+		attempt: u32,
+	},
+	Connected,
+	ConnectFailed(&'e E),
+	/// Established connection is closed
+	Disconnected,
+	/// The reconnect policy gave up after `attempts` consecutive attempts
+	GaveUp {
+		attempts: u32,
+	},
+}
+
+/// Maintains the connection according to `reconnect_policy` until the policy gives up.
+///
+/// Starts with running `connection` if it's already established or with calling `connect` otherwise. `run` is called for every
+/// established connection and returns the error that closed it if any. The state changes are reported to `on_event`. The uptime
+/// of the connection passed to the policy is measured with `clock`.
+#[cfg(any(feature = "client", feature = "manager", feature = "pool"))]
+pub(crate) async fn reconnect_loop<T, E, CF, RF>(
+	reconnect_policy: &dyn ReconnectPolicy,
+	clock: &(dyn Clock + Sync),
+	mut connection: Option<T>,
+	mut connect: impl FnMut() -> CF,
+	mut run: impl FnMut(T) -> RF,
+	mut on_event: impl FnMut(ReconnectEvent<E>),
+) where
+	E: Error + Send + Sync + 'static,
+	CF: Future<Output = Result<T, E>>,
+	RF: Future<Output = Option<Box<dyn Error + Send + Sync>>>,
+{
+	let mut retry = connection.is_some();
+	let mut attempt = 0;
+	let mut last_error = None;
+	let mut uptime = Duration::ZERO;
+	loop {
+		if let Some(connection) = connection.take() {
+			on_event(ReconnectEvent::Connected);
+			let connected_at = clock.now();
+			last_error = run(connection).await;
+			uptime = clock.now().saturating_duration_since(connected_at);
+			on_event(ReconnectEvent::Disconnected);
+			attempt = 0;
+		}
+		if retry {
+			attempt += 1;
+			let Some(delay) = reconnect_policy.next_delay(attempt, last_error.as_deref().map(|err| err as _), uptime) else {
+				on_event(ReconnectEvent::GaveUp { attempts: attempt });
+				return;
+			};
+			tokio::time::sleep(delay).await;
+		}
+		on_event(ReconnectEvent::Connecting { attempt });
+		retry = true;
+		match connect().await {
+			Ok(established) => connection = Some(established),
+			Err(err) => {
+				on_event(ReconnectEvent::ConnectFailed(&err));
+				last_error = Some(Box::new(err));
+				uptime = Duration::ZERO;
+			}
+		}
+	}
+}
+
+fn within_attempts(attempt: u32, max_attempts: Option<u32>) -> bool {
+	max_attempts.is_none_or(|max_attempts| attempt <= max_attempts)
+}
+
+/// Returns a pseudo-random number in 0..1, the quality is good enough for the jitter and avoids an extra dependency.
+fn random_fraction() -> f64 {
+	let random = RandomState::new().build_hasher().finish();
+	(random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_exponential_backoff() {
+		let policy = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(30)).with_max_attempts(10);
+		let delays = (1..=7)
+			.map(|attempt| policy.base_delay(attempt).as_secs())
+			.collect::<Vec<_>>();
+		assert_eq!(vec![1, 2, 4, 8, 16, 30, 30], delays);
+		assert_eq!(Duration::from_secs(30), policy.base_delay(u32::MAX));
+		for attempt in 1..=10 {
+			let delay = policy.next_delay(attempt, None, Duration::ZERO).unwrap();
+			let base = policy.base_delay(attempt);
+			assert!(delay <= base && delay >= base / 2, "{delay:?} is out of range for {base:?}");
+		}
+		assert_eq!(None, policy.next_delay(11, None, Duration::ZERO));
+
+		let policy = policy.with_jitter(0.).with_multiplier(3.);
+		assert_eq!(Some(Duration::from_secs(9)), policy.next_delay(3, None, Duration::ZERO));
+	}
+
+	#[test]
+	fn test_fibonacci_backoff() {
+		let policy = FibonacciBackoff::new(Duration::from_millis(100), Duration::from_secs(60));
+		assert_eq!(Some(Duration::from_millis(1300)), policy.next_delay(7, None, Duration::ZERO));
+		assert_eq!(
+			Some(Duration::from_secs(60)),
+			policy.next_delay(u32::MAX, None, Duration::ZERO)
+		);
+	}
+
+	#[test]
+	fn test_boxed_policy() {
+		let policy: Box<dyn ReconnectPolicy> = Box::new(FixedDelay::new(Duration::from_secs(1)));
+		let err = std::io::Error::other("test");
+		assert_eq!(
+			Some(Duration::from_secs(1)),
+			policy.next_delay(100, Some(&err), Duration::from_secs(10))
+		);
+	}
+
+	#[cfg(any(feature = "client", feature = "manager", feature = "pool"))]
+	#[tokio::test]
+	async fn test_reconnect_loop() {
+		use crate::clock::SystemClock;
+
+		let policy = FixedDelay::new(Duration::ZERO).with_max_attempts(2);
+		let mut results = vec![
+			Ok(1),
+			Err(std::io::Error::other("refused")),
+			Ok(2),
+			Err(std::io::Error::other("refused")),
+		]
+		.into_iter();
+		let mut runs = vec![];
+		let mut events = vec![];
+		reconnect_loop(
+			&policy,
+			&SystemClock,
+			Some(0),
+			|| std::future::ready(results.next().unwrap_or_else(|| Err(std::io::Error::other("refused")))),
+			|connection| {
+				runs.push(connection);
+				std::future::ready(None)
+			},
+			|event| {
+				events.push(match event {
+					ReconnectEvent::Connecting { attempt } => format!("connecting {attempt}"),
+					ReconnectEvent::Connected => "connected".to_string(),
+					ReconnectEvent::ConnectFailed(err) => format!("failed {err}"),
+					ReconnectEvent::Disconnected => "disconnected".to_string(),
+					ReconnectEvent::GaveUp { attempts } => format!("gave up {attempts}"),
+				})
+			},
+		)
+		.await;
+		assert_eq!(vec![0, 1, 2], runs);
+		assert_eq!(
+			vec![
+				"connected",
+				"disconnected",
+				"connecting 1",
+				"connected",
+				"disconnected",
+				"connecting 1",
+				"failed refused",
+				"connecting 2",
+				"connected",
+				"disconnected",
+				"connecting 1",
+				"failed refused",
+				"connecting 2",
+				"failed refused",
+				"gave up 3",
+			],
+			events
+		);
+	}
+
+	#[cfg(all(feature = "test-util", any(feature = "client", feature = "manager", feature = "pool")))]
+	#[tokio::test]
+	async fn test_reconnect_loop_uptime() {
+		use std::sync::Mutex;
+
+		use crate::clock::MockClock;
+
+		#[derive(Default)]
+		struct Recorder(Mutex<Vec<Duration>>);
+
+		impl ReconnectPolicy for Recorder {
+			fn next_delay(&self, attempt: u32, _last_error: Option<&(dyn Error + 'static)>, uptime: Duration) -> Option<Duration> {
+				self.0.lock().unwrap().push(uptime);
+				(attempt < 2).then_some(Duration::ZERO)
+			}
+		}
+
+		let clock = MockClock::new();
+		let policy = Recorder::default();
+		reconnect_loop(
+			&policy,
+			&clock,
+			Some(()),
+			|| std::future::ready(Err(std::io::Error::other("refused"))),
+			|()| {
+				clock.advance(Duration::from_secs(60));
+				std::future::ready(None)
+			},
+			|_| {},
+		)
+		.await;
+		assert_eq!(vec![Duration::from_secs(60), Duration::ZERO], *policy.0.lock().unwrap());
+	}
+}
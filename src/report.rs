@@ -0,0 +1,705 @@
+//! Daily and monthly energy summaries.
+//!
+//! [EnergyReport] folds the telegrams, e.g. from a live stream or from a [Journal](crate::journal::Journal) replay, into the
+//! per-day consumption and production, gas usage, peak demand and the estimated cost. The summaries can be exported to CSV with
+//! [to_csv()] or, with the `serde` feature, serialized to JSON or any other format.
+//!
+//! The telegrams missed during the reconnections leave gaps in the data, [EnergyReport] reports them as [DataGap] and lets the
+//! application fill them in with [EnergyReport::with_backfill()].
+//!
+//! The water and gas meters read with S0 pulse counters instead of the M-Bus are merged into the same summaries with
+//! [EnergyReport::record_counter()].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::pulse::{CounterReading, Medium};
+use crate::telegram::{Obis, Telegram, Timestamp, parse_quantity};
+
+const IMPORTED_TARIFF1: Obis = Obis::new(1, 0, 1, 8, 1);
+const IMPORTED_TARIFF2: Obis = Obis::new(1, 0, 1, 8, 2);
+const EXPORTED_TARIFF1: Obis = Obis::new(1, 0, 2, 8, 1);
+const EXPORTED_TARIFF2: Obis = Obis::new(1, 0, 2, 8, 2);
+
+type BackfillHook = Box<dyn FnMut(DataGap) -> Vec<Telegram> + Send>;
+
+/// Accumulator of the daily and monthly energy summaries.
+///
+/// The consumption is calculated from the increments of the meter registers between the consecutive telegrams, so the telegrams
+/// must be recorded in the order of their creation. The increments are assigned to the day of the telegram timestamp in the
+/// meter local time, the gas increments to the day of the gas meter reading. A decrease of a register, e.g. after the meter
+/// replacement, is ignored.
+///
+/// The consumption over a gap between the telegrams is still accounted for, but all of it is assigned to the day of the telegram
+/// after the gap and the peak values within the gap are lost. The gaps longer than [EnergyReport::with_max_interval()] are
+/// passed to the backfill hook, the gaps that were not backfilled are returned by [EnergyReport::gaps()].
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::report::{EnergyPrices, EnergyReport, to_csv};
+///
+/// let telegram = |timestamp: &str, imported: &str| {
+///     let telegram = format!("/test\r\n\r\n0-0:1.0.0({timestamp})\r\n1-0:1.8.1({imported}*kWh)\r\n!\r\n");
+///     parse_telegram(telegram.as_bytes()).unwrap()
+/// };
+/// let mut report = EnergyReport::new().with_prices(EnergyPrices {
+///     import_tariff1: 0.25,
+///     ..EnergyPrices::default()
+/// });
+/// report.record(&telegram("240630120000S", "000100.000"));
+/// report.record(&telegram("240630180000S", "000104.000"));
+/// report.record(&telegram("240701060000S", "000106.000"));
+///
+/// let monthly = report.monthly();
+/// assert_eq!(2, monthly.len());
+/// assert_eq!(4., monthly[0].imported.tariff1);
+/// assert_eq!(Some(1.), monthly[0].cost);
+/// assert_eq!("2024-07", monthly[1].period.to_string());
+/// assert!(to_csv(&monthly).starts_with("period,imported_tariff1,"));
+/// ```
+pub struct EnergyReport {
+	prices: Option<EnergyPrices>,
+	max_interval: Duration,
+	backfill: Option<BackfillHook>,
+	days: BTreeMap<Period, DayTotals>,
+	last_readings: Readings,
+	last_counters: Counters,
+	last_timestamp: Option<Timestamp>,
+	gaps: Vec<DataGap>,
+}
+
+impl EnergyReport {
+	pub fn new() -> Self {
+		Self {
+			prices: None,
+			max_interval: Duration::from_secs(60),
+			backfill: None,
+			days: BTreeMap::new(),
+			last_readings: Readings::default(),
+			last_counters: Counters::default(),
+			last_timestamp: None,
+			gaps: vec![],
+		}
+	}
+
+	/// Estimate the cost using `prices`, the cost is not calculated by default.
+	pub fn with_prices(mut self, prices: EnergyPrices) -> Self {
+		self.prices = Some(prices);
+		self
+	}
+
+	/// Longest expected interval between the telegram timestamps, a longer interval is a [DataGap], 1 minute by default.
+	pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+		self.max_interval = max_interval;
+		self
+	}
+
+	/// Calls `backfill` for every detected [DataGap], replaces the previously attached hook.
+	///
+	/// The hook returns the telegrams covering the gap, e.g. replayed from the [Journal](crate::journal::Journal) or received
+	/// from another dongle connected to the same meter. They are recorded in the order of their timestamps before the telegram
+	/// that ended the gap, the ones without the timestamp or with the timestamp outside of the gap are ignored. If none of the
+	/// returned telegrams is recorded, the gap is added to [EnergyReport::gaps()].
+	pub fn with_backfill(mut self, backfill: impl FnMut(DataGap) -> Vec<Telegram> + Send + 'static) -> Self {
+		self.backfill = Some(Box::new(backfill));
+		self
+	}
+
+	/// Records the readings of `telegram` on the day of its timestamp.
+	///
+	/// The telegrams without the timestamp (legacy DSMR versions) are ignored, use [EnergyReport::record_at()] for them.
+	pub fn record(&mut self, telegram: &Telegram) {
+		if let Some(timestamp) = telegram.timestamp() {
+			self.record_at(telegram, timestamp);
+		}
+	}
+
+	/// Records the readings of `telegram` on the day of `timestamp`.
+	///
+	/// `timestamp` is used to detect the gaps, it's expected to be non-decreasing between the calls.
+	pub fn record_at(&mut self, telegram: &Telegram, timestamp: Timestamp) {
+		if let Some(last_timestamp) = self.last_timestamp {
+			let gap = DataGap {
+				from: last_timestamp,
+				to: timestamp,
+			};
+			if gap.duration() > self.max_interval {
+				let backfilled = self.backfill.as_mut().map(|backfill| backfill(gap)).unwrap_or_default();
+				let mut backfilled = backfilled
+					.iter()
+					.filter_map(|telegram| {
+						let timestamp = telegram.timestamp().filter(|timestamp| gap.contains(*timestamp))?;
+						Some((timestamp, telegram))
+					})
+					.collect::<Vec<_>>();
+				// the increments are only correct when the readings are accumulated in the chronological order
+				backfilled.sort_by_key(|(timestamp, _)| timestamp.to_unix_time(0));
+				for &(timestamp, telegram) in &backfilled {
+					self.accumulate(telegram, timestamp);
+				}
+				if backfilled.is_empty() {
+					self.gaps.push(gap);
+				}
+			}
+		}
+		self.accumulate(telegram, timestamp);
+		self.last_timestamp = Some(timestamp);
+	}
+
+	/// Records the reading of an external counter on the day of its timestamp.
+	///
+	/// The increments of the counter are calculated the same way as for the meter registers, so the readings of each medium must
+	/// be recorded in the order of their creation. The external gas readings are added to the gas consumption, don't record them
+	/// if the gas meter is also connected to the M-Bus of the electricity meter.
+	pub fn record_counter(&mut self, reading: &CounterReading) {
+		let value = to_thousandths(reading.value);
+		let day = self.days.entry(Period::day(reading.timestamp)).or_default();
+		let (total, previous) = match reading.medium {
+			Medium::Gas => (&mut day.gas, &mut self.last_counters.gas),
+			Medium::Water => (&mut day.water, &mut self.last_counters.water),
+		};
+		let total = total.get_or_insert(0);
+		*total = total.saturating_add(previous.and_then(|previous| value.checked_sub(previous)).unwrap_or(0));
+		*previous = Some(value);
+	}
+
+	/// Returns the gaps that were not backfilled in the chronological order.
+	pub fn gaps(&self) -> &[DataGap] {
+		&self.gaps
+	}
+
+	/// Returns the summaries of every recorded day in the chronological order.
+	pub fn daily(&self) -> Vec<PeriodSummary> {
+		self
+			.days
+			.iter()
+			.map(|(period, totals)| totals.summary(*period, self.prices))
+			.collect()
+	}
+
+	/// Returns the summaries of every recorded month in the chronological order.
+	pub fn monthly(&self) -> Vec<PeriodSummary> {
+		let mut months = BTreeMap::<Period, DayTotals>::new();
+		for (day, totals) in &self.days {
+			months.entry(day.month()).or_default().add(totals);
+		}
+		months
+			.iter()
+			.map(|(period, totals)| totals.summary(*period, self.prices))
+			.collect()
+	}
+
+	fn accumulate(&mut self, telegram: &Telegram, timestamp: Timestamp) {
+		let readings = Readings::from_telegram(telegram);
+		let day = self.days.entry(Period::day(timestamp)).or_default();
+		let increment = |current: Option<u64>, previous: Option<u64>| {
+			current
+				.zip(previous)
+				.and_then(|(current, previous)| current.checked_sub(previous))
+				.unwrap_or(0)
+		};
+		for tariff in 0..2 {
+			day.imported[tariff] =
+				day.imported[tariff].saturating_add(increment(readings.imported[tariff], self.last_readings.imported[tariff]));
+			day.exported[tariff] =
+				day.exported[tariff].saturating_add(increment(readings.exported[tariff], self.last_readings.exported[tariff]));
+		}
+		if let Some(net) = telegram.net_metering() {
+			day.peak_power = Some(day.peak_power.map_or(net.power_imported, |peak| peak.max(net.power_imported)));
+		}
+		if let Some(demand) = telegram.average_demand() {
+			day.peak_demand = Some(day.peak_demand.map_or(demand.current, |peak| peak.max(demand.current)));
+		}
+		if let Some(gas) = telegram.gas() {
+			let volume = to_thousandths(gas.volume);
+			let gas_day = self.days.entry(Period::day(gas.timestamp)).or_default();
+			let previous = self.last_readings.gas;
+			let total = gas_day.gas.get_or_insert(0);
+			*total = total.saturating_add(increment(Some(volume), previous));
+			self.last_readings.gas = Some(volume);
+		}
+		self.last_readings = Readings {
+			gas: self.last_readings.gas,
+			..readings
+		};
+	}
+}
+
+impl Default for EnergyReport {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl fmt::Debug for EnergyReport {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EnergyReport")
+			.field("prices", &self.prices)
+			.field("max_interval", &self.max_interval)
+			.field("backfill", &self.backfill.is_some())
+			.field("days", &self.days)
+			.field("last_readings", &self.last_readings)
+			.field("last_counters", &self.last_counters)
+			.field("last_timestamp", &self.last_timestamp)
+			.field("gaps", &self.gaps)
+			.finish()
+	}
+}
+
+/// Interval between two consecutive telegrams recorded by [EnergyReport] that is longer than expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DataGap {
+	/// Timestamp of the last telegram before the gap
+	pub from: Timestamp,
+	/// Timestamp of the first telegram after the gap
+	pub to: Timestamp,
+}
+
+impl DataGap {
+	/// Returns the length of the gap.
+	pub fn duration(&self) -> Duration {
+		Duration::from_secs(u64::try_from(self.to.to_unix_time(0) - self.from.to_unix_time(0)).unwrap_or(0))
+	}
+
+	/// Returns `true` if `timestamp` is strictly within the gap.
+	pub fn contains(&self, timestamp: Timestamp) -> bool {
+		(self.from.to_unix_time(0) + 1..self.to.to_unix_time(0)).contains(&timestamp.to_unix_time(0))
+	}
+}
+
+/// Prices used by [EnergyReport] to estimate the cost, in any currency per kWh and per m³.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EnergyPrices {
+	pub import_tariff1: f64,
+	pub import_tariff2: f64,
+	/// Compensation for the energy delivered to the grid, it's subtracted from the cost
+	pub export_tariff1: f64,
+	pub export_tariff2: f64,
+	pub gas: f64,
+	pub water: f64,
+}
+
+/// Day or month covered by a [PeriodSummary], displayed as "YYYY-MM-DD" or "YYYY-MM".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Period {
+	Day { year: u16, month: u8, day: u8 },
+	Month { year: u16, month: u8 },
+}
+
+impl Period {
+	fn day(timestamp: Timestamp) -> Self {
+		Self::Day {
+			year: timestamp.year,
+			month: timestamp.month,
+			day: timestamp.day,
+		}
+	}
+
+	fn month(self) -> Self {
+		match self {
+			Self::Day { year, month, .. } | Self::Month { year, month } => Self::Month { year, month },
+		}
+	}
+}
+
+impl fmt::Display for Period {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Day { year, month, day } => write!(f, "{year:04}-{month:02}-{day:02}"),
+			Self::Month { year, month } => write!(f, "{year:04}-{month:02}"),
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Period {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+/// Energy per tariff in kWh.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TariffEnergy {
+	pub tariff1: f64,
+	pub tariff2: f64,
+}
+
+impl TariffEnergy {
+	/// Returns the energy over all tariffs.
+	pub fn total(&self) -> f64 {
+		self.tariff1 + self.tariff2
+	}
+}
+
+/// Summary of a day or a month produced by [EnergyReport].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PeriodSummary {
+	pub period: Period,
+	/// Energy delivered to the client
+	pub imported: TariffEnergy,
+	/// Energy delivered by the client
+	pub exported: TariffEnergy,
+	/// Gas consumption in m³, `None` if there were no gas meter readings
+	pub gas: Option<f64>,
+	/// Water consumption in m³, `None` if there were no water counter readings
+	pub water: Option<f64>,
+	/// Highest current power delivered to the client in kW
+	pub peak_power: Option<f64>,
+	/// Highest quarter-hour average demand in kW, only reported by the e-MUCS meters, see [Telegram::average_demand()]
+	pub peak_demand: Option<f64>,
+	/// Estimated cost of the imported energy and gas minus the compensation for the exported energy, `None` without
+	/// [EnergyReport::with_prices()]
+	pub cost: Option<f64>,
+}
+
+/// Formats the summaries as CSV with a header row, the missing values are left empty.
+pub fn to_csv(summaries: &[PeriodSummary]) -> String {
+	let mut out = String::from(
+		"period,imported_tariff1,imported_tariff2,exported_tariff1,exported_tariff2,gas,water,peak_power,peak_demand,cost\n",
+	);
+	let optional = |value: Option<f64>, precision: usize| value.map(|value| format!("{value:.precision$}")).unwrap_or_default();
+	for summary in summaries {
+		// writing to a String never fails
+		let _ = writeln!(
+			out,
+			"{},{:.3},{:.3},{:.3},{:.3},{},{},{},{},{}",
+			summary.period,
+			summary.imported.tariff1,
+			summary.imported.tariff2,
+			summary.exported.tariff1,
+			summary.exported.tariff2,
+			optional(summary.gas, 3),
+			optional(summary.water, 3),
+			optional(summary.peak_power, 3),
+			optional(summary.peak_demand, 3),
+			optional(summary.cost, 2),
+		);
+	}
+	out
+}
+
+/// Register readings in Wh and dm³ to accumulate them without the rounding errors.
+#[derive(Debug, Clone, Copy, Default)]
+struct Readings {
+	imported: [Option<u64>; 2],
+	exported: [Option<u64>; 2],
+	gas: Option<u64>,
+}
+
+impl Readings {
+	fn from_telegram(telegram: &Telegram) -> Self {
+		let energy = |obis| {
+			telegram
+				.value(obis)
+				.and_then(|value| parse_quantity(value, "kWh"))
+				.map(to_thousandths)
+		};
+		Self {
+			imported: [energy(IMPORTED_TARIFF1), energy(IMPORTED_TARIFF2)],
+			exported: [energy(EXPORTED_TARIFF1), energy(EXPORTED_TARIFF2)],
+			gas: None,
+		}
+	}
+}
+
+/// Last readings of the external counters in dm³.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+	gas: Option<u64>,
+	water: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DayTotals {
+	imported: [u64; 2],
+	exported: [u64; 2],
+	gas: Option<u64>,
+	water: Option<u64>,
+	peak_power: Option<f64>,
+	peak_demand: Option<f64>,
+}
+
+impl DayTotals {
+	fn add(&mut self, other: &Self) {
+		let max = |a: Option<f64>, b: Option<f64>| a.into_iter().chain(b).reduce(f64::max);
+		for tariff in 0..2 {
+			self.imported[tariff] = self.imported[tariff].saturating_add(other.imported[tariff]);
+			self.exported[tariff] = self.exported[tariff].saturating_add(other.exported[tariff]);
+		}
+		self.gas = self.gas.into_iter().chain(other.gas).reduce(u64::saturating_add);
+		self.water = self.water.into_iter().chain(other.water).reduce(u64::saturating_add);
+		self.peak_power = max(self.peak_power, other.peak_power);
+		self.peak_demand = max(self.peak_demand, other.peak_demand);
+	}
+
+	fn summary(&self, period: Period, prices: Option<EnergyPrices>) -> PeriodSummary {
+		let from_thousandths = |value: u64| value as f64 / 1000.;
+		let imported = TariffEnergy {
+			tariff1: from_thousandths(self.imported[0]),
+			tariff2: from_thousandths(self.imported[1]),
+		};
+		let exported = TariffEnergy {
+			tariff1: from_thousandths(self.exported[0]),
+			tariff2: from_thousandths(self.exported[1]),
+		};
+		let gas = self.gas.map(from_thousandths);
+		let water = self.water.map(from_thousandths);
+		let cost = prices.map(|prices| {
+			imported.tariff1 * prices.import_tariff1 + imported.tariff2 * prices.import_tariff2
+				- exported.tariff1 * prices.export_tariff1
+				- exported.tariff2 * prices.export_tariff2
+				+ gas.unwrap_or(0.) * prices.gas
+				+ water.unwrap_or(0.) * prices.water
+		});
+		PeriodSummary {
+			period,
+			imported,
+			exported,
+			gas,
+			water,
+			peak_power: self.peak_power,
+			peak_demand: self.peak_demand,
+			cost,
+		}
+	}
+}
+
+fn to_thousandths(value: f64) -> u64 {
+	(value * 1000.).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex, PoisonError};
+
+	use super::*;
+	use crate::parse_telegram;
+	use crate::pulse::PulseCounter;
+	use crate::test_telegrams::{DSMR5, with_crc};
+
+	fn telegram(timestamp: &str, imported: &str, exported: &str, gas_timestamp: &str, gas: &str) -> Telegram {
+		let telegram = DSMR5
+			.replace("0-0:1.0.0(101209113020W)", &format!("0-0:1.0.0({timestamp})"))
+			.replace("1-0:1.8.2(123456.789*kWh)", &format!("1-0:1.8.2({imported}*kWh)"))
+			.replace("1-0:2.8.1(123456.789*kWh)", &format!("1-0:2.8.1({exported}*kWh)"))
+			.replace(
+				"0-1:24.2.1(101209112500W)(12785.123*m3)",
+				&format!("0-1:24.2.1({gas_timestamp})({gas}*m3)"),
+			);
+		parse_telegram(&with_crc(&telegram)).unwrap()
+	}
+
+	#[test]
+	fn test_energy_report() {
+		let mut report = EnergyReport::new().with_prices(EnergyPrices {
+			import_tariff2: 0.3,
+			export_tariff1: 0.1,
+			gas: 1.5,
+			..EnergyPrices::default()
+		});
+		report.record(&telegram(
+			"240131230000W",
+			"000100.100",
+			"000050.000",
+			"240131230000W",
+			"00010.000",
+		));
+		report.record(&telegram(
+			"240131235959W",
+			"000100.300",
+			"000050.100",
+			"240131230000W",
+			"00010.000",
+		));
+		// the gas reading of the last hour of the previous day arrives after midnight
+		report.record(&telegram(
+			"240201000500W",
+			"000100.400",
+			"000050.100",
+			"240131235959W",
+			"00010.500",
+		));
+		// the meter is replaced
+		report.record(&telegram(
+			"240201120000W",
+			"000000.000",
+			"000000.000",
+			"240201120000W",
+			"00000.000",
+		));
+		report.record(&telegram(
+			"240201130000W",
+			"000001.000",
+			"000000.000",
+			"240201130000W",
+			"00000.200",
+		));
+
+		let daily = report.daily();
+		assert_eq!(2, daily.len());
+		let day = &daily[0];
+		assert_eq!("2024-01-31", day.period.to_string());
+		assert_eq!(0.2, day.imported.tariff2);
+		assert_eq!(0., day.imported.tariff1);
+		assert_eq!(0.1, day.exported.tariff1);
+		assert_eq!(Some(0.5), day.gas);
+		assert_eq!(Some(1.193), day.peak_power);
+		assert_eq!(None, day.peak_demand);
+		assert!((day.cost.unwrap() - (0.2 * 0.3 - 0.1 * 0.1 + 0.5 * 1.5)).abs() < 1e-9);
+		let day = &daily[1];
+		assert_eq!(1.1, day.imported.tariff2);
+		assert_eq!(Some(0.2), day.gas);
+
+		let monthly = report.monthly();
+		assert_eq!(2, monthly.len());
+		assert_eq!("2024-01", monthly[0].period.to_string());
+		assert_eq!(0.2, monthly[0].imported.total());
+		#[cfg(feature = "serde")]
+		assert!(
+			serde_json::to_string(&monthly[0])
+				.unwrap()
+				.starts_with(r#"{"period":"2024-01","imported":{"tariff1":0.0,"tariff2":0.2},"#)
+		);
+		assert_eq!(
+			"period,imported_tariff1,imported_tariff2,exported_tariff1,exported_tariff2,gas,water,peak_power,peak_demand,cost\n\
+			2024-02,0.000,1.100,0.000,0.000,0.200,,1.193,,0.63\n",
+			to_csv(&monthly[1..])
+		);
+	}
+
+	#[test]
+	fn test_data_gaps() {
+		let requested = Arc::new(Mutex::new(vec![]));
+		let mut report = EnergyReport::new().with_backfill({
+			let requested = Arc::clone(&requested);
+			move |gap| {
+				let mut requested = requested.lock().unwrap_or_else(PoisonError::into_inner);
+				requested.push(gap);
+				if requested.len() > 1 {
+					return vec![];
+				}
+				// out of order
+				vec![
+					telegram("240201001500W", "000100.950", "000050.000", "240201000000W", "00010.000"),
+					telegram("240131235959W", "000100.900", "000050.000", "240131230000W", "00010.000"),
+					// outside of the gap
+					telegram("240131120000W", "000000.000", "000050.000", "240131230000W", "00010.000"),
+				]
+			}
+		});
+		report.record(&telegram(
+			"240131235900W",
+			"000100.000",
+			"000050.000",
+			"240131230000W",
+			"00010.000",
+		));
+		report.record(&telegram(
+			"240131235930W",
+			"000100.100",
+			"000050.000",
+			"240131230000W",
+			"00010.000",
+		));
+		report.record(&telegram(
+			"240201003000W",
+			"000101.000",
+			"000050.000",
+			"240201000000W",
+			"00010.000",
+		));
+		report.record(&telegram(
+			"240201010000W",
+			"000101.500",
+			"000050.000",
+			"240201000000W",
+			"00010.000",
+		));
+
+		let gap = |from: &str, to: &str| DataGap {
+			from: from.parse().unwrap(),
+			to: to.parse().unwrap(),
+		};
+		let first = gap("240131235930W", "240201003000W");
+		let second = gap("240201003000W", "240201010000W");
+		assert_eq!(Duration::from_secs(30 * 60 + 30), first.duration());
+		assert_eq!(vec![first, second], *requested.lock().unwrap_or_else(PoisonError::into_inner));
+		assert_eq!([second], report.gaps());
+
+		let daily = report.daily();
+		assert_eq!(0.9, daily[0].imported.tariff2);
+		assert_eq!(0.6, daily[1].imported.tariff2);
+
+		let mut report = EnergyReport::new().with_max_interval(Duration::from_secs(3600));
+		report.record(&telegram(
+			"240131235930W",
+			"000100.100",
+			"000050.000",
+			"240131230000W",
+			"00010.000",
+		));
+		report.record(&telegram(
+			"240201003000W",
+			"000101.000",
+			"000050.000",
+			"240201000000W",
+			"00010.000",
+		));
+		assert!(report.gaps().is_empty());
+	}
+
+	#[test]
+	fn test_overflow() {
+		let mut report = EnergyReport::new();
+		// the corrupted readings that don't fit into the totals
+		for timestamp in ["240131220000W", "240131221000W", "240201220000W", "240201221000W"] {
+			report.record(&telegram(timestamp, "000000.000", "000000.000", timestamp, "00000.000"));
+			report.record(&telegram(
+				timestamp,
+				"99999999999999999999.000",
+				"000000.000",
+				timestamp,
+				"00000.000",
+			));
+		}
+		let max = u64::MAX as f64 / 1000.;
+		let daily = report.daily();
+		assert_eq!(2, daily.len());
+		assert!(daily.iter().all(|day| day.imported.tariff2 == max));
+		assert_eq!(max, report.monthly()[0].imported.tariff2);
+	}
+
+	#[test]
+	fn test_record_counter() {
+		let mut report = EnergyReport::new().with_prices(EnergyPrices {
+			gas: 1.5,
+			water: 2.,
+			..EnergyPrices::default()
+		});
+		let water = PulseCounter::new(Medium::Water, 1000.).with_offset(100.);
+		let gas = PulseCounter::new(Medium::Gas, 100.);
+		let timestamp = |timestamp: &str| timestamp.parse().unwrap();
+		report.record_counter(&water.reading(0, timestamp("240131220000W")));
+		report.record_counter(&gas.reading(1000, timestamp("240131220000W")));
+		report.record_counter(&water.reading(500, timestamp("240131230000W")));
+		report.record_counter(&water.reading(1500, timestamp("240201010000W")));
+		report.record_counter(&gas.reading(1050, timestamp("240201010000W")));
+		// the counter is reset
+		report.record_counter(&water.reading(0, timestamp("240201020000W")));
+		report.record_counter(&water.reading(100, timestamp("240201030000W")));
+
+		let daily = report.daily();
+		assert_eq!(2, daily.len());
+		assert_eq!((Some(0.5), Some(0.)), (daily[0].water, daily[0].gas));
+		assert_eq!((Some(1.1), Some(0.5)), (daily[1].water, daily[1].gas));
+		assert_eq!(0., daily[1].imported.total());
+		assert!((daily[1].cost.unwrap() - (1.1 * 2. + 0.5 * 1.5)).abs() < 1e-9);
+		assert!(to_csv(&daily).ends_with("2024-02-01,0.000,0.000,0.000,0.000,0.500,1.100,,,2.95\n"));
+	}
+}
@@ -0,0 +1,311 @@
+//! Simulated smart meter for demos and tests of the code built on top of this crate.
+//!
+//! [SimulatedMeter] generates a realistic sequence of DSMR 5 telegrams in virtual time: a constant baseload with random appliance
+//! spikes, the solar production following the time of day and the gas consumption on a thermostat schedule. Days of meter data
+//! are generated in seconds, so the dashboards, reports and alert rules can be exercised without the real dongle.
+
+use std::f64::consts::PI;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::clock::MockClock;
+use crate::reader::{RawTelegram, crc16};
+use crate::telegram::{Timestamp, civil_from_days, days_from_civil};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// The gas meters report a new reading every 5 minutes
+const GAS_READING_INTERVAL: i64 = 5 * 60;
+
+/// Consumption and production pattern of [SimulatedMeter].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationProfile {
+	/// Constant consumption in kW
+	pub baseload: f64,
+	/// Average number of the appliance switch-ons per day, each lasts from 5 minutes to 1 hour
+	pub appliance_spikes_per_day: f64,
+	/// Maximum power of a single appliance in kW
+	pub appliance_power: f64,
+	/// Production of the solar panels at noon in kW, 0 for no solar panels
+	pub solar_peak: f64,
+	/// Hours of the day (0..24) between which the solar panels produce
+	pub daylight: Range<f64>,
+	/// Hours of the day when the thermostat calls for heat
+	pub heating_schedule: Vec<Range<u8>>,
+	/// Gas consumption in m³/h while heating
+	pub heating_gas_rate: f64,
+}
+
+impl Default for SimulationProfile {
+	/// Household with solar panels and gas heating in the morning and in the evening.
+	fn default() -> Self {
+		Self {
+			baseload: 0.15,
+			appliance_spikes_per_day: 12.,
+			appliance_power: 2.5,
+			solar_peak: 4.,
+			daylight: 7.0..21.0,
+			heating_schedule: vec![6..9, 17..23],
+			heating_gas_rate: 1.2,
+		}
+	}
+}
+
+/// Generator of DSMR 5 telegrams that follow a [SimulationProfile].
+///
+/// Every call to [SimulatedMeter::next_telegram()] (or [Iterator::next()]) advances the virtual time by the interval, 1 second
+/// by default, and returns the telegram for the new moment. The tariff 1 (low) is active from 23:00 to 07:00. The random
+/// appliance spikes are deterministic for the same seed.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::simulation::SimulatedMeter;
+/// use homey_energy_dongle::telegram::Timestamp;
+///
+/// let start = "240615000000S".parse::<Timestamp>().unwrap();
+/// let mut meter = SimulatedMeter::new(start).with_interval(std::time::Duration::from_secs(10));
+/// // one day of telegrams
+/// let telegrams = meter.by_ref().take(8640).collect::<Vec<_>>();
+/// let noon = telegrams[4319].parse().unwrap();
+/// assert!(noon.net_metering().unwrap().is_exporting());
+/// assert_eq!("240616000000S", meter.timestamp().to_string());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimulatedMeter {
+	profile: SimulationProfile,
+	interval: Duration,
+	clock: Option<MockClock>,
+	dst: Option<bool>,
+	/// Virtual time in seconds since 1970-01-01 00:00:00 in the meter local time
+	time: i64,
+	rng: u64,
+	/// End time and power of the running appliance
+	spike: Option<(i64, f64)>,
+	imported: [f64; 2],
+	exported: [f64; 2],
+	gas: f64,
+	gas_reading: (i64, f64),
+}
+
+impl SimulatedMeter {
+	/// Creates the meter with the default profile starting at `start`, the DST flag of `start` is used for all telegrams.
+	pub fn new(start: Timestamp) -> Self {
+		let time = days_from_civil(start.year, start.month, start.day) * SECONDS_PER_DAY
+			+ i64::from(start.hour) * 3600
+			+ i64::from(start.minute) * 60
+			+ i64::from(start.second);
+		Self {
+			profile: SimulationProfile::default(),
+			interval: Duration::from_secs(1),
+			clock: None,
+			dst: start.dst,
+			time,
+			rng: 0x2545_F491_4F6C_DD1D,
+			spike: None,
+			imported: [1000., 1000.],
+			exported: [100., 100.],
+			gas: 500.,
+			gas_reading: (time - time.rem_euclid(GAS_READING_INTERVAL), 500.),
+		}
+	}
+
+	pub fn with_profile(mut self, profile: SimulationProfile) -> Self {
+		self.profile = profile;
+		self
+	}
+
+	/// Virtual time between the telegrams, 1 second by default like the DSMR 5 meters.
+	pub fn with_interval(mut self, interval: Duration) -> Self {
+		self.interval = interval.max(Duration::from_secs(1));
+		self
+	}
+
+	/// Seed of the random appliance spikes.
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		// xorshift gets stuck at zero
+		self.rng = seed.max(1);
+		self
+	}
+
+	/// Advance `clock` together with the virtual time, so that the time-based components see the same passage of time.
+	pub fn with_clock(mut self, clock: MockClock) -> Self {
+		self.clock = Some(clock);
+		self
+	}
+
+	/// Returns the current virtual time in the meter local time.
+	pub fn timestamp(&self) -> Timestamp {
+		to_timestamp(self.time, self.dst)
+	}
+
+	/// Advances the virtual time by the interval and returns the telegram for the new moment.
+	pub fn next_telegram(&mut self) -> RawTelegram {
+		self.time += self.interval.as_secs() as i64;
+		if let Some(clock) = &self.clock {
+			clock.advance(self.interval);
+		}
+		let hours = self.interval.as_secs_f64() / 3600.;
+		let power = self.power();
+		let tariff = self.tariff();
+		if power >= 0. {
+			self.imported[tariff - 1] += power * hours;
+		} else {
+			self.exported[tariff - 1] -= power * hours;
+		}
+		self.gas += self.gas_rate() * hours;
+		let reading_time = self.time - self.time.rem_euclid(GAS_READING_INTERVAL);
+		if reading_time > self.gas_reading.0 {
+			self.gas_reading = (reading_time, self.gas);
+		}
+		self.telegram(power, tariff)
+	}
+
+	/// Current net power in kW, negative when exporting.
+	fn power(&mut self) -> f64 {
+		let hour = self.hour();
+		if self.spike.is_some_and(|(end, _)| end <= self.time) {
+			self.spike = None;
+		}
+		if self.spike.is_none() {
+			let probability = self.profile.appliance_spikes_per_day * self.interval.as_secs_f64() / SECONDS_PER_DAY as f64;
+			if self.random() < probability {
+				let duration = 300. + self.random() * 3300.;
+				let power = self.profile.appliance_power * (0.4 + 0.6 * self.random());
+				self.spike = Some((self.time + duration as i64, power));
+			}
+		}
+		let appliance = self.spike.map_or(0., |(_, power)| power);
+		let daylight = &self.profile.daylight;
+		let solar = if daylight.contains(&hour) {
+			self.profile.solar_peak * (PI * (hour - daylight.start) / (daylight.end - daylight.start)).sin()
+		} else {
+			0.
+		};
+		self.profile.baseload + appliance - solar
+	}
+
+	fn gas_rate(&self) -> f64 {
+		let hour = (self.time.rem_euclid(SECONDS_PER_DAY) / 3600) as u8;
+		if self.profile.heating_schedule.iter().any(|hours| hours.contains(&hour)) {
+			self.profile.heating_gas_rate
+		} else {
+			0.
+		}
+	}
+
+	/// Returns 1 for the low tariff from 23:00 to 07:00 and 2 otherwise.
+	fn tariff(&self) -> usize {
+		let hour = self.hour();
+		if (7.0..23.0).contains(&hour) {
+			2
+		} else {
+			1
+		}
+	}
+
+	fn hour(&self) -> f64 {
+		self.time.rem_euclid(SECONDS_PER_DAY) as f64 / 3600.
+	}
+
+	/// Returns a pseudo-random number in 0..1 using xorshift.
+	fn random(&mut self) -> f64 {
+		self.rng ^= self.rng << 13;
+		self.rng ^= self.rng >> 7;
+		self.rng ^= self.rng << 17;
+		(self.rng >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	fn telegram(&self, power: f64, tariff: usize) -> RawTelegram {
+		let telegram = format!(
+			"/ISk5\\2MT382-1000\r\n\
+			\r\n\
+			1-3:0.2.8(50)\r\n\
+			0-0:1.0.0({})\r\n\
+			0-0:96.1.1(4B384547303034303436333935353037)\r\n\
+			1-0:1.8.1({:010.3}*kWh)\r\n\
+			1-0:1.8.2({:010.3}*kWh)\r\n\
+			1-0:2.8.1({:010.3}*kWh)\r\n\
+			1-0:2.8.2({:010.3}*kWh)\r\n\
+			0-0:96.14.0({tariff:04})\r\n\
+			1-0:1.7.0({:06.3}*kW)\r\n\
+			1-0:2.7.0({:06.3}*kW)\r\n\
+			0-1:24.1.0(003)\r\n\
+			0-1:96.1.0(3232323241424344313233343536373839)\r\n\
+			0-1:24.2.1({})({:09.3}*m3)\r\n\
+			!",
+			self.timestamp(),
+			self.imported[0],
+			self.imported[1],
+			self.exported[0],
+			self.exported[1],
+			power.max(0.),
+			(-power).max(0.),
+			to_timestamp(self.gas_reading.0, self.dst),
+			self.gas_reading.1,
+		);
+		let crc = crc16(telegram.as_bytes());
+		RawTelegram {
+			contents: format!("{telegram}{crc:04X}\r\n").into_bytes(),
+		}
+	}
+}
+
+impl Iterator for SimulatedMeter {
+	type Item = RawTelegram;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.next_telegram())
+	}
+}
+
+fn to_timestamp(time: i64, dst: Option<bool>) -> Timestamp {
+	let (year, month, day) = civil_from_days(time.div_euclid(SECONDS_PER_DAY));
+	let seconds = time.rem_euclid(SECONDS_PER_DAY);
+	Timestamp {
+		year,
+		month,
+		day,
+		hour: (seconds / 3600) as u8,
+		minute: (seconds / 60 % 60) as u8,
+		second: (seconds % 60) as u8,
+		dst,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::clock::Clock;
+	use crate::report::EnergyReport;
+
+	#[test]
+	fn test_simulated_meter() {
+		let clock = MockClock::new();
+		let start = clock.now();
+		let mut meter = SimulatedMeter::new("240228220000W".parse().unwrap())
+			.with_interval(Duration::from_secs(60))
+			.with_seed(42)
+			.with_clock(clock.clone());
+		let mut report = EnergyReport::new();
+		let mut previous_gas = None;
+		for telegram in meter.by_ref().take(2 * 24 * 60) {
+			let telegram = telegram.parse().unwrap();
+			let gas = telegram.gas().unwrap();
+			assert!(previous_gas.is_none_or(|previous| previous <= gas.volume));
+			assert_eq!(0, gas.timestamp.minute % 5);
+			previous_gas = Some(gas.volume);
+			report.record(&telegram);
+		}
+		assert_eq!("240301220000W", meter.timestamp().to_string());
+		assert_eq!(Duration::from_secs(2 * 24 * 60 * 60), clock.now() - start);
+
+		let daily = report.daily();
+		let periods = daily.iter().map(|summary| summary.period.to_string()).collect::<Vec<_>>();
+		assert_eq!(["2024-02-28", "2024-02-29", "2024-03-01"], periods.as_slice());
+		let day = &daily[1];
+		// the heating runs for 9 hours a day
+		assert!((day.gas.unwrap() - 9. * 1.2).abs() < 0.01, "{:?}", day.gas);
+		assert!(day.imported.tariff1 > 0. && day.imported.tariff2 > 0.);
+		assert!(day.exported.tariff2 > 10.);
+		assert_eq!(0., day.exported.tariff1);
+	}
+}
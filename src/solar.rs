@@ -0,0 +1,242 @@
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::telegram::Telegram;
+use crate::topology::Reading;
+
+/// Self-consumption and export tracking for the installations with solar panels.
+///
+/// Feed the telegrams of the grid meter into [SolarTracker::record_grid()], it returns a [SolarSummary] every time the configured
+/// interval elapses. To get the self-consumption figures in the summary as well, feed the telegrams of a second dongle on the
+/// production meter into [SolarTracker::record_production_telegram()] or report the total production from the inverter with
+/// [SolarTracker::record_production()].
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::solar::SolarTracker;
+///
+/// let telegram = |imported: f64, exported: f64| {
+///     let telegram = format!(
+///         "/ISk5\\2ME382-1003\r\n\r\n1-0:1.8.1({imported:09.3}*kWh)\r\n1-0:1.8.2(00000.000*kWh)\r\n\
+///         1-0:2.8.1({exported:09.3}*kWh)\r\n1-0:2.8.2(00000.000*kWh)\r\n1-0:1.7.0(0000.00*kW)\r\n!\r\n"
+///     );
+///     parse_telegram(telegram.as_bytes()).unwrap()
+/// };
+/// let mut tracker = SolarTracker::new(Duration::from_secs(3600));
+/// let start = Instant::now();
+/// tracker.record_production(100.);
+/// assert!(tracker.record_grid_at(&telegram(10., 20.), start).is_none());
+/// tracker.record_production(103.);
+/// let summary = tracker.record_grid_at(&telegram(10.5, 22.), start + Duration::from_secs(3600)).unwrap();
+/// assert_eq!(Some(1.), summary.self_consumed());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SolarTracker<C = SystemClock> {
+	clock: C,
+	interval: Duration,
+	interval_start: Option<(Instant, Registers)>,
+	production: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Registers {
+	imported: f64,
+	exported: f64,
+	production: Option<f64>,
+}
+
+impl SolarTracker {
+	/// Creates a new [SolarTracker] instance that emits a summary every `interval`.
+	pub fn new(interval: Duration) -> Self {
+		Self::with_clock(interval, SystemClock)
+	}
+}
+
+impl<C: Clock> SolarTracker<C> {
+	/// Creates a new [SolarTracker] instance that uses `clock` as a source of time, see [clock](crate::clock).
+	pub fn with_clock(interval: Duration, clock: C) -> Self {
+		Self {
+			clock,
+			interval,
+			interval_start: None,
+			production: None,
+		}
+	}
+
+	/// Records the current total production in kWh.
+	///
+	/// The value must be cumulative, the production over the interval is calculated as the difference of the values current at
+	/// its start and at its end.
+	pub fn record_production(&mut self, total: f64) {
+		self.production = Some(total);
+	}
+
+	/// Records the total production from the telegram of the production meter.
+	///
+	/// `reading` selects the register that counts the produced energy: [Reading::ExportedPower] for the meter that reports the
+	/// production as delivered by the client, [Reading::ImportedPower] for the meter that reports it as delivered to the client
+	/// and [Reading::NetPower] for the net production. Telegrams without the net metering registers are ignored.
+	pub fn record_production_telegram(&mut self, telegram: &Telegram, reading: Reading) {
+		if let Some(net) = telegram.net_metering() {
+			self.record_production(match reading {
+				Reading::NetPower => net.exported - net.imported,
+				Reading::ImportedPower => net.imported,
+				Reading::ExportedPower => net.exported,
+			});
+		}
+	}
+
+	/// Records the telegram of the grid meter received at the current moment.
+	///
+	/// Returns the summary of the finished interval if it has elapsed. Telegrams without the net metering registers are ignored.
+	pub fn record_grid(&mut self, telegram: &Telegram) -> Option<SolarSummary> {
+		self.record_grid_at(telegram, self.clock.now())
+	}
+
+	/// Records the telegram of the grid meter received at the specified moment.
+	///
+	/// `at` is expected to be non-decreasing between the calls.
+	pub fn record_grid_at(&mut self, telegram: &Telegram, at: Instant) -> Option<SolarSummary> {
+		let net = telegram.net_metering()?;
+		let registers = Registers {
+			imported: net.imported,
+			exported: net.exported,
+			production: self.production,
+		};
+		let Some((start, start_registers)) = self.interval_start else {
+			self.interval_start = Some((at, registers));
+			return None;
+		};
+		let duration = at.saturating_duration_since(start);
+		if duration < self.interval {
+			return None;
+		}
+		self.interval_start = Some((at, registers));
+		Some(SolarSummary {
+			duration,
+			imported: registers.imported - start_registers.imported,
+			exported: registers.exported - start_registers.exported,
+			produced: registers
+				.production
+				.zip(start_registers.production)
+				.map(|(end, start)| end - start),
+		})
+	}
+}
+
+/// Energy totals over a single interval of [SolarTracker], all values are in kWh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolarSummary {
+	/// Actual duration of the interval
+	pub duration: Duration,
+	/// Energy delivered from the grid
+	pub imported: f64,
+	/// Energy delivered to the grid
+	pub exported: f64,
+	/// Energy produced by the solar panels, `None` if the production is not reported
+	pub produced: Option<f64>,
+}
+
+impl SolarSummary {
+	/// Returns the part of the produced energy that was consumed locally.
+	pub fn self_consumed(&self) -> Option<f64> {
+		self.produced.map(|produced| (produced - self.exported).max(0.))
+	}
+
+	/// Returns the ratio of the self-consumed energy to the produced energy (0 to 1), `None` if nothing was produced.
+	pub fn self_consumption_ratio(&self) -> Option<f64> {
+		let produced = self.produced.filter(|&produced| produced > 0.)?;
+		Some((self.self_consumed()? / produced).min(1.))
+	}
+
+	/// Returns the total energy consumed locally, i.e. imported plus self-consumed.
+	pub fn consumed(&self) -> Option<f64> {
+		Some(self.imported + self.self_consumed()?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+
+	use super::{SolarSummary, SolarTracker};
+	use crate::parse_telegram;
+	use crate::telegram::Telegram;
+	use crate::test_telegrams::{DSMR5, with_crc};
+	use crate::topology::Reading;
+
+	fn telegram(imported: &str, exported: &str) -> Telegram {
+		let telegram = DSMR5
+			.replace("1-0:1.8.1(123456.789*kWh)", &format!("1-0:1.8.1({imported}*kWh)"))
+			.replace("1-0:2.8.1(123456.789*kWh)", &format!("1-0:2.8.1({exported}*kWh)"));
+		parse_telegram(&with_crc(&telegram)).unwrap()
+	}
+
+	#[test]
+	fn test_solar() {
+		let start = Instant::now();
+		let at = |secs: u64| start + Duration::from_secs(secs);
+		let mut tracker = SolarTracker::new(Duration::from_secs(60));
+
+		assert_eq!(None, tracker.record_grid_at(&telegram("000010.000", "000020.000"), at(0)));
+		assert_eq!(None, tracker.record_grid_at(&telegram("000010.250", "000020.000"), at(30)));
+		let summary = tracker.record_grid_at(&telegram("000010.500", "000020.000"), at(61)).unwrap();
+		assert_eq!(
+			SolarSummary {
+				duration: Duration::from_secs(61),
+				imported: 0.5,
+				exported: 0.,
+				produced: None,
+			},
+			summary
+		);
+		assert_eq!(None, summary.self_consumption_ratio());
+
+		tracker.record_production(50.);
+		let summary = tracker
+			.record_grid_at(&telegram("000010.500", "000022.000"), at(121))
+			.unwrap();
+		assert_eq!(None, summary.produced);
+
+		tracker.record_production(54.);
+		let summary = tracker
+			.record_grid_at(&telegram("000010.500", "000025.000"), at(181))
+			.unwrap();
+		assert_eq!(Some(4.), summary.produced);
+		assert_eq!(Some(1.), summary.self_consumed());
+		assert_eq!(Some(0.25), summary.self_consumption_ratio());
+		assert_eq!(Some(1.), summary.consumed());
+
+		let summary = tracker
+			.record_grid_at(&telegram("000011.000", "000025.000"), at(241))
+			.unwrap();
+		assert_eq!(Some(0.), summary.produced);
+		assert_eq!(None, summary.self_consumption_ratio());
+	}
+
+	#[test]
+	fn test_production_telegram() {
+		let start = Instant::now();
+		let mut tracker = SolarTracker::new(Duration::from_secs(60));
+		tracker.record_production_telegram(&telegram("000000.100", "000050.000"), Reading::ExportedPower);
+		tracker.record_grid_at(&telegram("000010.000", "000020.000"), start);
+		tracker.record_production_telegram(&telegram("000000.100", "000053.000"), Reading::ExportedPower);
+		let summary = tracker
+			.record_grid_at(&telegram("000010.000", "000021.000"), start + Duration::from_secs(60))
+			.unwrap();
+		assert_eq!(Some(3.), summary.produced);
+		assert_eq!(Some(2.), summary.self_consumed());
+
+		let mut tracker = SolarTracker::new(Duration::from_secs(60));
+		tracker.record_production_telegram(&telegram("000001.000", "000050.000"), Reading::NetPower);
+		tracker.record_grid_at(&telegram("000010.000", "000020.000"), start);
+		tracker.record_production_telegram(&telegram("000001.000", "000054.000"), Reading::NetPower);
+		let summary = tracker
+			.record_grid_at(&telegram("000010.000", "000020.000"), start + Duration::from_secs(60))
+			.unwrap();
+		assert_eq!(Some(4.), summary.produced);
+	}
+}
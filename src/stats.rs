@@ -0,0 +1,320 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use futures_util::Stream;
+
+use crate::clock::{Clock, SystemClock};
+use crate::reader::{CrcCheck, RawTelegram};
+
+/// Telegram arrival statistics over a sliding time window.
+///
+/// Call [TelegramStats::record()] for every received telegram and [TelegramStats::snapshot()] to get the current state of the
+/// statistics. If you have a [Stream] of [RawTelegram], it's more convenient to wrap it in [StatsStream] that does the recording
+/// automatically.
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use homey_energy_dongle::reader::RawTelegram;
+/// use homey_energy_dongle::stats::TelegramStats;
+///
+/// let mut stats = TelegramStats::new(Duration::from_secs(60));
+/// let start = Instant::now();
+/// let telegram = RawTelegram {
+///     contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n".to_vec(),
+/// };
+/// stats.record_at(&telegram, start);
+/// stats.record_at(&telegram, start + Duration::from_secs(1));
+/// let snapshot = stats.snapshot_at(start + Duration::from_secs(2));
+/// assert_eq!(2, snapshot.telegrams);
+/// assert_eq!(Some(Duration::from_secs(1)), snapshot.mean_interval);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TelegramStats<C = SystemClock> {
+	clock: C,
+	window: Duration,
+	arrivals: VecDeque<Instant>,
+	malformed: VecDeque<Instant>,
+	crc_failed: VecDeque<Instant>,
+	total_telegrams: u64,
+	total_malformed: u64,
+	total_crc_failed: u64,
+}
+
+impl TelegramStats {
+	/// Creates a new [TelegramStats] instance that calculates the statistics over the last `window` of time.
+	pub fn new(window: Duration) -> Self {
+		Self::with_clock(window, SystemClock)
+	}
+}
+
+impl<C: Clock> TelegramStats<C> {
+	/// Creates a new [TelegramStats] instance that uses `clock` as a source of time, see [clock](crate::clock).
+	pub fn with_clock(window: Duration, clock: C) -> Self {
+		Self {
+			clock,
+			window,
+			arrivals: VecDeque::new(),
+			malformed: VecDeque::new(),
+			crc_failed: VecDeque::new(),
+			total_telegrams: 0,
+			total_malformed: 0,
+			total_crc_failed: 0,
+		}
+	}
+
+	/// Record the arrival of `telegram` at the current moment.
+	pub fn record(&mut self, telegram: &RawTelegram) {
+		self.record_at(telegram, self.clock.now());
+	}
+
+	/// Record the arrival of `telegram` at the specified moment.
+	///
+	/// `at` is expected to be non-decreasing between the calls.
+	pub fn record_at(&mut self, telegram: &RawTelegram, at: Instant) {
+		self.arrivals.push_back(at);
+		self.total_telegrams += 1;
+		match telegram.check_crc() {
+			CrcCheck::Valid | CrcCheck::Missing => {}
+			CrcCheck::Mismatch => {
+				self.crc_failed.push_back(at);
+				self.total_crc_failed += 1;
+			}
+			CrcCheck::Malformed => {
+				self.malformed.push_back(at);
+				self.total_malformed += 1;
+			}
+		}
+		self.evict(at);
+	}
+
+	/// Returns the statistics as of the current moment.
+	pub fn snapshot(&self) -> StatsSnapshot {
+		self.snapshot_at(self.clock.now())
+	}
+
+	/// Returns the statistics as of the specified moment.
+	pub fn snapshot_at(&self, now: Instant) -> StatsSnapshot {
+		let window_start = now.checked_sub(self.window);
+		let in_window = |at: &&Instant| window_start.is_none_or(|window_start| **at >= window_start);
+		let arrivals = self.arrivals.iter().filter(in_window).copied().collect::<Vec<_>>();
+		let intervals = arrivals
+			.windows(2)
+			.map(|pair| pair[1].saturating_duration_since(pair[0]))
+			.collect::<Vec<_>>();
+		let mean_interval = (!intervals.is_empty()).then(|| intervals.iter().sum::<Duration>() / intervals.len() as u32);
+		let jitter = mean_interval.map(|mean_interval| {
+			let mean = mean_interval.as_secs_f64();
+			let variance = intervals
+				.iter()
+				.map(|interval| (interval.as_secs_f64() - mean).powi(2))
+				.sum::<f64>()
+				/ intervals.len() as f64;
+			Duration::from_secs_f64(variance.sqrt())
+		});
+		let since_last = self.arrivals.back().map(|last| now.saturating_duration_since(*last));
+		let longest_gap = intervals.iter().copied().chain(since_last).max().unwrap_or_default();
+		StatsSnapshot {
+			window: self.window,
+			telegrams: arrivals.len(),
+			rate: arrivals.len() as f64 / self.window.as_secs_f64(),
+			mean_interval,
+			jitter,
+			longest_gap,
+			since_last,
+			malformed: self.malformed.iter().filter(in_window).count(),
+			crc_failed: self.crc_failed.iter().filter(in_window).count(),
+			total_telegrams: self.total_telegrams,
+			total_malformed: self.total_malformed,
+			total_crc_failed: self.total_crc_failed,
+		}
+	}
+
+	fn evict(&mut self, now: Instant) {
+		let Some(window_start) = now.checked_sub(self.window) else {
+			return;
+		};
+		for queue in [&mut self.arrivals, &mut self.malformed, &mut self.crc_failed] {
+			while queue.front().is_some_and(|at| *at < window_start) {
+				queue.pop_front();
+			}
+		}
+	}
+}
+
+/// Point-in-time copy of the statistics collected by [TelegramStats].
+///
+/// Fields without the `total_` prefix only account for the telegrams received within the last `window`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSnapshot {
+	/// Length of the sliding window
+	pub window: Duration,
+	/// Number of telegrams received
+	pub telegrams: usize,
+	/// Telegrams per second
+	pub rate: f64,
+	/// Average time between two consecutive telegrams, `None` if fewer than 2 telegrams were received
+	pub mean_interval: Option<Duration>,
+	/// Standard deviation of the time between two consecutive telegrams, `None` if fewer than 2 telegrams were received
+	pub jitter: Option<Duration>,
+	/// Longest time between two consecutive telegrams including the time since the last telegram
+	pub longest_gap: Duration,
+	/// Time since the last received telegram, `None` if no telegrams were received yet
+	pub since_last: Option<Duration>,
+	/// Number of telegrams with a malformed footer
+	pub malformed: usize,
+	/// Number of telegrams with CRC mismatch
+	pub crc_failed: usize,
+	/// Number of telegrams received since the creation of [TelegramStats]
+	pub total_telegrams: u64,
+	/// Number of telegrams with a malformed footer received since the creation of [TelegramStats]
+	pub total_malformed: u64,
+	/// Number of telegrams with CRC mismatch received since the creation of [TelegramStats]
+	pub total_crc_failed: u64,
+}
+
+/// Cloneable handle to the [TelegramStats] collected by [StatsStream].
+///
+/// Can be moved to a different task (e.g., the one serving the health endpoint) to query the statistics.
+#[derive(Debug)]
+pub struct StatsHandle<C = SystemClock> {
+	stats: Arc<Mutex<TelegramStats<C>>>,
+}
+
+impl<C> Clone for StatsHandle<C> {
+	fn clone(&self) -> Self {
+		Self {
+			stats: Arc::clone(&self.stats),
+		}
+	}
+}
+
+impl<C: Clock> StatsHandle<C> {
+	/// Returns the statistics as of the current moment.
+	pub fn snapshot(&self) -> StatsSnapshot {
+		self.stats.lock().unwrap_or_else(PoisonError::into_inner).snapshot()
+	}
+}
+
+/// Wrapper over a [Stream] of [RawTelegram] that records every passing telegram into [TelegramStats].
+///
+/// The telegrams are passed through unchanged. Use [StatsStream::handle()] to get access to the collected statistics.
+pub struct StatsStream<S, C = SystemClock> {
+	stats: Arc<Mutex<TelegramStats<C>>>,
+	inner: S,
+}
+
+impl<S: Stream<Item = RawTelegram>> StatsStream<S> {
+	/// Creates a new [StatsStream] calculating the statistics over the last `window` of time.
+	pub fn new(inner: S, window: Duration) -> Self {
+		Self::with_stats(inner, TelegramStats::new(window))
+	}
+}
+
+impl<S: Stream<Item = RawTelegram>, C: Clock> StatsStream<S, C> {
+	/// Creates a new [StatsStream] recording into the supplied `stats`.
+	pub fn with_stats(inner: S, stats: TelegramStats<C>) -> Self {
+		StatsStream {
+			stats: Arc::new(Mutex::new(stats)),
+			inner,
+		}
+	}
+
+	/// Returns the handle for querying the collected statistics.
+	pub fn handle(&self) -> StatsHandle<C> {
+		StatsHandle {
+			stats: Arc::clone(&self.stats),
+		}
+	}
+}
+
+impl<S: Stream<Item = RawTelegram> + Unpin, C: Clock> Stream for StatsStream<S, C> {
+	type Item = RawTelegram;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let Some(telegram) = ready!(Pin::new(&mut self.inner).poll_next(cx)) else {
+			return Poll::Ready(None);
+		};
+		self.stats.lock().unwrap_or_else(PoisonError::into_inner).record(&telegram);
+		Poll::Ready(Some(telegram))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+
+	use super::TelegramStats;
+	use crate::reader::RawTelegram;
+
+	#[test]
+	fn test_stats() {
+		let valid = RawTelegram {
+			contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n".to_vec(),
+		};
+		let crc_failed = RawTelegram {
+			contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BE\r\n".to_vec(),
+		};
+		let malformed = RawTelegram {
+			contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5\r\n".to_vec(),
+		};
+
+		let start = Instant::now();
+		let at = |secs: u64| start + Duration::from_secs(secs);
+		let mut stats = TelegramStats::new(Duration::from_secs(10));
+
+		let snapshot = stats.snapshot_at(start);
+		assert_eq!(0, snapshot.telegrams);
+		assert_eq!(None, snapshot.mean_interval);
+		assert_eq!(None, snapshot.since_last);
+
+		stats.record_at(&valid, at(0));
+		stats.record_at(&crc_failed, at(1));
+		stats.record_at(&valid, at(2));
+		stats.record_at(&malformed, at(6));
+		let snapshot = stats.snapshot_at(at(7));
+		assert_eq!(4, snapshot.telegrams);
+		assert_eq!(0.4, snapshot.rate);
+		assert_eq!(Some(Duration::from_secs(2)), snapshot.mean_interval);
+		assert_eq!(Duration::from_secs(4), snapshot.longest_gap);
+		assert_eq!(Some(Duration::from_secs(1)), snapshot.since_last);
+		assert_eq!(1, snapshot.crc_failed);
+		assert_eq!(1, snapshot.malformed);
+		let jitter = snapshot.jitter.unwrap();
+		assert!(jitter > Duration::from_millis(1414) && jitter < Duration::from_millis(1415));
+
+		// first 3 telegrams leave the window
+		let snapshot = stats.snapshot_at(at(15));
+		assert_eq!(1, snapshot.telegrams);
+		assert_eq!(None, snapshot.mean_interval);
+		assert_eq!(Duration::from_secs(9), snapshot.longest_gap);
+		assert_eq!(0, snapshot.crc_failed);
+		assert_eq!(1, snapshot.malformed);
+		assert_eq!(4, snapshot.total_telegrams);
+		assert_eq!(1, snapshot.total_crc_failed);
+	}
+
+	#[cfg(feature = "test-util")]
+	#[test]
+	fn test_stats_mock_clock() {
+		use crate::clock::MockClock;
+
+		let telegram = RawTelegram {
+			contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n".to_vec(),
+		};
+		let clock = MockClock::new();
+		let mut stats = TelegramStats::with_clock(Duration::from_secs(10), clock.clone());
+		stats.record(&telegram);
+		clock.advance(Duration::from_secs(3));
+		stats.record(&telegram);
+		clock.advance(Duration::from_secs(1));
+		let snapshot = stats.snapshot();
+		assert_eq!(2, snapshot.telegrams);
+		assert_eq!(Some(Duration::from_secs(3)), snapshot.mean_interval);
+		assert_eq!(Some(Duration::from_secs(1)), snapshot.since_last);
+	}
+}
@@ -0,0 +1,635 @@
+use std::fmt;
+use std::str::{self, FromStr};
+
+pub use demand::{AverageDemand, DEMAND_PERIOD, PeakRecord};
+pub use lenient::{LenientParser, ParseWarning};
+pub use mbus::{GasReading, ThermalMeterKind, ThermalReading, WaterReading};
+pub use net::NetMetering;
+pub use power_quality::{PowerFailureEvent, PowerQuality};
+pub use switch::SwitchPosition;
+pub use tariff::{Tariff, TariffChanged, TariffChanges};
+pub use text::{TextMessage, TextMessageChanged, TextMessageTracker};
+pub use version::{DsmrVersion, VERSION, VERSION_EMUCS};
+
+use crate::reader::{RawTelegram, crc16};
+
+mod demand;
+mod lenient;
+mod mbus;
+mod net;
+mod power_quality;
+mod switch;
+mod tariff;
+mod text;
+mod version;
+
+/// Parse the bytes of a single DSMR telegram into a [Telegram].
+///
+/// The input is expected to be exactly one telegram: the header line starting with "/", the data lines and the footer line
+/// starting with "!" optionally followed by the CRC. This is the format of the [RawTelegram] instances produced by
+/// [RawTelegramReader](crate::reader::RawTelegramReader). If the footer contains a CRC it's validated against the telegram
+/// contents. The CRC can only be omitted in the telegrams of the legacy DSMR versions, see [DsmrVersion].
+///
+/// This function never panics regardless of the input, so it's safe to call it on the untrusted data received over the network.
+/// The processing time is linear to the input size. This is checked by the `parse_telegram` target of the `cargo fuzz` setup in
+/// the repository.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::telegram::Obis;
+///
+/// let telegram = parse_telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n").unwrap();
+/// assert_eq!("test", telegram.header);
+/// assert_eq!(Some("000123.456*kWh"), telegram.value(Obis::new(1, 0, 1, 8, 1)));
+/// ```
+pub fn parse_telegram(bytes: &[u8]) -> Result<Telegram, ParseError> {
+	parse_telegram_with(bytes, |line, _| Err(ParseError::InvalidLine { line }))
+}
+
+/// Parses the telegram calling `on_invalid_line` with the 1-based number and the contents of every malformed data line, the line
+/// is skipped if the callback returns `Ok`.
+fn parse_telegram_with(
+	bytes: &[u8],
+	mut on_invalid_line: impl FnMut(usize, &str) -> Result<(), ParseError>,
+) -> Result<Telegram, ParseError> {
+	let text = str::from_utf8(bytes).map_err(|e| ParseError::InvalidCharacter { offset: e.valid_up_to() })?;
+	if let Some((offset, _)) = text
+		.char_indices()
+		.find(|(_, c)| !(c.is_ascii_graphic() || matches!(c, ' ' | '\r' | '\n')))
+	{
+		return Err(ParseError::InvalidCharacter { offset });
+	}
+
+	let rest = text.strip_prefix('/').ok_or(ParseError::MissingHeader)?;
+	let (header, body) = rest.split_once('\n').ok_or(ParseError::MissingFooter)?;
+	let header = header.strip_suffix('\r').unwrap_or(header);
+
+	let footer_offset = if body.starts_with('!') {
+		0
+	} else {
+		body.find("\n!").ok_or(ParseError::MissingFooter)? + 1
+	};
+	let (body, footer) = body.split_at_checked(footer_offset).ok_or(ParseError::MissingFooter)?;
+	let crc_checked_len = text.len() - footer.len() + 1;
+	let footer = footer.strip_prefix('!').ok_or(ParseError::MissingFooter)?;
+	let (crc, trailer) = footer.split_once('\n').unwrap_or((footer, ""));
+	if !trailer.is_empty() {
+		return Err(ParseError::TrailingData);
+	}
+	let crc = crc.strip_suffix('\r').unwrap_or(crc);
+	let crc = if crc.is_empty() {
+		None
+	} else {
+		if crc.len() != 4 || !crc.bytes().all(|b| b.is_ascii_hexdigit()) {
+			return Err(ParseError::InvalidCrc);
+		}
+		let expected = u16::from_str_radix(crc, 16).map_err(|_| ParseError::InvalidCrc)?;
+		let calculated = crc16(bytes.get(..crc_checked_len).ok_or(ParseError::MissingFooter)?);
+		if expected != calculated {
+			return Err(ParseError::CrcMismatch { expected, calculated });
+		}
+		Some(expected)
+	};
+
+	let mut objects = Vec::<CosemObject>::new();
+	// whether the last object line was skipped, its continuation lines must not be attached to the object before it
+	let mut skipped = false;
+	for (index, line) in body.split('\n').enumerate() {
+		let line = line.strip_suffix('\r').unwrap_or(line);
+		if line.trim().is_empty() {
+			continue;
+		}
+		// header is line 1
+		let line_number = index + 2;
+		if line.starts_with('(') {
+			// continuation of the values of the previous object, used by the legacy DSMR versions, e.g. for the gas reading
+			match (objects.last_mut().filter(|_| !skipped), parse_values(line)) {
+				(Some(object), Some(values)) => object.values.extend(values),
+				_ => on_invalid_line(line_number, line)?,
+			}
+		} else if let Some(object) = parse_object(line) {
+			objects.push(object);
+			skipped = false;
+		} else {
+			on_invalid_line(line_number, line)?;
+			skipped = true;
+		}
+	}
+
+	let telegram = Telegram {
+		header: header.to_string(),
+		objects,
+		crc,
+	};
+	if telegram.crc.is_none() && telegram.version().requires_crc() {
+		return Err(ParseError::MissingCrc);
+	}
+	Ok(telegram)
+}
+
+fn parse_object(line: &str) -> Option<CosemObject> {
+	let (obis, values) = line.split_at_checked(line.find('(')?)?;
+	Some(CosemObject {
+		obis: obis.trim_end().parse().ok()?,
+		values: parse_values(values)?,
+	})
+}
+
+fn parse_values(mut rest: &str) -> Option<Vec<String>> {
+	let mut values = vec![];
+	while let Some(value_start) = rest.strip_prefix('(') {
+		let (value, value_rest) = value_start.split_once(')')?;
+		if value.contains('(') {
+			return None;
+		}
+		values.push(value.to_string());
+		rest = value_rest;
+	}
+	rest.trim().is_empty().then_some(values)
+}
+
+/// Parses a numeric value with an optional unit, e.g. "000123.456*kWh", the unit must match `unit` if present.
+pub(crate) fn parse_quantity(value: &str, unit: &str) -> Option<f64> {
+	let number = match value.split_once('*') {
+		Some((number, value_unit)) if value_unit.eq_ignore_ascii_case(unit) => number,
+		Some(_) => return None,
+		None => value,
+	};
+	if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+		return None;
+	}
+	number.parse().ok()
+}
+
+/// Parsed DSMR telegram.
+///
+/// Produced by [parse_telegram()] or [RawTelegram::parse()]. Use [Telegram::get()] or [Telegram::value()] to access the data
+/// objects by their OBIS reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Telegram {
+	/// Contents of the header line without the leading "/", this is the identification of the meter, e.g. "ISk5\2MT382-1000"
+	pub header: String,
+	/// Data objects in the order of their appearance in the telegram
+	pub objects: Vec<CosemObject>,
+	/// CRC from the footer if present, it's already validated during parsing
+	pub crc: Option<u16>,
+}
+
+impl Telegram {
+	/// Returns the first data object with the specified OBIS reference.
+	pub fn get(&self, obis: Obis) -> Option<&CosemObject> {
+		self.objects.iter().find(|object| object.obis == obis)
+	}
+
+	/// Returns the first value of the first data object with the specified OBIS reference.
+	pub fn value(&self, obis: Obis) -> Option<&str> {
+		self.get(obis).and_then(|object| object.values.first()).map(String::as_str)
+	}
+
+	/// Returns the time of the telegram creation (0-0:1.0.0), the legacy DSMR versions don't report it.
+	pub fn timestamp(&self) -> Option<Timestamp> {
+		self.value(Obis::new(0, 0, 1, 0, 0))?.parse().ok()
+	}
+}
+
+impl RawTelegram {
+	/// Parse the contents of this telegram, see [parse_telegram()] for details.
+	pub fn parse(&self) -> Result<Telegram, ParseError> {
+		parse_telegram(&self.contents)
+	}
+}
+
+/// Single data line of a DSMR telegram, e.g. "1-0:1.8.1(000123.456*kWh)".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CosemObject {
+	pub obis: Obis,
+	/// Contents of each of the parenthesized values without the parentheses, e.g. "000123.456*kWh"
+	pub values: Vec<String>,
+}
+
+/// OBIS reference identifying a data object in a DSMR telegram.
+///
+/// The group F is not used in DSMR telegrams, so it's omitted. Implements [FromStr] and [fmt::Display] for the "A-B:C.D.E"
+/// textual form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Obis {
+	pub a: u8,
+	pub b: u8,
+	pub c: u8,
+	pub d: u8,
+	pub e: u8,
+}
+
+impl Obis {
+	pub const fn new(a: u8, b: u8, c: u8, d: u8, e: u8) -> Self {
+		Self { a, b, c, d, e }
+	}
+}
+
+impl FromStr for Obis {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		fn group(s: &str) -> Result<u8, ParseError> {
+			if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+				return Err(ParseError::InvalidObis);
+			}
+			s.parse().map_err(|_| ParseError::InvalidObis)
+		}
+
+		let (a, rest) = s.split_once('-').ok_or(ParseError::InvalidObis)?;
+		let (b, rest) = rest.split_once(':').ok_or(ParseError::InvalidObis)?;
+		let (c, rest) = rest.split_once('.').ok_or(ParseError::InvalidObis)?;
+		let (d, e) = rest.split_once('.').ok_or(ParseError::InvalidObis)?;
+		Ok(Self::new(group(a)?, group(b)?, group(c)?, group(d)?, group(e)?))
+	}
+}
+
+impl fmt::Display for Obis {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}-{}:{}.{}.{}", self.a, self.b, self.c, self.d, self.e)
+	}
+}
+
+/// Timestamp in the DSMR "YYMMDDhhmmssX" format where "X" is "S" for the summer time and "W" for the winter time.
+///
+/// The timestamp is in the local time of the meter. The legacy DSMR versions omit the "X" part. Implements [FromStr] and
+/// [fmt::Display] for the textual form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Timestamp {
+	/// Full year, e.g. 2024
+	pub year: u16,
+	pub month: u8,
+	pub day: u8,
+	pub hour: u8,
+	pub minute: u8,
+	pub second: u8,
+	/// `Some(true)` if the summer (daylight saving) time is active, `None` if the timestamp doesn't specify it
+	pub dst: Option<bool>,
+}
+
+impl Timestamp {
+	/// Returns the number of seconds since the UNIX epoch.
+	///
+	/// `utc_offset` is the offset of the standard (winter) time of the meter from UTC in seconds, e.g. 3600 for the Central
+	/// European Time. An hour is added to it if the timestamp is in the summer time.
+	pub fn to_unix_time(&self, utc_offset: i32) -> i64 {
+		let utc_offset = i64::from(utc_offset)
+			+ if self.dst == Some(true) {
+				3600
+			} else {
+				0
+			};
+		days_from_civil(self.year, self.month, self.day) * 24 * 60 * 60
+			+ i64::from(self.hour) * 3600
+			+ i64::from(self.minute) * 60
+			+ i64::from(self.second)
+			- utc_offset
+	}
+}
+
+impl FromStr for Timestamp {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (digits, dst) = s.split_at_checked(12).ok_or(ParseError::InvalidTimestamp)?;
+		if !digits.bytes().all(|b| b.is_ascii_digit()) {
+			return Err(ParseError::InvalidTimestamp);
+		}
+		let dst = match dst {
+			"S" => Some(true),
+			"W" => Some(false),
+			"" => None,
+			_ => return Err(ParseError::InvalidTimestamp),
+		};
+		let field = |offset: usize| {
+			digits
+				.get(offset..offset + 2)
+				.and_then(|field| field.parse::<u8>().ok())
+				.ok_or(ParseError::InvalidTimestamp)
+		};
+		let out = Self {
+			year: 2000 + u16::from(field(0)?),
+			month: field(2)?,
+			day: field(4)?,
+			hour: field(6)?,
+			minute: field(8)?,
+			second: field(10)?,
+			dst,
+		};
+		if !(1..=12).contains(&out.month) || !(1..=31).contains(&out.day) || out.hour > 23 || out.minute > 59 || out.second > 59 {
+			return Err(ParseError::InvalidTimestamp);
+		}
+		Ok(out)
+	}
+}
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{:02}{:02}{:02}{:02}{:02}{:02}{}",
+			self.year % 100,
+			self.month,
+			self.day,
+			self.hour,
+			self.minute,
+			self.second,
+			match self.dst {
+				Some(true) => "S",
+				Some(false) => "W",
+				None => "",
+			}
+		)
+	}
+}
+
+/// Number of days since 1970-01-01, see <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+pub(crate) fn days_from_civil(year: u16, month: u8, day: u8) -> i64 {
+	let year = i64::from(year) - i64::from(month <= 2);
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let month = i64::from(month);
+	let day_of_year = (153
+		* (month
+			+ if month > 2 {
+				-3
+			} else {
+				9
+			}) + 2)
+		/ 5 + i64::from(day)
+		- 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of [days_from_civil()], see <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+pub(crate) fn civil_from_days(days: i64) -> (u16, u8, u8) {
+	let days = days + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days - era * 146_097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 {
+		month_index + 3
+	} else {
+		month_index - 9
+	};
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+	(year as u16, month as u8, day as u8)
+}
+
+/// Possible error scenarios for [parse_telegram()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+	/// Input contains a byte that is not a printable ASCII character, space, CR or LF
+	InvalidCharacter { offset: usize },
+	/// Input doesn't start with the header line
+	MissingHeader,
+	/// Input doesn't contain the footer line
+	MissingFooter,
+	/// Input contains data after the footer line
+	TrailingData,
+	/// CRC in the footer is not a 4-digit hexadecimal number
+	InvalidCrc,
+	/// CRC in the footer doesn't match the telegram contents
+	CrcMismatch { expected: u16, calculated: u16 },
+	/// CRC is missing from the footer while the telegram version requires it
+	MissingCrc,
+	/// Data line is not in the "OBIS(value)(value)..." format, `line` is 1-based
+	InvalidLine { line: usize },
+	/// OBIS reference is not in the "A-B:C.D.E" format
+	InvalidObis,
+	/// Timestamp is not in the "YYMMDDhhmmssX" format
+	InvalidTimestamp,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::InvalidCharacter { offset } => write!(f, "Invalid character at offset {offset}"),
+			Self::MissingHeader => write!(f, "Missing telegram header"),
+			Self::MissingFooter => write!(f, "Missing telegram footer"),
+			Self::TrailingData => write!(f, "Unexpected data after the telegram footer"),
+			Self::InvalidCrc => write!(f, "Invalid CRC format"),
+			Self::CrcMismatch { expected, calculated } => {
+				write!(f, "CRC mismatch, expected: {expected:04X}, calculated: {calculated:04X}")
+			}
+			Self::MissingCrc => write!(f, "Missing CRC"),
+			Self::InvalidLine { line } => write!(f, "Invalid data line: {line}"),
+			Self::InvalidObis => write!(f, "Invalid OBIS reference"),
+			Self::InvalidTimestamp => write!(f, "Invalid timestamp"),
+		}
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Telegram {
+	fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+		<RawTelegram as arbitrary::Arbitrary>::arbitrary(u)?
+			.parse()
+			.map_err(|_| arbitrary::Error::IncorrectFormat)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Obis, ParseError, Timestamp, civil_from_days, days_from_civil, parse_telegram};
+
+	const TELEGRAM: &[u8] = b"/ISk5\\2MT382-1000\r
+\r
+1-3:0.2.8(50)\r
+0-0:1.0.0(101209113020W)\r
+0-0:96.1.1(4B384547303034303436333935353037)\r
+1-0:1.8.1(123456.789*kWh)\r
+1-0:1.8.2(123456.789*kWh)\r
+1-0:2.8.1(123456.789*kWh)\r
+1-0:2.8.2(123456.789*kWh)\r
+0-0:96.14.0(0002)\r
+1-0:1.7.0(01.193*kW)\r
+1-0:2.7.0(00.000*kW)\r
+0-0:96.7.21(00004)\r
+0-0:96.7.9(00002)\r
+1-0:99.97.0(2)(0-0:96.7.19)(101208152415W)(0000000240*s)(101208151004W)(0000000301*s)\r
+1-0:32.32.0(00002)\r
+0-0:96.13.0(303132333435363738393A3B3C3D3E3F)\r
+0-1:24.1.0(003)\r
+0-1:96.1.0(3232323241424344313233343536373839)\r
+0-1:24.2.1(101209112500W)(12785.123*m3)\r
+!CF0C\r
+";
+
+	#[test]
+	fn test_parse_telegram() {
+		let telegram = parse_telegram(TELEGRAM).unwrap();
+		assert_eq!("ISk5\\2MT382-1000", telegram.header);
+		assert_eq!(Some(0xCF0C), telegram.crc);
+		assert_eq!(18, telegram.objects.len());
+		assert_eq!(Some("50"), telegram.value(Obis::new(1, 3, 0, 2, 8)));
+		assert_eq!(Some("01.193*kW"), telegram.value(Obis::new(1, 0, 1, 7, 0)));
+		let power_failures = telegram.get(Obis::new(1, 0, 99, 97, 0)).unwrap();
+		assert_eq!(
+			vec![
+				"2",
+				"0-0:96.7.19",
+				"101208152415W",
+				"0000000240*s",
+				"101208151004W",
+				"0000000301*s"
+			],
+			power_failures.values
+		);
+		let gas = telegram.get(Obis::new(0, 1, 24, 2, 1)).unwrap();
+		assert_eq!(vec!["101209112500W", "12785.123*m3"], gas.values);
+		assert_eq!(None, telegram.get(Obis::new(1, 0, 3, 8, 0)));
+
+		let telegram = parse_telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!\r\n").unwrap();
+		assert_eq!(None, telegram.crc);
+		assert_eq!(1, telegram.objects.len());
+	}
+
+	#[test]
+	fn test_parse_telegram_errors() {
+		assert_eq!(Err(ParseError::MissingHeader), parse_telegram(b""));
+		assert_eq!(Err(ParseError::MissingHeader), parse_telegram(b"1-0:1.8.1(1)\r\n!\r\n"));
+		assert_eq!(Err(ParseError::MissingFooter), parse_telegram(b"/"));
+		assert_eq!(Err(ParseError::MissingFooter), parse_telegram(b"/test\r\n1-0:1.8.1(1)\r\n"));
+		assert_eq!(Err(ParseError::InvalidCrc), parse_telegram(b"/test\r\n!12\r\n"));
+		assert_eq!(Err(ParseError::InvalidCrc), parse_telegram(b"/test\r\n!+123\r\n"));
+		assert_eq!(Err(ParseError::TrailingData), parse_telegram(b"/test\r\n!\r\n/test2"));
+		assert_eq!(
+			Err(ParseError::CrcMismatch {
+				expected: 0xB5BE,
+				calculated: 0xB5BD
+			}),
+			parse_telegram(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BE\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 3 }),
+			parse_telegram(b"/test\r\n\r\n1-0:1.8(1)\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 2 }),
+			parse_telegram(b"/test\r\n1-0:1.8.1(1)x\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 2 }),
+			parse_telegram(b"/test\r\n1-0:1.8.1((1)\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 2 }),
+			parse_telegram(b"/test\r\n1-0:1.8.1(1\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 2 }),
+			parse_telegram(b"/test\r\n1-0:1.8.256(1)\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 3 }),
+			parse_telegram(b"/test\r\n\r\n(1)\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidLine { line: 3 }),
+			parse_telegram(b"/test\r\n1-0:1.8.1(1)\r\n(1\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidCharacter { offset: 9 }),
+			parse_telegram(b"/test\r\n1-\0:1.8.1(1)\r\n!\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidCharacter { offset: 6 }),
+			parse_telegram("/test\r\u{e9}".as_bytes())
+		);
+		assert_eq!(Err(ParseError::InvalidCharacter { offset: 1 }), parse_telegram(b"/\xff"));
+	}
+
+	#[test]
+	fn test_parse_telegram_never_panics() {
+		let long_line = [b"/test\r\n1-0:1.8.1(".as_slice(), &[b'1'; 1 << 20], b")\r\n!\r\n"].concat();
+		assert_eq!(1 << 20, parse_telegram(&long_line).unwrap().objects[0].values[0].len());
+		let long_header = [b"/".as_slice(), &[b'a'; 1 << 20]].concat();
+		assert_eq!(Err(ParseError::MissingFooter), parse_telegram(&long_header));
+		let many_values = [b"/test\r\n1-0:1.8.1".as_slice(), &b"()".repeat(1 << 18), b"\r\n!\r\n"].concat();
+		assert_eq!(1 << 18, parse_telegram(&many_values).unwrap().objects[0].values.len());
+		assert!(parse_telegram(&[0; 1 << 10]).is_err());
+		assert!(parse_telegram(&[b'!'; 1 << 10]).is_err());
+		assert!(parse_telegram(&[b'('; 1 << 10]).is_err());
+
+		// simple xorshift-based fuzzing with the bytes that are meaningful for the parser
+		const ALPHABET: &[u8] = b"/!()\r\n-:.*0123456789ABCDEFkWh\0\xff ";
+		let mut state = 0x2545_F491_4F6C_DD1D_u64;
+		for _ in 0..10_000 {
+			let len = (state % 64) as usize;
+			let mut input = Vec::with_capacity(len + 1);
+			input.push(b'/');
+			for _ in 0..len {
+				state ^= state << 13;
+				state ^= state >> 7;
+				state ^= state << 17;
+				input.push(ALPHABET[(state % ALPHABET.len() as u64) as usize]);
+			}
+			let _ = parse_telegram(&input);
+			let _ = parse_telegram(&input[1..]);
+		}
+	}
+
+	#[test]
+	fn test_obis() {
+		assert_eq!(Ok(Obis::new(1, 0, 1, 8, 1)), "1-0:1.8.1".parse());
+		assert_eq!(Ok(Obis::new(0, 0, 96, 7, 21)), "0-0:96.7.21".parse());
+		assert_eq!("1-0:99.97.0", Obis::new(1, 0, 99, 97, 0).to_string());
+		assert_eq!(Err(ParseError::InvalidObis), "1-0:1.8".parse::<Obis>());
+		assert_eq!(Err(ParseError::InvalidObis), "1-0:1.8.+1".parse::<Obis>());
+		assert_eq!(Err(ParseError::InvalidObis), "1-0:1.8.1.2".parse::<Obis>());
+		assert_eq!(Err(ParseError::InvalidObis), "".parse::<Obis>());
+	}
+
+	#[test]
+	fn test_timestamp() {
+		let timestamp = "101209113020W".parse::<Timestamp>().unwrap();
+		assert_eq!(
+			Timestamp {
+				year: 2010,
+				month: 12,
+				day: 9,
+				hour: 11,
+				minute: 30,
+				second: 20,
+				dst: Some(false),
+			},
+			timestamp
+		);
+		assert_eq!("101209113020W", timestamp.to_string());
+		assert_eq!(Some(true), "240630235959S".parse::<Timestamp>().unwrap().dst);
+		let legacy = "101209113020".parse::<Timestamp>().unwrap();
+		assert_eq!(None, legacy.dst);
+		assert_eq!("101209113020", legacy.to_string());
+		assert_eq!(1_291_890_620, timestamp.to_unix_time(3600));
+		assert_eq!(1_291_890_620 + 3600, legacy.to_unix_time(0));
+		assert_eq!(
+			1_719_784_799,
+			"240630235959S".parse::<Timestamp>().unwrap().to_unix_time(3600)
+		);
+		assert_eq!(Err(ParseError::InvalidTimestamp), "10120911302".parse::<Timestamp>());
+		assert_eq!(Err(ParseError::InvalidTimestamp), "101209113020X".parse::<Timestamp>());
+		assert_eq!(Err(ParseError::InvalidTimestamp), "101309113020W".parse::<Timestamp>());
+		assert_eq!(Err(ParseError::InvalidTimestamp), "10120911302+W".parse::<Timestamp>());
+		assert_eq!(Err(ParseError::InvalidTimestamp), "10120911302\u{e9}".parse::<Timestamp>());
+	}
+
+	#[test]
+	fn test_civil_days() {
+		assert_eq!(0, days_from_civil(1970, 1, 1));
+		assert_eq!(19_783, days_from_civil(2024, 3, 1));
+		for days in [0, 59, 60, 11_016, 19_782, 19_783, 30_000] {
+			let (year, month, day) = civil_from_days(days);
+			assert_eq!(days, days_from_civil(year, month, day));
+		}
+		assert_eq!((2024, 2, 29), civil_from_days(19_782));
+	}
+}
@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use super::{Obis, Telegram, Timestamp, civil_from_days, days_from_civil, parse_quantity};
+
+const CURRENT_AVERAGE_DEMAND: Obis = Obis::new(1, 0, 1, 4, 0);
+const LAST_AVERAGE_DEMAND: Obis = Obis::new(1, 0, 1, 5, 0);
+const MAXIMUM_DEMAND: Obis = Obis::new(1, 0, 1, 6, 0);
+const MAXIMUM_DEMAND_HISTORY: Obis = Obis::new(0, 0, 98, 1, 0);
+
+/// Length of the demand period used by the e-MUCS meters for the capacity tariff.
+pub const DEMAND_PERIOD: Duration = Duration::from_secs(DEMAND_PERIOD_MINUTES as u64 * 60);
+const DEMAND_PERIOD_MINUTES: u8 = 15;
+
+/// Average power demand over the quarter-hour demand periods.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AverageDemand {
+	/// Average demand in the running period so far (1-0:1.4.0) in kW
+	pub current: f64,
+	/// Average demand of the last completed period (1-0:1.5.0) in kW, not all meters report it
+	pub last: Option<f64>,
+	/// Start of the running period, derived from the telegram timestamp
+	pub current_period_start: Option<Timestamp>,
+}
+
+impl AverageDemand {
+	/// Returns the start of the last completed period, i.e. [DEMAND_PERIOD] before the start of the running one.
+	///
+	/// The period running at midnight is preceded by the one starting at 23:45 of the previous day. Returns `None` if the start
+	/// of the running period is unknown.
+	pub fn last_period_start(&self) -> Option<Timestamp> {
+		const MINUTES_PER_DAY: i64 = 24 * 60;
+		let start = self.current_period_start?;
+		let minutes = days_from_civil(start.year, start.month, start.day) * MINUTES_PER_DAY
+			+ i64::from(start.hour) * 60
+			+ i64::from(start.minute)
+			- i64::from(DEMAND_PERIOD_MINUTES);
+		let (year, month, day) = civil_from_days(minutes.div_euclid(MINUTES_PER_DAY));
+		let minutes = minutes.rem_euclid(MINUTES_PER_DAY);
+		Some(Timestamp {
+			year,
+			month,
+			day,
+			hour: (minutes / 60) as u8,
+			minute: (minutes % 60) as u8,
+			..start
+		})
+	}
+}
+
+/// Maximum quarter-hour average demand over a month.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeakRecord {
+	/// Time when the record was added to the history (the start of the following month), `None` for the running month
+	pub captured: Option<Timestamp>,
+	/// Time of the peak
+	pub timestamp: Timestamp,
+	/// Peak average demand in kW
+	pub demand: f64,
+}
+
+impl Telegram {
+	/// Returns the average demand reported by the e-MUCS meters (Belgian Fluvius meters).
+	///
+	/// Returns `None` if the telegram doesn't contain the current average demand.
+	pub fn average_demand(&self) -> Option<AverageDemand> {
+		let demand = |obis: Obis| self.value(obis).and_then(|value| parse_quantity(value, "kW"));
+		Some(AverageDemand {
+			current: demand(CURRENT_AVERAGE_DEMAND)?,
+			last: demand(LAST_AVERAGE_DEMAND),
+			current_period_start: self.timestamp().map(|timestamp| Timestamp {
+				minute: timestamp.minute - timestamp.minute % DEMAND_PERIOD_MINUTES,
+				second: 0,
+				..timestamp
+			}),
+		})
+	}
+}
+
+impl Telegram {
+	/// Returns the maximum average demand of the running month (1-0:1.6.0).
+	pub fn monthly_peak(&self) -> Option<PeakRecord> {
+		let [timestamp, demand, ..] = self.get(MAXIMUM_DEMAND)?.values.as_slice() else {
+			return None;
+		};
+		Some(PeakRecord {
+			captured: None,
+			timestamp: timestamp.parse().ok()?,
+			demand: parse_quantity(demand, "kW")?,
+		})
+	}
+
+	/// Returns the history of the maximum average demand for the last 13 months (0-0:98.1.0), the most recent month first.
+	///
+	/// Malformed entries of the history are skipped.
+	pub fn peak_history(&self) -> Vec<PeakRecord> {
+		// (count)(1-0:1.6.0)(1-0:1.6.0)(captured)(timestamp)(demand)(captured)(timestamp)(demand)...
+		let Some([_count, _captured_obis, _peak_obis, records @ ..]) =
+			self.get(MAXIMUM_DEMAND_HISTORY).map(|object| object.values.as_slice())
+		else {
+			return vec![];
+		};
+		let mut history = records
+			.chunks_exact(3)
+			.filter_map(|record| {
+				let [captured, timestamp, demand] = record else {
+					return None;
+				};
+				Some(PeakRecord {
+					captured: Some(captured.parse().ok()?),
+					timestamp: timestamp.parse().ok()?,
+					demand: parse_quantity(demand, "kW")?,
+				})
+			})
+			.collect::<Vec<_>>();
+		history.sort_by_key(|record| Reverse(record.captured));
+		history
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse_telegram;
+	use crate::telegram::{AverageDemand, PeakRecord, Timestamp};
+	use crate::test_telegrams::{DSMR5, with_crc};
+
+	#[test]
+	fn test_average_demand() {
+		assert_eq!(None, parse_telegram(&with_crc(DSMR5)).unwrap().average_demand());
+
+		let telegram = DSMR5.replace("1-3:0.2.8(50)", "0-0:96.1.4(50217)").replace(
+			"1-0:2.8.2(123456.789*kWh)\r\n",
+			"1-0:2.8.2(123456.789*kWh)\r\n1-0:1.4.0(02.351*kW)\r\n",
+		);
+		let demand = parse_telegram(&with_crc(&telegram)).unwrap().average_demand().unwrap();
+		let start = Timestamp {
+			year: 2010,
+			month: 12,
+			day: 9,
+			hour: 11,
+			minute: 30,
+			second: 0,
+			dst: Some(false),
+		};
+		assert_eq!(
+			AverageDemand {
+				current: 2.351,
+				last: None,
+				current_period_start: Some(start),
+			},
+			demand
+		);
+		assert_eq!(Some(Timestamp { minute: 15, ..start }), demand.last_period_start());
+
+		let telegram = telegram
+			.replace("0-0:1.0.0(101209113020W)", "0-0:1.0.0(101209000512W)")
+			.replace("1-0:1.4.0(02.351*kW)", "1-0:1.4.0(02.351*kW)\r\n1-0:1.5.0(01.000*kW)");
+		let demand = parse_telegram(&with_crc(&telegram)).unwrap().average_demand().unwrap();
+		assert_eq!(Some(1.), demand.last);
+		assert_eq!(
+			Some(Timestamp {
+				hour: 0,
+				minute: 0,
+				..start
+			}),
+			demand.current_period_start
+		);
+		assert_eq!(
+			Some(Timestamp {
+				day: 8,
+				hour: 23,
+				minute: 45,
+				..start
+			}),
+			demand.last_period_start()
+		);
+	}
+
+	#[test]
+	fn test_peaks() {
+		let telegram = parse_telegram(&with_crc(DSMR5)).unwrap();
+		assert_eq!(None, telegram.monthly_peak());
+		assert!(telegram.peak_history().is_empty());
+
+		let telegram = DSMR5.replace("1-3:0.2.8(50)", "0-0:96.1.4(50217)").replace(
+			"1-0:2.8.2(123456.789*kWh)\r\n",
+			"1-0:2.8.2(123456.789*kWh)\r\n1-0:1.6.0(200509134558S)(01.111*kW)\r\n0-0:98.1.0(3)(1-0:1.6.0)(1-0:1.6.0)\
+			(200401000000S)(200305122139W)(05.980*kW)(200501000000W)(200423192538S)(03.695*kW)(200301000000W)(20021003542)(04.318*kW)\r\n",
+		);
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		let timestamp = |s: &str| s.parse::<Timestamp>().unwrap();
+		assert_eq!(
+			Some(PeakRecord {
+				captured: None,
+				timestamp: timestamp("200509134558S"),
+				demand: 1.111,
+			}),
+			telegram.monthly_peak()
+		);
+		assert_eq!(
+			vec![
+				PeakRecord {
+					captured: Some(timestamp("200501000000W")),
+					timestamp: timestamp("200423192538S"),
+					demand: 3.695,
+				},
+				PeakRecord {
+					captured: Some(timestamp("200401000000S")),
+					timestamp: timestamp("200305122139W"),
+					demand: 5.98,
+				},
+			],
+			telegram.peak_history()
+		);
+	}
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use super::{ParseError, Telegram, parse_telegram_with};
+
+type WarningCallback = Box<dyn FnMut(ParseWarning) + Send>;
+
+/// Malformed data line skipped by [LenientParser].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+	/// Sequence number of the telegram, see [LenientParser::next_sequence()]
+	pub sequence: u64,
+	/// 1-based number of the line in the telegram, the header is line 1
+	pub line: usize,
+	/// Contents of the line without the line terminator
+	pub contents: String,
+}
+
+impl fmt::Display for ParseWarning {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Skipped invalid line {} of telegram {}: {}",
+			self.line, self.sequence, self.contents
+		)
+	}
+}
+
+/// Parser that skips the malformed data lines instead of rejecting the whole telegram.
+///
+/// Some meters emit vendor-specific lines that don't follow the "OBIS(value)(value)..." format, [parse_telegram()] fails on
+/// them with [ParseError::InvalidLine]. This parser drops such lines and reports each of them as a [ParseWarning] to the
+/// callback attached with [LenientParser::with_warnings()]. All other errors, e.g., the CRC mismatch, are still returned.
+///
+/// The parser numbers the telegrams passed to [LenientParser::parse()] starting from 0, the number is reported in
+/// [ParseWarning::sequence] to match the warnings with the telegrams.
+///
+/// # Example
+/// ```
+/// use std::sync::mpsc;
+///
+/// use homey_energy_dongle::telegram::{LenientParser, Obis};
+///
+/// let (sender, warnings) = mpsc::channel();
+/// let mut parser = LenientParser::new().with_warnings(move |warning| sender.send(warning).unwrap());
+/// let telegram = parser.parse(b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n%vendor%\r\n!\r\n").unwrap();
+/// assert_eq!(Some("000123.456*kWh"), telegram.value(Obis::new(1, 0, 1, 8, 1)));
+/// let warning = warnings.try_recv().unwrap();
+/// assert_eq!((0, 4, "%vendor%"), (warning.sequence, warning.line, warning.contents.as_str()));
+/// ```
+///
+/// [parse_telegram()]: crate::parse_telegram
+pub struct LenientParser {
+	next_sequence: u64,
+	on_warning: Option<WarningCallback>,
+}
+
+impl LenientParser {
+	pub fn new() -> Self {
+		Self {
+			next_sequence: 0,
+			on_warning: None,
+		}
+	}
+
+	/// Calls `on_warning` for every skipped line, replaces the previously attached callback.
+	///
+	/// The callback is called synchronously during parsing, send the warnings to a channel to process them elsewhere.
+	pub fn with_warnings(mut self, on_warning: impl FnMut(ParseWarning) + Send + 'static) -> Self {
+		self.on_warning = Some(Box::new(on_warning));
+		self
+	}
+
+	/// Returns the sequence number that will be assigned to the next parsed telegram.
+	pub fn next_sequence(&self) -> u64 {
+		self.next_sequence
+	}
+
+	/// Parses a single telegram skipping the malformed data lines, see [parse_telegram()](crate::parse_telegram) for the
+	/// format.
+	///
+	/// Every call increments the sequence number, even if the telegram fails to parse.
+	pub fn parse(&mut self, bytes: &[u8]) -> Result<Telegram, ParseError> {
+		let sequence = self.next_sequence;
+		self.next_sequence += 1;
+		parse_telegram_with(bytes, |line, contents| {
+			if let Some(on_warning) = &mut self.on_warning {
+				on_warning(ParseWarning {
+					sequence,
+					line,
+					contents: contents.to_string(),
+				});
+			}
+			Ok(())
+		})
+	}
+}
+
+impl Default for LenientParser {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl fmt::Debug for LenientParser {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("LenientParser")
+			.field("next_sequence", &self.next_sequence)
+			.field("on_warning", &self.on_warning.is_some())
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex, PoisonError};
+
+	use super::*;
+	use crate::telegram::Obis;
+
+	#[test]
+	fn test_lenient_parser() {
+		let warnings = Arc::new(Mutex::new(vec![]));
+		let mut parser = LenientParser::new().with_warnings({
+			let warnings = Arc::clone(&warnings);
+			move |warning| warnings.lock().unwrap_or_else(PoisonError::into_inner).push(warning)
+		});
+
+		let telegram = parser
+			.parse(b"/test\r\n\r\n1-0:1.8.1(1)\r\n0-1:24.3.0(2)\r\n(3)\r\n!\r\n")
+			.unwrap();
+		assert_eq!(vec!["2", "3"], telegram.get(Obis::new(0, 1, 24, 3, 0)).unwrap().values);
+
+		// the continuation of the skipped line is skipped as well
+		let telegram = parser
+			.parse(b"/test\r\n\r\n1-0:1.8.1(1)\r\n1-0:1.8(2)\r\n(3)\r\n1-0:1.8.2((4)\r\n!\r\n")
+			.unwrap();
+		assert_eq!(1, telegram.objects.len());
+		assert_eq!(vec!["1"], telegram.objects[0].values);
+
+		assert_eq!(Err(ParseError::MissingFooter), parser.parse(b"/test\r\n1-0:1.8(1)\r\n"));
+		assert!(parser.parse(b"/test\r\n(1)\r\n!\r\n").is_ok());
+		assert_eq!(4, parser.next_sequence());
+
+		let warning = |sequence, line, contents: &str| ParseWarning {
+			sequence,
+			line,
+			contents: contents.to_string(),
+		};
+		assert_eq!(
+			vec![
+				warning(1, 4, "1-0:1.8(2)"),
+				warning(1, 5, "(3)"),
+				warning(1, 6, "1-0:1.8.2((4)"),
+				warning(3, 2, "(1)"),
+			],
+			*warnings.lock().unwrap_or_else(PoisonError::into_inner)
+		);
+	}
+}
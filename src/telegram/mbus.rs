@@ -0,0 +1,286 @@
+use super::{DsmrVersion, Obis, Telegram, Timestamp, parse_quantity};
+
+/// Reading of the gas meter connected to the M-Bus of the electricity meter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasReading {
+	/// M-Bus channel of the gas meter (1-4)
+	pub channel: u8,
+	/// Time of the last reading as reported by the gas meter
+	pub timestamp: Timestamp,
+	/// Total consumption in m³
+	pub volume: f64,
+}
+
+/// Reading of the water meter connected to the M-Bus of the electricity meter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaterReading {
+	/// M-Bus channel of the water meter (1-4)
+	pub channel: u8,
+	/// Time of the last reading as reported by the water meter
+	pub timestamp: Timestamp,
+	/// Total consumption in m³
+	pub volume: f64,
+}
+
+/// Reading of the thermal energy (heat or cold) meter connected to the M-Bus of the electricity meter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalReading {
+	/// M-Bus channel of the thermal meter (1-4)
+	pub channel: u8,
+	pub kind: ThermalMeterKind,
+	/// Time of the last reading as reported by the thermal meter
+	pub timestamp: Timestamp,
+	/// Total energy in GJ
+	pub energy: f64,
+}
+
+/// Kind of the thermal energy meter as specified by its M-Bus device type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThermalMeterKind {
+	/// Heat meter measuring at the outlet (device type 4)
+	Heat,
+	/// Heat meter measuring at the inlet (device type 12)
+	HeatInlet,
+	/// Cold meter (device type 10)
+	Cold,
+}
+
+impl ThermalMeterKind {
+	/// Converts the M-Bus device type (0-n:24.1.0).
+	pub fn from_device_type(device_type: u8) -> Option<Self> {
+		match device_type {
+			DEVICE_TYPE_HEAT => Some(Self::Heat),
+			DEVICE_TYPE_HEAT_INLET => Some(Self::HeatInlet),
+			DEVICE_TYPE_COLD => Some(Self::Cold),
+			_ => None,
+		}
+	}
+}
+
+const DEVICE_TYPE_GAS: u8 = 3;
+const DEVICE_TYPE_HEAT: u8 = 4;
+const DEVICE_TYPE_WATER: u8 = 7;
+const DEVICE_TYPE_COLD: u8 = 10;
+const DEVICE_TYPE_HEAT_INLET: u8 = 12;
+
+impl Telegram {
+	/// Returns the last reading of the gas meter.
+	///
+	/// The data object is selected according to the [DsmrVersion] of the telegram: 0-n:24.2.1 for DSMR 4 and 5, 0-n:24.2.3 for
+	/// e-MUCS and 0-n:24.3.0 for the legacy versions. The gas meter channel is identified by the device type 3, or, if the
+	/// telegram doesn't specify device types, the first channel with the reading.
+	pub fn gas(&self) -> Option<GasReading> {
+		let version = self.version();
+		let channel = self.gas_channel(version)?;
+		let (timestamp, volume) = self.mbus_reading(version, gas_reading_obis(version, channel))?;
+		Some(GasReading {
+			channel,
+			timestamp,
+			volume: parse_quantity(volume, "m3")?,
+		})
+	}
+
+	/// Returns the last reading of the water meter.
+	///
+	/// The water meter channel is identified by the device type 7, the reading is taken from the 0-n:24.2.1 data object (0-n:24.3.0
+	/// for the legacy versions). The volume is reported in m³, readings in dm³ (liters) are converted.
+	pub fn water(&self) -> Option<WaterReading> {
+		let version = self.version();
+		let channel = self.mbus_channel(DEVICE_TYPE_WATER)?;
+		let obis = match version {
+			DsmrVersion::Legacy => Obis::new(0, channel, 24, 3, 0),
+			DsmrVersion::V4 | DsmrVersion::V5 | DsmrVersion::EMucs | DsmrVersion::Unknown => Obis::new(0, channel, 24, 2, 1),
+		};
+		let (timestamp, volume) = self.mbus_reading(version, obis)?;
+		let volume = parse_quantity(volume, "m3").or_else(|| parse_quantity(volume, "dm3").map(|liters| liters / 1000.))?;
+		Some(WaterReading {
+			channel,
+			timestamp,
+			volume,
+		})
+	}
+
+	/// Returns the last readings of all thermal energy meters, in the order of their M-Bus channels.
+	///
+	/// The thermal meters are identified by the device types 4, 10 and 12, the readings are taken from the 0-n:24.2.1 data objects
+	/// and must be reported in GJ.
+	pub fn thermal(&self) -> Vec<ThermalReading> {
+		let version = self.version();
+		(1..=4)
+			.filter_map(|channel| {
+				let device_type = self.value(Obis::new(0, channel, 24, 1, 0))?.parse().ok()?;
+				let kind = ThermalMeterKind::from_device_type(device_type)?;
+				let (timestamp, energy) = self.mbus_reading(version, Obis::new(0, channel, 24, 2, 1))?;
+				Some(ThermalReading {
+					channel,
+					kind,
+					timestamp,
+					energy: parse_quantity(energy, "GJ")?,
+				})
+			})
+			.collect()
+	}
+}
+
+impl Telegram {
+	pub(super) fn gas_channel(&self, version: DsmrVersion) -> Option<u8> {
+		let has_device_types = (1..=4).any(|channel| self.get(Obis::new(0, channel, 24, 1, 0)).is_some());
+		if has_device_types {
+			self.mbus_channel(DEVICE_TYPE_GAS)
+		} else {
+			(1..=4).find(|&channel| self.get(gas_reading_obis(version, channel)).is_some())
+		}
+	}
+
+	/// Returns the first M-Bus channel with the specified device type (0-n:24.1.0).
+	pub(super) fn mbus_channel(&self, device_type: u8) -> Option<u8> {
+		(1..=4).find(|&channel| {
+			self
+				.value(Obis::new(0, channel, 24, 1, 0))
+				.and_then(|value| value.parse::<u8>().ok())
+				== Some(device_type)
+		})
+	}
+
+	/// Returns the capture timestamp and the raw value of the M-Bus reading.
+	pub(super) fn mbus_reading(&self, version: DsmrVersion, obis: Obis) -> Option<(Timestamp, &str)> {
+		let values = &self.get(obis)?.values;
+		let (timestamp, value) = match version {
+			// (timestamp)(00)(60)(1)(0-1:24.2.1)(m3)(value), value is on the next line
+			DsmrVersion::Legacy => (values.first()?, values.get(6)?),
+			DsmrVersion::V4 | DsmrVersion::V5 | DsmrVersion::EMucs | DsmrVersion::Unknown => (values.first()?, values.get(1)?),
+		};
+		Some((timestamp.parse().ok()?, value))
+	}
+}
+
+fn gas_reading_obis(version: DsmrVersion, channel: u8) -> Obis {
+	match version {
+		DsmrVersion::Legacy => Obis::new(0, channel, 24, 3, 0),
+		DsmrVersion::EMucs => Obis::new(0, channel, 24, 2, 3),
+		DsmrVersion::V4 | DsmrVersion::V5 | DsmrVersion::Unknown => Obis::new(0, channel, 24, 2, 1),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse_telegram;
+	use crate::telegram::{GasReading, ThermalMeterKind, ThermalReading, Timestamp, WaterReading};
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_gas() {
+		let gas = GasReading {
+			channel: 1,
+			timestamp: Timestamp {
+				year: 2010,
+				month: 12,
+				day: 9,
+				hour: 11,
+				minute: 25,
+				second: 0,
+				dst: Some(false),
+			},
+			volume: 12785.123,
+		};
+		assert_eq!(Some(gas), parse_telegram(&with_crc(DSMR5)).unwrap().gas());
+
+		let telegram = DSMR5
+			.replace("1-3:0.2.8(50)", "0-0:96.1.4(50217)")
+			.replace("0-1:24.2.1", "0-1:24.2.3");
+		assert_eq!(Some(gas), parse_telegram(&with_crc(&telegram)).unwrap().gas());
+
+		let telegram = DSMR5.replace("0-1:24.1.0(003)", "0-1:24.1.0(007)");
+		assert_eq!(None, parse_telegram(&with_crc(&telegram)).unwrap().gas());
+
+		let gas = GasReading {
+			channel: 1,
+			timestamp: Timestamp {
+				year: 2012,
+				month: 10,
+				day: 30,
+				hour: 14,
+				minute: 0,
+				second: 0,
+				dst: None,
+			},
+			volume: 1.234,
+		};
+		assert_eq!(Some(gas), parse_telegram(DSMR3.as_bytes()).unwrap().gas());
+	}
+
+	#[test]
+	fn test_water() {
+		assert_eq!(None, parse_telegram(&with_crc(DSMR5)).unwrap().water());
+
+		let water = WaterReading {
+			channel: 2,
+			timestamp: Timestamp {
+				year: 2010,
+				month: 12,
+				day: 9,
+				hour: 11,
+				minute: 25,
+				second: 0,
+				dst: Some(false),
+			},
+			volume: 123.456,
+		};
+		let telegram = format!(
+			"{}0-2:24.1.0(007)\r\n0-2:24.2.1(101209112500W)(00123.456*m3)\r\n!",
+			DSMR5.strip_suffix('!').unwrap()
+		);
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		assert_eq!(Some(water), telegram.water());
+		assert_eq!(1, telegram.gas().unwrap().channel);
+
+		let telegram = format!(
+			"{}0-2:24.1.0(007)\r\n0-2:24.2.1(101209112500W)(123456*dm3)\r\n!",
+			DSMR5.strip_suffix('!').unwrap()
+		);
+		assert_eq!(Some(water), parse_telegram(&with_crc(&telegram)).unwrap().water());
+
+		let telegram = DSMR5.replace("0-1:24.1.0(003)", "0-1:24.1.0(007)");
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		assert_eq!(None, telegram.gas());
+		assert_eq!(Some(1), telegram.water().map(|water| water.channel));
+	}
+
+	#[test]
+	fn test_thermal() {
+		assert!(parse_telegram(&with_crc(DSMR5)).unwrap().thermal().is_empty());
+
+		let timestamp = Timestamp {
+			year: 2010,
+			month: 12,
+			day: 9,
+			hour: 11,
+			minute: 25,
+			second: 0,
+			dst: Some(false),
+		};
+		let telegram = format!(
+			"{}0-2:24.1.0(004)\r\n0-2:24.2.1(101209112500W)(00012.345*GJ)\r\n0-3:24.1.0(010)\r\n0-3:24.2.1(101209112500W)(00001.500*GJ)\r\n0-4:24.1.0(012)\r\n0-4:24.2.1(101209112500W)(00002.000*MWh)\r\n!",
+			DSMR5.strip_suffix('!').unwrap()
+		);
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		assert_eq!(
+			vec![
+				ThermalReading {
+					channel: 2,
+					kind: ThermalMeterKind::Heat,
+					timestamp,
+					energy: 12.345,
+				},
+				ThermalReading {
+					channel: 3,
+					kind: ThermalMeterKind::Cold,
+					timestamp,
+					energy: 1.5,
+				},
+			],
+			telegram.thermal()
+		);
+		assert_eq!(1, telegram.gas().unwrap().channel);
+	}
+}
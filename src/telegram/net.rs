@@ -0,0 +1,108 @@
+use super::{Obis, Telegram, parse_quantity};
+
+const ENERGY_DELIVERED: [Obis; 2] = [Obis::new(1, 0, 1, 8, 1), Obis::new(1, 0, 1, 8, 2)];
+const ENERGY_RETURNED: [Obis; 2] = [Obis::new(1, 0, 2, 8, 1), Obis::new(1, 0, 2, 8, 2)];
+const POWER_DELIVERED: Obis = Obis::new(1, 0, 1, 7, 0);
+const POWER_RETURNED: Obis = Obis::new(1, 0, 2, 7, 0);
+
+/// Net energy and power exchanged with the grid.
+///
+/// The net values are positive when importing from the grid and negative when exporting to the grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetMetering {
+	/// Total energy delivered to the client over all tariffs (1-0:1.8.1 + 1-0:1.8.2) in kWh
+	pub imported: f64,
+	/// Total energy delivered by the client over all tariffs (1-0:2.8.1 + 1-0:2.8.2) in kWh
+	pub exported: f64,
+	/// Current power delivered to the client (1-0:1.7.0) in kW
+	pub power_imported: f64,
+	/// Current power delivered by the client (1-0:2.7.0) in kW
+	pub power_exported: f64,
+}
+
+impl NetMetering {
+	/// Returns the net energy in kWh, i.e. `imported - exported`.
+	pub fn net_energy(&self) -> f64 {
+		self.imported - self.exported
+	}
+
+	/// Returns the current net power in kW, i.e. `power_imported - power_exported`.
+	pub fn net_power(&self) -> f64 {
+		self.power_imported - self.power_exported
+	}
+
+	/// Returns `true` if more power is currently delivered by the client than to it.
+	pub fn is_exporting(&self) -> bool {
+		self.net_power() < 0.
+	}
+}
+
+impl Telegram {
+	/// Returns the net metering summary computed from the cumulative registers and the instantaneous power.
+	///
+	/// Returns `None` if any of the required values is missing or malformed. Meters without a production register don't report
+	/// 1-0:2.7.0 consistently, so the missing returned power is treated as zero.
+	pub fn net_metering(&self) -> Option<NetMetering> {
+		let sum = |obis: [Obis; 2]| -> Option<f64> { obis.into_iter().map(|obis| parse_quantity(self.value(obis)?, "kWh")).sum() };
+		let power = |obis: Obis| self.value(obis).and_then(|value| parse_quantity(value, "kW"));
+		Some(NetMetering {
+			imported: sum(ENERGY_DELIVERED)?,
+			exported: sum(ENERGY_RETURNED)?,
+			power_imported: power(POWER_DELIVERED)?,
+			power_exported: match self.value(POWER_RETURNED) {
+				Some(value) => parse_quantity(value, "kW")?,
+				None => 0.,
+			},
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse_telegram;
+	use crate::telegram::NetMetering;
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_net_metering() {
+		let net = parse_telegram(&with_crc(DSMR5)).unwrap().net_metering().unwrap();
+		assert_eq!(
+			NetMetering {
+				imported: 246913.578,
+				exported: 246913.578,
+				power_imported: 1.193,
+				power_exported: 0.,
+			},
+			net
+		);
+		assert_eq!(0., net.net_energy());
+		assert_eq!(1.193, net.net_power());
+		assert!(!net.is_exporting());
+
+		let telegram = DSMR5
+			.replace("1-0:1.7.0(01.193*kW)", "1-0:1.7.0(00.000*kW)")
+			.replace("1-0:2.7.0(00.000*kW)", "1-0:2.7.0(02.500*kW)")
+			.replace("1-0:2.8.2(123456.789*kWh)", "1-0:2.8.2(000000.000*kWh)");
+		let net = parse_telegram(&with_crc(&telegram)).unwrap().net_metering().unwrap();
+		assert!((net.net_energy() - 123456.789).abs() < 1e-6);
+		assert_eq!(-2.5, net.net_power());
+		assert!(net.is_exporting());
+
+		let net = parse_telegram(DSMR3.as_bytes()).unwrap().net_metering().unwrap();
+		assert!((net.net_energy() - 246.912).abs() < 1e-9);
+		assert_eq!(0.26, net.net_power());
+
+		let telegram = DSMR5.replace("1-0:2.7.0(00.000*kW)\r\n", "");
+		assert_eq!(
+			0.,
+			parse_telegram(&with_crc(&telegram))
+				.unwrap()
+				.net_metering()
+				.unwrap()
+				.power_exported
+		);
+
+		let telegram = DSMR5.replace("1-0:1.8.2(123456.789*kWh)\r\n", "");
+		assert_eq!(None, parse_telegram(&with_crc(&telegram)).unwrap().net_metering());
+	}
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use super::{Obis, Telegram, Timestamp, parse_quantity};
+
+const POWER_FAILURES: Obis = Obis::new(0, 0, 96, 7, 21);
+const LONG_POWER_FAILURES: Obis = Obis::new(0, 0, 96, 7, 9);
+const POWER_FAILURE_LOG: Obis = Obis::new(1, 0, 99, 97, 0);
+const VOLTAGE_SAGS: [Obis; 3] = [
+	Obis::new(1, 0, 32, 32, 0),
+	Obis::new(1, 0, 52, 32, 0),
+	Obis::new(1, 0, 72, 32, 0),
+];
+const VOLTAGE_SWELLS: [Obis; 3] = [
+	Obis::new(1, 0, 32, 36, 0),
+	Obis::new(1, 0, 52, 36, 0),
+	Obis::new(1, 0, 72, 36, 0),
+];
+
+/// Power quality counters and the power failure event log reported by the meter.
+///
+/// All counters are cumulative since the installation of the meter. The per-phase arrays are indexed by the phase number minus
+/// one, the values for L2 and L3 are `None` for the single-phase meters.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PowerQuality {
+	/// Number of power failures in any phase (0-0:96.7.21)
+	pub power_failures: Option<u32>,
+	/// Number of long power failures in any phase (0-0:96.7.9)
+	pub long_power_failures: Option<u32>,
+	/// Most recent long power failures (1-0:99.97.0), empty if the log is not present in the telegram
+	pub power_failure_log: Vec<PowerFailureEvent>,
+	/// Number of voltage sags per phase (1-0:32.32.0, 1-0:52.32.0, 1-0:72.32.0)
+	pub voltage_sags: [Option<u32>; 3],
+	/// Number of voltage swells per phase (1-0:32.36.0, 1-0:52.36.0, 1-0:72.36.0)
+	pub voltage_swells: [Option<u32>; 3],
+}
+
+/// Single entry of the long power failure event log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerFailureEvent {
+	/// Time of the end of the power failure
+	pub end: Timestamp,
+	pub duration: Duration,
+}
+
+impl Telegram {
+	/// Returns the power quality counters and the power failure event log.
+	///
+	/// Malformed entries of the power failure event log are skipped.
+	pub fn power_quality(&self) -> PowerQuality {
+		let count = |obis: Obis| self.value(obis).and_then(|value| value.parse().ok());
+		PowerQuality {
+			power_failures: count(POWER_FAILURES),
+			long_power_failures: count(LONG_POWER_FAILURES),
+			power_failure_log: self.power_failure_log(),
+			voltage_sags: VOLTAGE_SAGS.map(count),
+			voltage_swells: VOLTAGE_SWELLS.map(count),
+		}
+	}
+
+	fn power_failure_log(&self) -> Vec<PowerFailureEvent> {
+		// (count)(0-0:96.7.19)(end timestamp)(duration)(end timestamp)(duration)...
+		let Some([_count, _log_obis, events @ ..]) = self.get(POWER_FAILURE_LOG).map(|object| object.values.as_slice()) else {
+			return vec![];
+		};
+		events
+			.chunks_exact(2)
+			.filter_map(|event| {
+				let [end, duration] = event else {
+					return None;
+				};
+				Some(PowerFailureEvent {
+					end: end.parse().ok()?,
+					duration: Duration::try_from_secs_f64(parse_quantity(duration, "s")?).ok()?,
+				})
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use crate::parse_telegram;
+	use crate::telegram::{PowerFailureEvent, PowerQuality, Timestamp};
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_power_quality() {
+		let telegram = parse_telegram(&with_crc(DSMR5)).unwrap();
+		assert_eq!(
+			PowerQuality {
+				power_failures: Some(4),
+				long_power_failures: Some(2),
+				power_failure_log: vec![
+					PowerFailureEvent {
+						end: Timestamp {
+							year: 2010,
+							month: 12,
+							day: 8,
+							hour: 15,
+							minute: 24,
+							second: 15,
+							dst: Some(false),
+						},
+						duration: Duration::from_secs(240),
+					},
+					PowerFailureEvent {
+						end: Timestamp {
+							year: 2010,
+							month: 12,
+							day: 8,
+							hour: 15,
+							minute: 10,
+							second: 4,
+							dst: Some(false),
+						},
+						duration: Duration::from_secs(301),
+					},
+				],
+				voltage_sags: [Some(2), Some(1), Some(0)],
+				voltage_swells: [Some(0), Some(3), Some(0)],
+			},
+			telegram.power_quality()
+		);
+
+		let telegram = DSMR5.replace(
+			"1-0:99.97.0(2)(0-0:96.7.19)(101208152415W)(0000000240*s)(101208151004W)(0000000301*s)",
+			"1-0:99.97.0(0)(0-0:96.7.19)",
+		);
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		assert!(telegram.power_quality().power_failure_log.is_empty());
+
+		// the entries with the durations out of range are skipped
+		let telegram = DSMR5.replace("(0000000240*s)", "(99999999999999999999999*s)");
+		let telegram = parse_telegram(&with_crc(&telegram)).unwrap();
+		assert_eq!(
+			vec![Duration::from_secs(301)],
+			telegram
+				.power_quality()
+				.power_failure_log
+				.iter()
+				.map(|event| event.duration)
+				.collect::<Vec<_>>()
+		);
+
+		let telegram = parse_telegram(DSMR3.as_bytes()).unwrap();
+		assert_eq!(PowerQuality::default(), telegram.power_quality());
+	}
+}
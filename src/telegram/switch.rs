@@ -0,0 +1,83 @@
+use std::fmt;
+
+use super::{Obis, Telegram};
+
+const BREAKER_POSITION: Obis = Obis::new(0, 0, 96, 3, 10);
+
+/// Position of the electricity switch (breaker) or of the gas valve, these can be operated remotely by the grid operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwitchPosition {
+	Disconnected,
+	Connected,
+	/// The switch has been released by the grid operator and can be reconnected by the customer
+	ReadyForReconnection,
+}
+
+impl SwitchPosition {
+	/// Converts the numeric value of the switch position object.
+	pub fn from_code(code: u8) -> Option<Self> {
+		match code {
+			0 => Some(Self::Disconnected),
+			1 => Some(Self::Connected),
+			2 => Some(Self::ReadyForReconnection),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for SwitchPosition {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Disconnected => f.write_str("disconnected"),
+			Self::Connected => f.write_str("connected"),
+			Self::ReadyForReconnection => f.write_str("ready for reconnection"),
+		}
+	}
+}
+
+impl Telegram {
+	/// Returns the position of the electricity switch (0-0:96.3.10).
+	///
+	/// Only the older meters report it, it's not part of the DSMR 5 telegram.
+	pub fn breaker_position(&self) -> Option<SwitchPosition> {
+		self.switch_position(BREAKER_POSITION)
+	}
+
+	/// Returns the position of the gas valve (0-n:24.4.0) on the gas meter channel.
+	pub fn valve_position(&self) -> Option<SwitchPosition> {
+		let channel = self.gas_channel(self.version())?;
+		self.switch_position(Obis::new(0, channel, 24, 4, 0))
+	}
+
+	fn switch_position(&self, obis: Obis) -> Option<SwitchPosition> {
+		self.value(obis)?.parse().ok().and_then(SwitchPosition::from_code)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse_telegram;
+	use crate::telegram::SwitchPosition;
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_switch_position() {
+		let telegram = parse_telegram(DSMR3.as_bytes()).unwrap();
+		assert_eq!(Some(SwitchPosition::Connected), telegram.breaker_position());
+		assert_eq!(Some(SwitchPosition::Connected), telegram.valve_position());
+
+		let telegram = DSMR3
+			.replace("0-0:96.3.10(1)", "0-0:96.3.10(0)")
+			.replace("0-1:24.4.0(1)", "0-1:24.4.0(2)");
+		let telegram = parse_telegram(telegram.as_bytes()).unwrap();
+		assert_eq!(Some(SwitchPosition::Disconnected), telegram.breaker_position());
+		assert_eq!(Some(SwitchPosition::ReadyForReconnection), telegram.valve_position());
+
+		let telegram = DSMR3.replace("0-0:96.3.10(1)", "0-0:96.3.10(3)");
+		assert_eq!(None, parse_telegram(telegram.as_bytes()).unwrap().breaker_position());
+
+		let telegram = parse_telegram(&with_crc(DSMR5)).unwrap();
+		assert_eq!(None, telegram.breaker_position());
+		assert_eq!(None, telegram.valve_position());
+	}
+}
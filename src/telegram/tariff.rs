@@ -0,0 +1,184 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use futures_util::Stream;
+
+use super::{DsmrVersion, Obis, Telegram, Timestamp};
+
+const TARIFF_INDICATOR: Obis = Obis::new(0, 0, 96, 14, 0);
+
+/// Tariff currently active in the meter (0-0:96.14.0).
+///
+/// The meaning of the tariffs depends on the country: in the Netherlands tariff 1 is the low (night) tariff, in Belgium tariff 1
+/// is the day tariff. Use [Tariff::is_low()] to get the country-independent interpretation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tariff {
+	Tariff1,
+	Tariff2,
+}
+
+impl Tariff {
+	/// Converts the numeric value of the tariff indicator.
+	pub fn from_code(code: u16) -> Option<Self> {
+		match code {
+			1 => Some(Self::Tariff1),
+			2 => Some(Self::Tariff2),
+			_ => None,
+		}
+	}
+
+	/// Returns `true` if this is the low (cheaper) tariff for the meters of the specified version.
+	pub fn is_low(self, version: DsmrVersion) -> bool {
+		match version {
+			DsmrVersion::EMucs => self == Self::Tariff2,
+			DsmrVersion::Legacy | DsmrVersion::V4 | DsmrVersion::V5 | DsmrVersion::Unknown => self == Self::Tariff1,
+		}
+	}
+}
+
+impl fmt::Display for Tariff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Tariff1 => f.write_str("tariff 1"),
+			Self::Tariff2 => f.write_str("tariff 2"),
+		}
+	}
+}
+
+impl Telegram {
+	/// Returns the currently active tariff.
+	pub fn tariff(&self) -> Option<Tariff> {
+		self.value(TARIFF_INDICATOR)?.parse().ok().and_then(Tariff::from_code)
+	}
+}
+
+/// Event emitted by [TariffChanges] when the active tariff changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TariffChanged {
+	pub from: Tariff,
+	pub to: Tariff,
+	/// Timestamp of the first telegram with the new tariff, `None` for the legacy meters that don't report it
+	pub at: Option<Timestamp>,
+}
+
+/// Wrapper that converts a [Stream] of [Telegram] into a [Stream] of [TariffChanged] events.
+///
+/// The tariff of the first telegram is taken as the initial state and doesn't produce an event. Telegrams without a valid tariff
+/// indicator are skipped.
+///
+/// # Example
+/// ```
+/// use futures_util::{StreamExt, stream};
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::telegram::{Tariff, TariffChanges};
+///
+/// # async fn example() {
+/// let low = parse_telegram(b"/ISk5\\2ME382-1003\r\n\r\n0-0:96.14.0(0001)\r\n!\r\n").unwrap();
+/// let normal = parse_telegram(b"/ISk5\\2ME382-1003\r\n\r\n0-0:96.14.0(0002)\r\n!\r\n").unwrap();
+/// let mut changes = TariffChanges::new(stream::iter([normal.clone(), normal, low]));
+/// let change = changes.next().await.unwrap();
+/// assert_eq!(Tariff::Tariff2, change.from);
+/// assert_eq!(Tariff::Tariff1, change.to);
+/// # }
+/// ```
+pub struct TariffChanges<S> {
+	current: Option<Tariff>,
+	inner: S,
+}
+
+impl<S: Stream<Item = Telegram>> TariffChanges<S> {
+	/// Creates a new [TariffChanges] wrapper over `inner`.
+	pub fn new(inner: S) -> Self {
+		TariffChanges { current: None, inner }
+	}
+
+	/// Returns the tariff of the last processed telegram.
+	pub fn current(&self) -> Option<Tariff> {
+		self.current
+	}
+}
+
+impl<S: Stream<Item = Telegram> + Unpin> Stream for TariffChanges<S> {
+	type Item = TariffChanged;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		loop {
+			let Some(telegram) = ready!(Pin::new(&mut self.inner).poll_next(cx)) else {
+				return Poll::Ready(None);
+			};
+			let Some(to) = telegram.tariff() else {
+				continue;
+			};
+			match self.current.replace(to) {
+				Some(from) if from != to => {
+					return Poll::Ready(Some(TariffChanged {
+						from,
+						to,
+						at: telegram.timestamp(),
+					}));
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures_util::{StreamExt, stream};
+
+	use crate::parse_telegram;
+	use crate::telegram::{DsmrVersion, Tariff, TariffChanged, TariffChanges, Telegram};
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_tariff() {
+		let telegram = parse_telegram(&with_crc(DSMR5)).unwrap();
+		assert_eq!(Some(Tariff::Tariff2), telegram.tariff());
+		assert!(!Tariff::Tariff2.is_low(DsmrVersion::V5));
+		assert!(Tariff::Tariff2.is_low(DsmrVersion::EMucs));
+
+		let telegram = DSMR5.replace("0-0:96.14.0(0002)", "0-0:96.14.0(0003)");
+		assert_eq!(None, parse_telegram(&with_crc(&telegram)).unwrap().tariff());
+	}
+
+	#[tokio::test]
+	async fn test_tariff_changes() {
+		let telegram = |tariff: &str, timestamp: &str| -> Telegram {
+			let telegram = DSMR5
+				.replace("0-0:96.14.0(0002)", &format!("0-0:96.14.0({tariff})"))
+				.replace("0-0:1.0.0(101209113020W)", &format!("0-0:1.0.0({timestamp})"));
+			parse_telegram(&with_crc(&telegram)).unwrap()
+		};
+		let no_tariff = parse_telegram(DSMR3.replace("0-0:96.14.0(0002)\r\n", "").as_bytes()).unwrap();
+		let telegrams = [
+			no_tariff.clone(),
+			telegram("0002", "101209113000W"),
+			telegram("0002", "101209113010W"),
+			telegram("0001", "101209113020W"),
+			no_tariff,
+			telegram("0001", "101209113030W"),
+			telegram("0002", "101209113040W"),
+		];
+		let mut changes = TariffChanges::new(stream::iter(telegrams));
+		assert_eq!(
+			Some(TariffChanged {
+				from: Tariff::Tariff2,
+				to: Tariff::Tariff1,
+				at: Some("101209113020W".parse().unwrap()),
+			}),
+			changes.next().await
+		);
+		assert_eq!(
+			Some(TariffChanged {
+				from: Tariff::Tariff1,
+				to: Tariff::Tariff2,
+				at: Some("101209113040W".parse().unwrap()),
+			}),
+			changes.next().await
+		);
+		assert_eq!(None, changes.next().await);
+		assert_eq!(Some(Tariff::Tariff2), changes.current());
+	}
+}
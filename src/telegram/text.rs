@@ -0,0 +1,145 @@
+use std::str;
+
+use super::{Obis, Telegram};
+
+const TEXT_MESSAGE: Obis = Obis::new(0, 0, 96, 13, 0);
+const TEXT_MESSAGE_CODE: Obis = Obis::new(0, 0, 96, 13, 1);
+
+/// Text message channel of the meter, the utility can use it to push arbitrary messages to the customer.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextMessage {
+	/// Decoded text message (0-0:96.13.0)
+	pub text: Option<String>,
+	/// Decoded text message code (0-0:96.13.1), only sent by the older meters
+	pub code: Option<String>,
+}
+
+impl TextMessage {
+	/// Returns `true` if neither the text nor the code is present.
+	pub fn is_empty(&self) -> bool {
+		self.text.is_none() && self.code.is_none()
+	}
+}
+
+impl Telegram {
+	/// Returns the contents of the text message channel.
+	///
+	/// The messages are transmitted hex-encoded, they are decoded here. Values that are not valid hex are returned as is, invalid
+	/// UTF-8 sequences are replaced with U+FFFD. Empty messages are reported as `None`.
+	pub fn text_message(&self) -> TextMessage {
+		let decode = |obis: Obis| self.value(obis).filter(|value| !value.is_empty()).map(decode_text);
+		TextMessage {
+			text: decode(TEXT_MESSAGE),
+			code: decode(TEXT_MESSAGE_CODE),
+		}
+	}
+}
+
+fn decode_text(value: &str) -> String {
+	if value.len() % 2 != 0 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+		return value.to_string();
+	}
+	let bytes = value
+		.as_bytes()
+		.chunks_exact(2)
+		.filter_map(|pair| u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok())
+		.collect::<Vec<_>>();
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Detects the changes of the text message channel over a sequence of telegrams.
+///
+/// # Example
+/// ```
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::telegram::TextMessageTracker;
+///
+/// let mut tracker = TextMessageTracker::new();
+/// let telegram = parse_telegram(b"/ISk5\\2ME382-1003\r\n\r\n0-0:96.13.0(48656C6C6F)\r\n!\r\n").unwrap();
+/// let changed = tracker.update(&telegram).unwrap();
+/// assert_eq!(Some("Hello"), changed.current.text.as_deref());
+/// assert!(tracker.update(&telegram).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TextMessageTracker {
+	last: Option<TextMessage>,
+}
+
+/// Event emitted by [TextMessageTracker] when the text message changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextMessageChanged {
+	pub previous: TextMessage,
+	pub current: TextMessage,
+}
+
+impl TextMessageTracker {
+	/// Creates a new [TextMessageTracker] instance.
+	///
+	/// The empty text message is considered to be the initial state, so a non-empty message in the first telegram is reported as a
+	/// change.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Processes the next telegram and returns the change event if its text message differs from the previous one.
+	pub fn update(&mut self, telegram: &Telegram) -> Option<TextMessageChanged> {
+		let current = telegram.text_message();
+		let previous = self.last.take().unwrap_or_default();
+		self.last = Some(current.clone());
+		(previous != current).then_some(TextMessageChanged { previous, current })
+	}
+
+	/// Returns the text message from the last processed telegram.
+	pub fn current(&self) -> Option<&TextMessage> {
+		self.last.as_ref()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parse_telegram;
+	use crate::telegram::{TextMessage, TextMessageChanged, TextMessageTracker};
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_text_message() {
+		let telegram = parse_telegram(&with_crc(DSMR5)).unwrap();
+		assert_eq!(Some("0123456789:;<=>?".repeat(5)), telegram.text_message().text);
+		assert_eq!(None, telegram.text_message().code);
+
+		let telegram = parse_telegram(DSMR3.as_bytes()).unwrap();
+		assert!(telegram.text_message().is_empty());
+
+		let telegram = DSMR3.replace("0-0:96.13.1()", "0-0:96.13.1(12345678)");
+		let telegram = parse_telegram(telegram.as_bytes()).unwrap();
+		assert_eq!(Some("\u{12}4Vx"), telegram.text_message().code.as_deref());
+
+		let telegram = DSMR3.replace("0-0:96.13.0()", "0-0:96.13.0(Hello)");
+		let telegram = parse_telegram(telegram.as_bytes()).unwrap();
+		assert_eq!(Some("Hello"), telegram.text_message().text.as_deref());
+	}
+
+	#[test]
+	fn test_text_message_tracker() {
+		let empty = parse_telegram(DSMR3.as_bytes()).unwrap();
+		let message = parse_telegram(&with_crc(DSMR5)).unwrap();
+		let mut tracker = TextMessageTracker::new();
+		assert!(tracker.update(&empty).is_none());
+		assert_eq!(Some(&TextMessage::default()), tracker.current());
+		assert_eq!(
+			Some(TextMessageChanged {
+				previous: TextMessage::default(),
+				current: message.text_message(),
+			}),
+			tracker.update(&message)
+		);
+		assert!(tracker.update(&message).is_none());
+		assert_eq!(
+			Some(TextMessageChanged {
+				previous: message.text_message(),
+				current: TextMessage::default(),
+			}),
+			tracker.update(&empty)
+		);
+	}
+}
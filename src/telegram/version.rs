@@ -0,0 +1,75 @@
+use super::{Obis, Telegram};
+
+/// Version information object of DSMR 4.0 and later
+pub const VERSION: Obis = Obis::new(1, 3, 0, 2, 8);
+/// Version information object of the Belgian e-MUCS standard
+pub const VERSION_EMUCS: Obis = Obis::new(0, 0, 96, 1, 4);
+
+/// Version of the DSMR standard that a telegram conforms to.
+///
+/// Detected from the version information object (1-3:0.2.8 or 0-0:96.1.4) with [Telegram::version()]. The typed accessors of
+/// [Telegram] use it to select the data objects that are specific to the DSMR version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DsmrVersion {
+	/// DSMR 2.2 or 3.0, telegrams of these versions contain neither the version information object nor the CRC
+	Legacy,
+	/// DSMR 4.x
+	V4,
+	/// DSMR 5.x
+	V5,
+	/// Belgian e-MUCS standard (based on DSMR 5.0.2)
+	EMucs,
+	/// Version information object is present, but the version is not recognized
+	Unknown,
+}
+
+impl DsmrVersion {
+	/// Detect the version of the DSMR standard from the version information object of `telegram`.
+	pub fn detect(telegram: &Telegram) -> Self {
+		if telegram.get(VERSION_EMUCS).is_some() {
+			return Self::EMucs;
+		}
+		match telegram.value(VERSION).map(|version| version.as_bytes()) {
+			None => Self::Legacy,
+			Some([b'4', _]) => Self::V4,
+			Some([b'5', _]) => Self::V5,
+			Some(_) => Self::Unknown,
+		}
+	}
+
+	/// Returns `true` if the telegrams of this version must include the CRC in the footer.
+	pub fn requires_crc(self) -> bool {
+		match self {
+			Self::Legacy => false,
+			Self::V4 | Self::V5 | Self::EMucs | Self::Unknown => true,
+		}
+	}
+}
+
+impl Telegram {
+	/// Returns the version of the DSMR standard that this telegram conforms to, see [DsmrVersion::detect()].
+	pub fn version(&self) -> DsmrVersion {
+		DsmrVersion::detect(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::DsmrVersion;
+	use crate::parse_telegram;
+	use crate::telegram::ParseError;
+	use crate::test_telegrams::{DSMR3, DSMR5, with_crc};
+
+	#[test]
+	fn test_version() {
+		assert_eq!(DsmrVersion::V5, parse_telegram(&with_crc(DSMR5)).unwrap().version());
+		let telegram = DSMR5.replace("1-3:0.2.8(50)", "1-3:0.2.8(42)");
+		assert_eq!(DsmrVersion::V4, parse_telegram(&with_crc(&telegram)).unwrap().version());
+		let telegram = DSMR5.replace("1-3:0.2.8(50)", "0-0:96.1.4(50217)");
+		assert_eq!(DsmrVersion::EMucs, parse_telegram(&with_crc(&telegram)).unwrap().version());
+		let telegram = DSMR5.replace("1-3:0.2.8(50)", "1-3:0.2.8(7)");
+		assert_eq!(DsmrVersion::Unknown, parse_telegram(&with_crc(&telegram)).unwrap().version());
+		assert_eq!(DsmrVersion::Legacy, parse_telegram(DSMR3.as_bytes()).unwrap().version());
+		assert_eq!(Err(ParseError::MissingCrc), parse_telegram(format!("{DSMR5}\r\n").as_bytes()));
+	}
+}
@@ -0,0 +1,71 @@
+//! Sample telegrams for the unit tests.
+
+use crate::reader::crc16;
+
+/// Example telegram from the DSMR 5.0.2 P1 companion standard
+pub const DSMR5: &str = "/ISk5\\2MT382-1000\r
+\r
+1-3:0.2.8(50)\r
+0-0:1.0.0(101209113020W)\r
+0-0:96.1.1(4B384547303034303436333935353037)\r
+1-0:1.8.1(123456.789*kWh)\r
+1-0:1.8.2(123456.789*kWh)\r
+1-0:2.8.1(123456.789*kWh)\r
+1-0:2.8.2(123456.789*kWh)\r
+0-0:96.14.0(0002)\r
+1-0:1.7.0(01.193*kW)\r
+1-0:2.7.0(00.000*kW)\r
+0-0:96.7.21(00004)\r
+0-0:96.7.9(00002)\r
+1-0:99.97.0(2)(0-0:96.7.19)(101208152415W)(0000000240*s)(101208151004W)(0000000301*s)\r
+1-0:32.32.0(00002)\r
+1-0:52.32.0(00001)\r
+1-0:72.32.0(00000)\r
+1-0:32.36.0(00000)\r
+1-0:52.36.0(00003)\r
+1-0:72.36.0(00000)\r
+0-0:96.13.0(303132333435363738393A3B3C3D3E3F303132333435363738393A3B3C3D3E3F303132333435363738393A3B3C3D3E3F303132333435363738393A3B3C3D3E3F303132333435363738393A3B3C3D3E3F)\r
+1-0:32.7.0(220.1*V)\r
+1-0:52.7.0(220.2*V)\r
+1-0:72.7.0(220.3*V)\r
+1-0:31.7.0(001*A)\r
+1-0:51.7.0(002*A)\r
+1-0:71.7.0(003*A)\r
+1-0:21.7.0(01.111*kW)\r
+1-0:41.7.0(02.222*kW)\r
+1-0:61.7.0(03.333*kW)\r
+1-0:22.7.0(04.444*kW)\r
+1-0:42.7.0(05.555*kW)\r
+1-0:62.7.0(06.666*kW)\r
+0-1:24.1.0(003)\r
+0-1:96.1.0(3232323241424344313233343536373839)\r
+0-1:24.2.1(101209112500W)(12785.123*m3)\r
+!";
+
+/// Example telegram of a DSMR 3.0 meter, there is no CRC and the gas reading is split over 2 lines
+pub const DSMR3: &str = "/ISk5\\2ME382-1003\r
+\r
+0-0:96.1.1(4B414C37303035313139303936333132)\r
+1-0:1.8.1(00123.456*kWh)\r
+1-0:1.8.2(00123.456*kWh)\r
+1-0:2.8.1(00000.000*kWh)\r
+1-0:2.8.2(00000.000*kWh)\r
+0-0:96.14.0(0002)\r
+1-0:1.7.0(0000.26*kW)\r
+1-0:2.7.0(0000.00*kW)\r
+0-0:17.0.0(0999.00*kW)\r
+0-0:96.3.10(1)\r
+0-0:96.13.1()\r
+0-0:96.13.0()\r
+0-1:24.1.0(3)\r
+0-1:96.1.0(3238303131303031323431323131343133)\r
+0-1:24.3.0(121030140000)(00)(60)(1)(0-1:24.2.1)(m3)\r
+(00001.234)\r
+0-1:24.4.0(1)\r
+!\r
+";
+
+/// Appends the correct CRC and the final CRLF to the telegram ending with "!"
+pub fn with_crc(telegram: &str) -> Vec<u8> {
+	format!("{telegram}{:04X}\r\n", crc16(telegram.as_bytes())).into_bytes()
+}
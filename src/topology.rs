@@ -0,0 +1,442 @@
+//! Relationships between several metering points of the same installation.
+//!
+//! An installation often has more than one meter: the grid meter, a sub-meter of the solar inverter, a meter of the EV charger on
+//! a second dongle. The quantity of interest, e.g. the consumption of the house, is then not measured by any single meter, but is
+//! a combination of them. [Topology] declares the meters and the derived points and calculates the derived values from the
+//! telegrams of all meters.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::telegram::Telegram;
+
+/// Set of metering points and the points derived from them.
+///
+/// Every meter reports the power from its telegrams, the value of a derived point is the weighted sum of the meter values. The
+/// meters send the telegrams at different rates and at different moments, so the meter values are linearly interpolated to the
+/// moments of the telegrams of all meters involved. A derived value is therefore calculated only when every involved meter has
+/// reported at or after its moment, the derived values are delayed by up to the telegram interval of the slowest meter. The
+/// values are not calculated for the time when one of the involved meters was not reporting.
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use homey_energy_dongle::parse_telegram;
+/// use homey_energy_dongle::topology::{Reading, Topology};
+///
+/// let telegram = |imported: f64, exported: f64| {
+///     let telegram = format!(
+///         "/test\r\n\r\n1-0:1.8.1(000001.000*kWh)\r\n1-0:1.8.2(000001.000*kWh)\r\n1-0:2.8.1(000001.000*kWh)\r\n\
+///         1-0:2.8.2(000001.000*kWh)\r\n1-0:1.7.0({imported:06.3}*kW)\r\n1-0:2.7.0({exported:06.3}*kW)\r\n!\r\n"
+///     );
+///     parse_telegram(telegram.as_bytes()).unwrap()
+/// };
+/// let mut topology = Topology::builder()
+///     .meter("grid", Reading::NetPower)
+///     .meter("solar", Reading::ExportedPower)
+///     .meter("ev", Reading::ImportedPower)
+///     .derived("house", [("grid", 1.), ("solar", 1.), ("ev", -1.)])
+///     .build()
+///     .unwrap();
+/// let start = Instant::now();
+/// assert!(topology.record_at("grid", &telegram(0., 1.), start).is_empty());
+/// assert!(topology.record_at("ev", &telegram(2., 0.), start).is_empty());
+/// let house = topology.record_at("solar", &telegram(0., 3.5), start);
+/// assert_eq!(0.5, house[0].power);
+/// ```
+pub struct Topology<C = SystemClock> {
+	clock: C,
+	max_age: Duration,
+	meters: Vec<Meter>,
+	derived: Vec<Derived>,
+}
+
+impl Topology {
+	/// Returns the builder to declare the metering points.
+	pub fn builder() -> TopologyBuilder {
+		TopologyBuilder {
+			max_age: Duration::from_secs(60),
+			meters: vec![],
+			derived: vec![],
+		}
+	}
+}
+
+impl<C: Clock> Topology<C> {
+	/// Records the telegram of the meter `meter` received at the current moment.
+	///
+	/// Returns the derived values that can be calculated with the new telegram in the chronological order. Telegrams of the
+	/// unknown meters and without the power values are ignored.
+	pub fn record(&mut self, meter: &str, telegram: &Telegram) -> Vec<DerivedValue> {
+		self.record_at(meter, telegram, self.clock.now())
+	}
+
+	/// Records the telegram of the meter `meter` received at the specified moment.
+	///
+	/// `at` is expected to be non-decreasing between the calls.
+	pub fn record_at(&mut self, meter: &str, telegram: &Telegram, at: Instant) -> Vec<DerivedValue> {
+		let Some(meter) = self.meters.iter_mut().find(|candidate| *candidate.name == *meter) else {
+			return vec![];
+		};
+		let Some(net) = telegram.net_metering() else {
+			return vec![];
+		};
+		meter.samples.push_back(Sample {
+			at,
+			power: meter.reading.power(net.power_imported, net.power_exported),
+		});
+
+		let mut out = vec![];
+		for derived in &mut self.derived {
+			// the values are only calculated within the time span covered by the samples of all involved meters
+			let Some(until) = derived
+				.terms
+				.iter()
+				.map(|(meter, _)| self.meters[*meter].samples.back().map(|sample| sample.at))
+				.min()
+				.flatten()
+			else {
+				continue;
+			};
+			let from = derived
+				.terms
+				.iter()
+				.filter_map(|(meter, _)| self.meters[*meter].samples.front().map(|sample| sample.at))
+				.max()
+				.unwrap_or(until);
+			let mut moments = derived
+				.terms
+				.iter()
+				.flat_map(|(meter, _)| &self.meters[*meter].samples)
+				.map(|sample| sample.at)
+				.filter(|moment| (from..=until).contains(moment) && derived.last.is_none_or(|last| *moment > last))
+				.collect::<Vec<_>>();
+			moments.sort_unstable();
+			moments.dedup();
+			for moment in moments {
+				let power = derived
+					.terms
+					.iter()
+					.map(|(meter, weight)| weight * self.meters[*meter].power_at(moment))
+					.sum();
+				out.push(DerivedValue {
+					point: Arc::clone(&derived.name),
+					at: moment,
+					power,
+				});
+				derived.last = Some(moment);
+			}
+		}
+		out.sort_by_key(|value| value.at);
+		self.evict(at);
+		out
+	}
+
+	/// Drops the samples that are older than `max_age` or no longer needed for the interpolation.
+	fn evict(&mut self, now: Instant) {
+		let oldest = now.checked_sub(self.max_age);
+		for (index, meter) in self.meters.iter_mut().enumerate() {
+			let needed_from = self
+				.derived
+				.iter()
+				.filter(|derived| derived.terms.iter().any(|(meter, _)| *meter == index))
+				.map(|derived| derived.last)
+				.min()
+				.flatten();
+			while meter
+				.samples
+				.front()
+				.is_some_and(|sample| oldest.is_some_and(|oldest| sample.at < oldest))
+				|| meter
+					.samples
+					.get(1)
+					.is_some_and(|next| needed_from.is_some_and(|needed_from| next.at <= needed_from))
+			{
+				meter.samples.pop_front();
+			}
+		}
+	}
+}
+
+impl<C: fmt::Debug> fmt::Debug for Topology<C> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Topology")
+			.field("clock", &self.clock)
+			.field("max_age", &self.max_age)
+			.field("meters", &self.meters.iter().map(|meter| &meter.name).collect::<Vec<_>>())
+			.field(
+				"derived",
+				&self.derived.iter().map(|derived| &derived.name).collect::<Vec<_>>(),
+			)
+			.finish()
+	}
+}
+
+/// Builder for [Topology], created by [Topology::builder()].
+#[derive(Debug, Clone)]
+pub struct TopologyBuilder {
+	max_age: Duration,
+	meters: Vec<(String, Reading)>,
+	derived: Vec<(String, Vec<(String, f64)>)>,
+}
+
+impl TopologyBuilder {
+	/// Declares the meter `name` that reports the power selected by `reading`.
+	///
+	/// With [DongleManager](crate::manager::DongleManager) the name of the dongle is a natural choice for the meter name.
+	pub fn meter(mut self, name: impl Into<String>, reading: Reading) -> Self {
+		self.meters.push((name.into(), reading));
+		self
+	}
+
+	/// Declares the point `name` which value is the sum of the values of the meters multiplied by their weights.
+	pub fn derived<M: Into<String>>(mut self, name: impl Into<String>, terms: impl IntoIterator<Item = (M, f64)>) -> Self {
+		let terms = terms.into_iter().map(|(meter, weight)| (meter.into(), weight)).collect();
+		self.derived.push((name.into(), terms));
+		self
+	}
+
+	/// Maximum age of a meter value used in the calculation, 1 minute by default.
+	///
+	/// When one of the meters stops reporting, the derived points that involve it are not calculated until it reports again.
+	pub fn max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = max_age;
+		self
+	}
+
+	/// Creates the [Topology] that uses [SystemClock].
+	pub fn build(self) -> Result<Topology, TopologyError> {
+		self.build_with_clock(SystemClock)
+	}
+
+	/// Creates the [Topology] that uses `clock` as a source of time, see [clock](crate::clock).
+	pub fn build_with_clock<C: Clock>(self, clock: C) -> Result<Topology<C>, TopologyError> {
+		let mut names = self
+			.meters
+			.iter()
+			.map(|(name, _)| name)
+			.chain(self.derived.iter().map(|(name, _)| name))
+			.collect::<Vec<_>>();
+		names.sort_unstable();
+		if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+			return Err(TopologyError::DuplicateName(pair[0].clone()));
+		}
+		let derived = self
+			.derived
+			.into_iter()
+			.map(|(name, terms)| {
+				let terms = terms
+					.into_iter()
+					.map(
+						|(meter, weight)| match self.meters.iter().position(|(name, _)| *name == meter) {
+							Some(index) => Ok((index, weight)),
+							None => Err(TopologyError::UnknownMeter {
+								derived: name.clone(),
+								meter,
+							}),
+						},
+					)
+					.collect::<Result<Vec<_>, _>>()?;
+				if terms.is_empty() {
+					return Err(TopologyError::EmptyDerived(name));
+				}
+				Ok(Derived {
+					name: name.into(),
+					terms,
+					last: None,
+				})
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Topology {
+			clock,
+			max_age: self.max_age,
+			meters: self
+				.meters
+				.into_iter()
+				.map(|(name, reading)| Meter {
+					name: name.into(),
+					reading,
+					samples: VecDeque::new(),
+				})
+				.collect(),
+			derived,
+		})
+	}
+}
+
+/// Power value that the meter contributes to the derived points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reading {
+	/// Power delivered to the client minus the power delivered by the client, e.g. for the grid meter
+	NetPower,
+	/// Power delivered to the client, e.g. for the meter of a consumer like the EV charger
+	ImportedPower,
+	/// Power delivered by the client, e.g. for the meter of the solar inverter
+	ExportedPower,
+}
+
+impl Reading {
+	fn power(self, imported: f64, exported: f64) -> f64 {
+		match self {
+			Self::NetPower => imported - exported,
+			Self::ImportedPower => imported,
+			Self::ExportedPower => exported,
+		}
+	}
+}
+
+/// Value of a derived point calculated by [Topology].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedValue {
+	/// Name of the derived point
+	pub point: Arc<str>,
+	/// Moment the value is calculated for, it's the moment of receipt of one of the involved telegrams
+	pub at: Instant,
+	/// Power in kW
+	pub power: f64,
+}
+
+/// Possible error scenarios for [TopologyBuilder::build()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TopologyError {
+	/// Several meters or derived points share the same name
+	DuplicateName(String),
+	/// Derived point refers to a meter that is not declared
+	UnknownMeter { derived: String, meter: String },
+	/// Derived point doesn't refer to any meter
+	EmptyDerived(String),
+}
+
+impl fmt::Display for TopologyError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::DuplicateName(name) => write!(f, "Name {name} is declared more than once"),
+			Self::UnknownMeter { derived, meter } => write!(f, "Derived point {derived} refers to unknown meter {meter}"),
+			Self::EmptyDerived(name) => write!(f, "Derived point {name} doesn't refer to any meter"),
+		}
+	}
+}
+
+impl Error for TopologyError {}
+
+#[derive(Debug)]
+struct Meter {
+	name: Arc<str>,
+	reading: Reading,
+	/// Samples in the order of receipt
+	samples: VecDeque<Sample>,
+}
+
+impl Meter {
+	/// Returns the power at `at` interpolated between the surrounding samples or the value of the nearest sample.
+	fn power_at(&self, at: Instant) -> f64 {
+		let next = self.samples.partition_point(|sample| sample.at < at);
+		match (
+			next.checked_sub(1).and_then(|prev| self.samples.get(prev)),
+			self.samples.get(next),
+		) {
+			(Some(prev), Some(next)) => {
+				let span = next.at.duration_since(prev.at).as_secs_f64();
+				let fraction = at.duration_since(prev.at).as_secs_f64() / span;
+				prev.power + (next.power - prev.power) * fraction
+			}
+			(None, Some(sample)) | (Some(sample), None) => sample.power,
+			(None, None) => 0.,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+	at: Instant,
+	power: f64,
+}
+
+#[derive(Debug)]
+struct Derived {
+	name: Arc<str>,
+	/// Indices of the meters and their weights
+	terms: Vec<(usize, f64)>,
+	/// Moment of the last calculated value
+	last: Option<Instant>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parse_telegram;
+
+	fn telegram(imported: f64, exported: f64) -> Telegram {
+		let telegram = format!(
+			"/test\r\n\r\n1-0:1.8.1(000001.000*kWh)\r\n1-0:1.8.2(000001.000*kWh)\r\n1-0:2.8.1(000001.000*kWh)\r\n\
+			1-0:2.8.2(000001.000*kWh)\r\n1-0:1.7.0({imported:06.3}*kW)\r\n1-0:2.7.0({exported:06.3}*kW)\r\n!\r\n"
+		);
+		parse_telegram(telegram.as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn test_topology() {
+		let mut topology = Topology::builder()
+			.meter("grid", Reading::NetPower)
+			.meter("solar", Reading::ExportedPower)
+			.derived("house", [("grid", 1.), ("solar", 1.)])
+			.max_age(Duration::from_secs(30))
+			.build()
+			.unwrap();
+		let start = Instant::now();
+		let at = |secs| start + Duration::from_secs(secs);
+		let round = |power: f64| (power * 1000.).round() / 1000.;
+		let values = |values: Vec<DerivedValue>| {
+			values
+				.into_iter()
+				.map(|value| (value.at.duration_since(start).as_secs(), round(value.power)))
+				.collect::<Vec<_>>()
+		};
+
+		// the grid meter reports every second, the solar meter every 10 seconds
+		assert!(topology.record_at("solar", &telegram(0., 2.), at(0)).is_empty());
+		assert_eq!(vec![(0, 3.)], values(topology.record_at("grid", &telegram(1., 0.), at(0))));
+		for secs in 1..=10 {
+			assert!(topology.record_at("grid", &telegram(0., 1.), at(secs)).is_empty());
+		}
+		let expected = (1..=10).map(|secs| (secs, round(1. + secs as f64 / 10.))).collect::<Vec<_>>();
+		assert_eq!(expected, values(topology.record_at("solar", &telegram(0., 3.), at(10))));
+		assert!(topology.record_at("unknown", &telegram(0., 3.), at(11)).is_empty());
+		// only the last sample is kept for the interpolation of the next values
+		assert_eq!(1, topology.meters[0].samples.len());
+
+		// the solar meter is gone for longer than the maximum age
+		for secs in 11..=45 {
+			assert!(topology.record_at("grid", &telegram(1., 0.), at(secs)).is_empty());
+		}
+		assert!(topology.meters[1].samples.is_empty());
+		assert!(topology.record_at("solar", &telegram(0., 4.), at(46)).is_empty());
+		assert_eq!(vec![(46, 5.)], values(topology.record_at("grid", &telegram(1., 0.), at(47))));
+	}
+
+	#[test]
+	fn test_topology_errors() {
+		let builder = Topology::builder().meter("grid", Reading::NetPower);
+		assert_eq!(
+			TopologyError::DuplicateName("grid".to_string()),
+			builder.clone().derived("grid", [("grid", 1.)]).build().unwrap_err()
+		);
+		assert_eq!(
+			TopologyError::UnknownMeter {
+				derived: "house".to_string(),
+				meter: "solar".to_string(),
+			},
+			builder.clone().derived("house", [("solar", 1.)]).build().unwrap_err()
+		);
+		assert_eq!(
+			TopologyError::EmptyDerived("house".to_string()),
+			builder.derived("house", Vec::<(String, f64)>::new()).build().unwrap_err()
+		);
+	}
+}
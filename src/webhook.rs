@@ -0,0 +1,320 @@
+use core::fmt;
+use core::pin::pin;
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::future::{Either, select};
+use futures_util::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use log::{trace, warn};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode};
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::reader::RawTelegram;
+
+/// Name of the header that carries the HMAC signature of the request body, see [WebhookSinkBuilder::hmac_secret()].
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Sink that POSTs the telegrams as JSON to an HTTP endpoint, the easiest way to integrate with serverless and cloud functions.
+///
+/// Every request carries a batch of telegrams in the following format:
+/// ```json
+/// {
+///   "telegrams": [
+///     {
+///       "received_at": 1700000000123,
+///       "crc": "valid",
+///       "contents": "/ISk5\\2MT382-1000\r\n..."
+///     }
+///   ]
+/// }
+/// ```
+/// `received_at` is the number of milliseconds since the UNIX epoch, `crc` is one of `valid`, `mismatch`, `missing` or
+/// `malformed`, see [CrcCheck::as_str()]. `contents` is the telegram string, the contents that are not valid ASCII are sent as
+/// an object with a single `base64` key instead, the same way as with the `serde` feature, see [RawTelegram].
+///
+/// [CrcCheck::as_str()]: crate::reader::CrcCheck::as_str
+///
+/// The failed requests are retried with exponential backoff, the client errors (HTTP 4xx except 408 and 429) are not retried.
+///
+/// The HTTPS endpoints are supported with the rustls TLS backend and the Mozilla root certificates, which the `webhook` feature
+/// enables in reqwest.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use futures_util::Stream;
+/// use homey_energy_dongle::reader::RawTelegram;
+/// use homey_energy_dongle::webhook::WebhookSink;
+///
+/// async fn example(telegrams: impl Stream<Item = RawTelegram>) {
+///     let webhook = WebhookSink::builder("https://example.com/telegrams")
+///         .bearer_token("secret-token")
+///         .hmac_secret("signing-secret")
+///         .batch_interval(Duration::from_secs(10))
+///         .build()
+///         .unwrap();
+///     webhook.forward(telegrams).await;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+	client: Client,
+	url: String,
+	bearer_token: Option<String>,
+	hmac_secret: Option<Vec<u8>>,
+	max_retries: u32,
+	retry_delay: Duration,
+	batch_interval: Option<Duration>,
+}
+
+impl WebhookSink {
+	/// Returns the builder to configure the sink that POSTs to `url`.
+	pub fn builder(url: impl Into<String>) -> WebhookSinkBuilder {
+		WebhookSinkBuilder {
+			url: url.into(),
+			bearer_token: None,
+			hmac_secret: None,
+			max_retries: 3,
+			retry_delay: Duration::from_secs(1),
+			batch_interval: None,
+			timeout: Some(Duration::from_secs(30)),
+		}
+	}
+
+	/// Sends a batch of telegrams received at the specified moments in a single request, retrying if necessary.
+	pub async fn send(&self, telegrams: &[(SystemTime, RawTelegram)]) -> Result<(), WebhookError> {
+		let body = payload(telegrams);
+		let mut retry_delay = self.retry_delay;
+		let mut attempt = 0;
+		loop {
+			let res = self.post(body.clone()).await;
+			match res {
+				Ok(()) => return Ok(()),
+				Err(err) if attempt < self.max_retries && err.is_retryable() => {
+					attempt += 1;
+					warn!("Webhook request failed, retrying in {retry_delay:?} (attempt {attempt}): {err}");
+					async_timer::new_timer(retry_delay).await;
+					retry_delay = retry_delay.saturating_mul(2);
+				}
+				Err(err) => return Err(err),
+			}
+		}
+	}
+
+	/// Sends all telegrams from `telegrams` until the stream finishes.
+	///
+	/// Without the batch interval every telegram is sent in a separate request. With the batch interval the telegrams received
+	/// within the interval after the first one are sent together. The batches that fail after all retries are logged and dropped.
+	pub async fn forward(&self, telegrams: impl Stream<Item = RawTelegram>) {
+		// the stream is polled again after it finished if it finishes during the batch interval
+		let mut telegrams = pin!(telegrams.fuse());
+		while let Some(first) = telegrams.next().await {
+			let mut batch = vec![(SystemTime::now(), first)];
+			if let Some(batch_interval) = self.batch_interval {
+				let mut timer = pin!(async_timer::new_timer(batch_interval));
+				while let Either::Left((Some(telegram), _)) = select(telegrams.next(), timer.as_mut()).await {
+					batch.push((SystemTime::now(), telegram));
+				}
+			}
+			if let Err(err) = self.send(&batch).await {
+				warn!("Dropping {} telegrams after the failed webhook request: {err}", batch.len());
+			}
+		}
+	}
+
+	async fn post(&self, body: Vec<u8>) -> Result<(), WebhookError> {
+		let mut request = self.client.post(&self.url).header(CONTENT_TYPE, "application/json");
+		if let Some(token) = &self.bearer_token {
+			request = request.bearer_auth(token);
+		}
+		if let Some(secret) = &self.hmac_secret {
+			request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+		}
+		trace!("Sending webhook request of {} bytes to {}", body.len(), self.url);
+		let status = request.body(body).send().await.map_err(WebhookError::Http)?.status();
+		if status.is_success() {
+			Ok(())
+		} else {
+			Err(WebhookError::Status(status))
+		}
+	}
+}
+
+/// Builder for [WebhookSink], created by [WebhookSink::builder()].
+#[derive(Debug, Clone)]
+pub struct WebhookSinkBuilder {
+	url: String,
+	bearer_token: Option<String>,
+	hmac_secret: Option<Vec<u8>>,
+	max_retries: u32,
+	retry_delay: Duration,
+	batch_interval: Option<Duration>,
+	timeout: Option<Duration>,
+}
+
+impl WebhookSinkBuilder {
+	/// Send the token in the `Authorization: Bearer` header.
+	pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+		self.bearer_token = Some(token.into());
+		self
+	}
+
+	/// Sign the request body with HMAC-SHA256 using `secret`.
+	///
+	/// The signature is sent in the [SIGNATURE_HEADER] header as `sha256=` followed by the lowercase hex digest.
+	pub fn hmac_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+		self.hmac_secret = Some(secret.into());
+		self
+	}
+
+	/// Maximum number of the retries of a failed request, 3 by default.
+	pub fn max_retries(mut self, max_retries: u32) -> Self {
+		self.max_retries = max_retries;
+		self
+	}
+
+	/// Delay before the first retry, it's doubled for every next retry, 1 second by default.
+	pub fn retry_delay(mut self, delay: Duration) -> Self {
+		self.retry_delay = delay;
+		self
+	}
+
+	/// Collect the telegrams for `interval` and send them in a single request, disabled by default.
+	pub fn batch_interval(mut self, interval: Duration) -> Self {
+		self.batch_interval = Some(interval);
+		self
+	}
+
+	/// Timeout of a single request, 30 seconds by default.
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	/// Creates the sink, fails if the HTTP client can't be initialized, e.g. when the TLS backend fails to load.
+	pub fn build(self) -> Result<WebhookSink, WebhookError> {
+		let mut client = Client::builder();
+		if let Some(timeout) = self.timeout {
+			client = client.timeout(timeout);
+		}
+		Ok(WebhookSink {
+			client: client.build().map_err(WebhookError::Http)?,
+			url: self.url,
+			bearer_token: self.bearer_token,
+			hmac_secret: self.hmac_secret,
+			max_retries: self.max_retries,
+			retry_delay: self.retry_delay,
+			batch_interval: self.batch_interval,
+		})
+	}
+}
+
+/// Possible error scenarios for [WebhookSink].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WebhookError {
+	/// HTTP client error, e.g., the endpoint is not reachable
+	Http(reqwest::Error),
+	/// Endpoint responded with a non-success status
+	Status(StatusCode),
+}
+
+impl WebhookError {
+	/// Whether the request can succeed if it's repeated.
+	pub fn is_retryable(&self) -> bool {
+		match self {
+			Self::Http(err) => !err.is_builder(),
+			Self::Status(status) => {
+				status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS || *status == StatusCode::REQUEST_TIMEOUT
+			}
+		}
+	}
+}
+
+impl fmt::Display for WebhookError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Http(err) => write!(f, "HTTP error: {err}, details: {err:?}"),
+			Self::Status(status) => write!(f, "Webhook endpoint responded with status {status}"),
+		}
+	}
+}
+
+impl std::error::Error for WebhookError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Http(err) => Some(err),
+			Self::Status(_) => None,
+		}
+	}
+}
+
+fn payload(telegrams: &[(SystemTime, RawTelegram)]) -> Vec<u8> {
+	let telegrams = telegrams
+		.iter()
+		.map(|(received_at, telegram)| {
+			let crc = telegram.check_crc().as_str();
+			json!({
+				"received_at": received_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+				"crc": crc,
+				"contents": telegram,
+			})
+		})
+		.collect::<Vec<_>>();
+	json!({ "telegrams": telegrams }).to_string().into_bytes()
+}
+
+fn signature(secret: &[u8], body: &[u8]) -> String {
+	let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+	mac.update(body);
+	let mut out = String::from("sha256=");
+	for byte in mac.finalize().into_bytes() {
+		out.push_str(&format!("{byte:02x}"));
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_payload() {
+		let telegram = RawTelegram {
+			contents: b"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n".to_vec(),
+		};
+		let received_at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+		assert_eq!(
+			r#"{"telegrams":[{"contents":"/test\r\n\r\n1-0:1.8.1(000123.456*kWh)\r\n!B5BD\r\n","crc":"valid","received_at":1700000000123}]}"#,
+			String::from_utf8(payload(&[(received_at, telegram)])).unwrap()
+		);
+
+		let telegram = RawTelegram {
+			contents: b"/test\xFF\r\n\r\n!\r\n".to_vec(),
+		};
+		assert_eq!(
+			r#"{"telegrams":[{"contents":{"base64":"L3Rlc3T/DQoNCiENCg=="},"crc":"missing","received_at":1700000000123}]}"#,
+			String::from_utf8(payload(&[(received_at, telegram)])).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_signature() {
+		// RFC 4231, test case 2
+		assert_eq!(
+			"sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+			signature(b"Jefe", b"what do ya want for nothing?")
+		);
+	}
+
+	#[test]
+	fn test_retryable() {
+		assert!(WebhookError::Status(StatusCode::INTERNAL_SERVER_ERROR).is_retryable());
+		assert!(WebhookError::Status(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+		assert!(!WebhookError::Status(StatusCode::UNAUTHORIZED).is_retryable());
+	}
+}
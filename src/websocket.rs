@@ -1,22 +1,51 @@
 use core::fmt;
-use core::future::ready;
+use core::future::Future;
 use core::net::SocketAddr;
-use core::pin::Pin;
-use core::task::{Context, Poll, ready};
+use core::pin::{Pin, pin};
+use core::task::{Context, Poll, Waker, ready};
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime};
 
-use futures_util::{SinkExt, Stream, StreamExt};
-use log::{error, trace, warn};
+use async_timer::Timed;
+use futures_util::stream::FuturesUnordered;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use log::trace;
 use reqwest::Client;
-use reqwest_websocket::{CloseCode, Message, RequestBuilderExt, WebSocket};
+pub use reqwest_websocket::{CloseCode, Message};
+use reqwest_websocket::{RequestBuilderExt, WebSocket, WebSocketConfig};
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc;
+#[cfg(feature = "tokio")]
+use tokio::task::JoinHandle;
 
 use crate::Bytes;
+use crate::reader::{RawTelegram, RawTelegramReader};
 
 /// Wrapper for the WebSocket connection to a Homey Energy Dongle.
 ///
 /// This struct implements [Stream] over [Bytes] buffers received from the dongle. To create a new connection, call
-/// [WebsocketEnergyDongle::connect()] with the dongle host details.
+/// [WebsocketEnergyDongle::connect()] with the dongle host details or use [WebsocketEnergyDongle::builder()] to configure the
+/// connection.
+///
+/// It also implements [Sink] over [Message] to send messages to the dongle. The current firmware doesn't process any incoming
+/// messages, so this is only useful for experiments with the future firmware features.
 pub struct WebsocketEnergyDongle {
 	websocket: WebSocket,
+	/// Data message received during the liveness probe, it's yielded first by the stream
+	pending: Option<Received>,
+	/// Payload of the last ping from the dongle that is not yet answered
+	pong: Option<Bytes>,
+	pong_unflushed: bool,
+	/// Time of the last pong received from the dongle
+	last_pong: Option<Instant>,
+	peer_addr: SocketAddr,
+	url: String,
+	connected_at: SystemTime,
+	max_message_size: Option<usize>,
+	read_timeout: Option<Duration>,
+	read_timer: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
 }
 
 impl WebsocketEnergyDongle {
@@ -27,74 +56,822 @@ impl WebsocketEnergyDongle {
 	/// The Homey Energy Dongle supports a maximum of 2 concurrent connections. When a 3rd connection is attempted, this function will
 	/// return `Err(Error::DongleError(DongleError::ConnectionLimitReached))`.
 	///
+	/// This call doesn't time out, use [WebsocketEnergyDongle::builder()] to specify the timeouts.
+	///
 	/// See the [crate-level documentation](crate) for more details and examples.
 	pub async fn connect(addr: SocketAddr, path: &str) -> Result<Self, ConnectError> {
-		let path = path.strip_prefix('/').unwrap_or(path);
+		Self::builder(addr, path).connect().await
+	}
+
+	/// Returns the builder to configure the connection to a Homey Energy Dongle.
+	///
+	/// # Example
+	/// ```no_run
+	/// use std::time::Duration;
+	///
+	/// use homey_energy_dongle::websocket::WebsocketEnergyDongle;
+	///
+	/// async fn example() {
+	///     let dongle = WebsocketEnergyDongle::builder("192.168.1.10:80".parse().unwrap(), "/ws")
+	///         .connect_timeout(Duration::from_secs(5))
+	///         .handshake_timeout(Duration::from_secs(5))
+	///         .read_timeout(Duration::from_secs(30))
+	///         .connect()
+	///         .await
+	///         .unwrap();
+	/// }
+	/// ```
+	pub fn builder(addr: SocketAddr, path: &str) -> WebsocketEnergyDongleBuilder {
+		Self::builder_with_addresses([addr], path)
+	}
+
+	/// Returns the builder to connect to a Homey Energy Dongle reachable at multiple addresses.
+	///
+	/// When the dongle advertises both IPv6 and IPv4 addresses, the connection attempts are raced in the "happy eyeballs" fashion
+	/// (RFC 8305): the addresses are ordered alternating between the address families, starting with IPv6, and each next attempt is
+	/// started after a short delay without waiting for the previous one to fail. The first successful connection wins, the others
+	/// are canceled. See [WebsocketEnergyDongleBuilder::happy_eyeballs_delay()].
+	///
+	/// The addresses of a discovered dongle are returned by [crate::discover::EnergyDongleHostInfo::socket_addresses()].
+	///
+	/// # Example
+	/// ```no_run
+	/// use homey_energy_dongle::websocket::WebsocketEnergyDongle;
+	///
+	/// async fn example() {
+	///     let addrs = ["192.168.1.10:80".parse().unwrap(), "[fe80::1]:80".parse().unwrap()];
+	///     let dongle = WebsocketEnergyDongle::builder_with_addresses(addrs, "/ws")
+	///         .connect()
+	///         .await
+	///         .unwrap();
+	/// }
+	/// ```
+	pub fn builder_with_addresses(addrs: impl IntoIterator<Item = SocketAddr>, path: &str) -> WebsocketEnergyDongleBuilder {
+		WebsocketEnergyDongleBuilder {
+			addrs: interleave_address_families(addrs),
+			path: path.to_string(),
+			happy_eyeballs_delay: DEFAULT_HAPPY_EYEBALLS_DELAY,
+			connect_timeout: None,
+			handshake_timeout: None,
+			read_timeout: None,
+			max_message_size: None,
+			liveness_probe: LivenessProbe::Pong,
+			probe_attempts: 1,
+		}
+	}
+
+	/// Splits the connection into separate streams of the binary and the text messages.
+	///
+	/// The [Stream] implementation of [WebsocketEnergyDongle] yields the contents of both binary and text messages as [Bytes], so a
+	/// status message sent by the firmware in a text frame would end up in the middle of the telegram data. After the split, the
+	/// telegram data (binary messages) goes to [TelegramDataStream] together with all the errors and the text messages go to
+	/// [TextMessageStream].
+	///
+	/// Both streams share the connection, so the messages for one stream are buffered until it's polled. If you're not interested
+	/// in one of the streams, drop it, and its messages will be discarded.
+	pub fn demux(self) -> (TelegramDataStream, TextMessageStream) {
+		let demux = Arc::new(Mutex::new(Demux {
+			dongle: self,
+			data: VecDeque::new(),
+			text: VecDeque::new(),
+			data_waker: None,
+			text_waker: None,
+			data_dropped: false,
+			text_dropped: false,
+			terminated: false,
+		}));
+		(
+			TelegramDataStream {
+				demux: Arc::clone(&demux),
+			},
+			TextMessageStream { demux },
+		)
+	}
+
+	/// Connects to a Homey Energy Dongle and calls `on_telegram` for every received telegram and `on_error` for every error.
+	///
+	/// The returned future completes when the connection is closed, see [WebsocketEnergyDongle::dispatch()]. This is a
+	/// convenient entry point when the stream combinators don't fit, e.g. in GUI applications or FFI bindings.
+	///
+	/// # Example
+	/// ```no_run
+	/// use homey_energy_dongle::websocket::WebsocketEnergyDongle;
+	///
+	/// async fn example() {
+	///     WebsocketEnergyDongle::subscribe(
+	///         "192.168.1.10:80".parse().unwrap(),
+	///         "/ws",
+	///         |telegram| println!("{} bytes received", telegram.contents.len()),
+	///         |err| eprintln!("{err}"),
+	///     )
+	///     .await
+	///     .unwrap();
+	/// }
+	/// ```
+	pub async fn subscribe(
+		addr: SocketAddr,
+		path: &str,
+		on_telegram: impl FnMut(RawTelegram),
+		on_error: impl FnMut(StreamError),
+	) -> Result<(), ConnectError> {
+		Self::connect(addr, path).await?.dispatch(on_telegram, on_error).await;
+		Ok(())
+	}
+
+	/// Reads the telegrams from this connection and calls `on_telegram` for every one of them and `on_error` for every error.
+	///
+	/// The returned future completes when the connection is closed.
+	pub async fn dispatch(mut self, mut on_telegram: impl FnMut(RawTelegram), mut on_error: impl FnMut(StreamError)) {
+		let mut reader = RawTelegramReader::new();
+		while let Some(res) = self.next().await {
+			match res {
+				Ok(bytes) => reader.feed(&bytes).into_iter().for_each(&mut on_telegram),
+				Err(err) => on_error(err),
+			}
+		}
+	}
+
+	/// Connects to a Homey Energy Dongle and reads the telegrams in a background task, see [WebsocketEnergyDongle::spawn_reader()].
+	///
+	/// Must be called within the context of a Tokio runtime.
+	#[cfg(feature = "tokio")]
+	pub async fn spawn(
+		addr: SocketAddr,
+		path: &str,
+	) -> Result<(JoinHandle<()>, mpsc::Receiver<Result<RawTelegram, StreamError>>), ConnectError> {
+		Ok(Self::connect(addr, path).await?.spawn_reader())
+	}
+
+	/// Moves the connection into a background task that extracts the telegrams and sends them to the returned channel.
+	///
+	/// The errors of the connection are sent to the channel too. The task finishes when the connection is closed or when the
+	/// receiver is dropped, you can also abort it using the returned [JoinHandle].
+	///
+	/// Must be called within the context of a Tokio runtime.
+	///
+	/// # Example
+	/// ```no_run
+	/// use homey_energy_dongle::websocket::WebsocketEnergyDongle;
+	///
+	/// async fn example() {
+	///     let (_task, mut telegrams) = WebsocketEnergyDongle::spawn("192.168.1.10:80".parse().unwrap(), "/ws")
+	///         .await
+	///         .unwrap();
+	///     while let Some(telegram) = telegrams.recv().await {
+	///         dbg!(telegram);
+	///     }
+	/// }
+	/// ```
+	#[cfg(feature = "tokio")]
+	pub fn spawn_reader(mut self) -> (JoinHandle<()>, mpsc::Receiver<Result<RawTelegram, StreamError>>) {
+		let (sender, receiver) = mpsc::channel(SPAWN_CHANNEL_CAPACITY);
+		let task = tokio::spawn(async move {
+			let mut reader = RawTelegramReader::new();
+			while let Some(res) = self.next().await {
+				let items = match res {
+					Ok(bytes) => reader.feed(&bytes).into_iter().map(Ok).collect(),
+					Err(err) => vec![Err(err)],
+				};
+				for item in items {
+					if sender.send(item).await.is_err() {
+						trace!("Telegram receiver is dropped, stopping the reader");
+						return;
+					}
+				}
+			}
+		});
+		(task, receiver)
+	}
+
+	/// Returns the address of the dongle that the connection is established to.
+	///
+	/// When the connection was created with [WebsocketEnergyDongle::builder_with_addresses()], this is the address that won the
+	/// race.
+	pub fn peer_addr(&self) -> SocketAddr {
+		self.peer_addr
+	}
+
+	/// Returns the WebSocket URL of the connection, e.g. "ws://192.168.1.10:80/ws".
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	/// Returns the URL path of the connection, e.g. "/ws".
+	pub fn path(&self) -> &str {
+		let after_scheme = self.url.strip_prefix("ws://").unwrap_or(&self.url);
+		after_scheme.find('/').map_or("/", |path_start| &after_scheme[path_start..])
+	}
+
+	/// Returns the WebSocket subprotocol negotiated during the handshake, the dongle doesn't currently negotiate any.
+	pub fn protocol(&self) -> Option<&str> {
+		self.websocket.protocol()
+	}
+
+	/// Returns the time when the connection was established, i.e. when the liveness probe succeeded.
+	pub fn connected_at(&self) -> SystemTime {
+		self.connected_at
+	}
+
+	/// Returns the time of the last pong received from the dongle after the connection is established.
+	///
+	/// Use it together with sending [Message::Ping] through the [Sink] implementation to check that the connection is alive.
+	/// The pong itself is consumed internally and is not yielded by the stream.
+	pub fn last_pong(&self) -> Option<Instant> {
+		self.last_pong
+	}
+
+	fn restart_read_timer(&mut self) {
+		self.read_timer = self
+			.read_timeout
+			.map(|read_timeout| Box::pin(async_timer::new_timer(read_timeout)) as Pin<Box<_>>);
+	}
+
+	/// Polls for the next data message, the control messages are handled internally.
+	fn poll_received(&mut self, cx: &mut Context) -> Poll<Option<Result<Received, StreamError>>> {
+		if let Some(pending) = self.pending.take() {
+			return Poll::Ready(Some(Ok(pending)));
+		}
+		loop {
+			// the pong is sent in the background, pending state only means that the sink will wake us up when it's ready
+			if let Poll::Ready(Err(err)) = self.poll_send_pong(cx) {
+				return Poll::Ready(Some(Err(StreamError::WebSocket(err))));
+			}
+			let msg_res = match Pin::new(&mut self.websocket).poll_next(cx) {
+				Poll::Ready(Some(msg_res)) => msg_res,
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Pending => return self.poll_read_timeout(cx),
+			};
+			self.restart_read_timer();
+			let msg = match msg_res {
+				Ok(msg) => msg,
+				Err(err) => return Poll::Ready(Some(Err(StreamError::WebSocket(err)))),
+			};
+			match msg {
+				Message::Text(txt) => return Poll::Ready(Some(self.check_message_size(Received::Text(txt)))),
+				Message::Binary(bin) => return Poll::Ready(Some(self.check_message_size(Received::Binary(bin)))),
+				Message::Ping(payload) => {
+					trace!("Replying to ping with payload: {payload:?}");
+					// only the most recent ping needs to be answered
+					self.pong = Some(payload);
+				}
+				Message::Pong(payload) => {
+					trace!("Received pong with payload: {payload:?}");
+					self.last_pong = Some(Instant::now());
+				}
+				Message::Close { code, reason } => {
+					return Poll::Ready(Some(Err(StreamError::DongleError(DongleError::from_code_and_reason(
+						code, reason,
+					)))));
+				}
+			}
+		}
+	}
+
+	fn check_message_size(&self, message: Received) -> Result<Received, StreamError> {
+		let size = match &message {
+			Received::Binary(bin) => bin.len(),
+			Received::Text(txt) => txt.len(),
+		};
+		match self.max_message_size {
+			Some(limit) if size > limit => Err(StreamError::MessageTooBig { size, limit }),
+			_ => Ok(message),
+		}
+	}
+
+	/// Sends the queued reply to the dongle ping.
+	fn poll_send_pong(&mut self, cx: &mut Context) -> Poll<Result<(), reqwest_websocket::Error>> {
+		if let Some(payload) = self.pong.take() {
+			match Pin::new(&mut self.websocket).poll_ready(cx) {
+				Poll::Ready(Ok(())) => {
+					Pin::new(&mut self.websocket).start_send(Message::Pong(payload))?;
+					self.pong_unflushed = true;
+				}
+				Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+				Poll::Pending => {
+					self.pong = Some(payload);
+					return Poll::Pending;
+				}
+			}
+		}
+		if self.pong_unflushed {
+			ready!(Pin::new(&mut self.websocket).poll_flush(cx))?;
+			self.pong_unflushed = false;
+		}
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_read_timeout<T>(&mut self, cx: &mut Context) -> Poll<Option<Result<T, StreamError>>> {
+		let (Some(read_timer), Some(read_timeout)) = (&mut self.read_timer, self.read_timeout) else {
+			return Poll::Pending;
+		};
+		ready!(read_timer.as_mut().poll(cx));
+		self.restart_read_timer();
+		Poll::Ready(Some(Err(StreamError::ReadTimeout(read_timeout))))
+	}
+}
+
+/// Builder for the connection to a Homey Energy Dongle, created by [WebsocketEnergyDongle::builder()].
+///
+/// All timeouts are disabled by default.
+#[derive(Debug, Clone)]
+pub struct WebsocketEnergyDongleBuilder {
+	addrs: Vec<SocketAddr>,
+	path: String,
+	happy_eyeballs_delay: Duration,
+	connect_timeout: Option<Duration>,
+	handshake_timeout: Option<Duration>,
+	read_timeout: Option<Duration>,
+	max_message_size: Option<usize>,
+	liveness_probe: LivenessProbe,
+	probe_attempts: u32,
+}
+
+impl WebsocketEnergyDongleBuilder {
+	#[cfg(feature = "otel")]
+	pub(crate) fn addrs(&self) -> &[SocketAddr] {
+		&self.addrs
+	}
+
+	#[cfg(feature = "otel")]
+	pub(crate) fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// Maximum time to establish the connection and perform the HTTP upgrade to WebSocket.
+	///
+	/// When exceeded, [WebsocketEnergyDongleBuilder::connect()] returns [ConnectError::ConnectTimeout].
+	pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+		self.connect_timeout = Some(timeout);
+		self
+	}
+
+	/// Maximum time to wait for the dongle to respond to the initial ping.
+	///
+	/// The timeout applies to every probe attempt, see [WebsocketEnergyDongleBuilder::probe_attempts()]. When exceeded for all
+	/// of them, [WebsocketEnergyDongleBuilder::connect()] returns [ConnectError::HandshakeTimeout].
+	pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+		self.handshake_timeout = Some(timeout);
+		self
+	}
+
+	/// Maximum size of a single message received from the dongle in bytes.
+	///
+	/// The limit is passed to the WebSocket protocol implementation as the maximum message and frame size, so an oversized
+	/// message is rejected while it's being received, before it's buffered in full. The [Stream] implementation of
+	/// [WebsocketEnergyDongle] then yields [StreamError::WebSocket] with the capacity error and the connection should be
+	/// reestablished. As a fallback, the size of every received message is checked again and an oversized one is replaced with
+	/// [StreamError::MessageTooBig].
+	///
+	/// The dongle normally sends the telegrams in chunks well below 4 KB. To limit the memory used for assembling the telegrams,
+	/// see [crate::reader::RawTelegramReader::with_max_buffered_len()].
+	pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+		self.max_message_size = Some(max_message_size);
+		self
+	}
+
+	/// How to check that the dongle is alive after the connection is established, [LivenessProbe::Pong] by default.
+	pub fn liveness_probe(mut self, probe: LivenessProbe) -> Self {
+		self.liveness_probe = probe;
+		self
+	}
+
+	/// Number of pings sent during the liveness probe before giving up, 1 by default.
+	///
+	/// The next ping is only sent when the previous one times out, so this setting only makes sense together with
+	/// [WebsocketEnergyDongleBuilder::handshake_timeout()].
+	pub fn probe_attempts(mut self, attempts: u32) -> Self {
+		self.probe_attempts = attempts.max(1);
+		self
+	}
+
+	/// Delay between starting the connection attempts to the different addresses of the dongle, 250 ms by default.
+	///
+	/// Only used for the builders created with [WebsocketEnergyDongle::builder_with_addresses()].
+	pub fn happy_eyeballs_delay(mut self, delay: Duration) -> Self {
+		self.happy_eyeballs_delay = delay;
+		self
+	}
+
+	/// Maximum time between the messages received from the dongle.
+	///
+	/// When exceeded, the [Stream] implementation of [WebsocketEnergyDongle] yields [StreamError::ReadTimeout]. The stream is not
+	/// terminated, so you can decide whether to keep waiting or to reconnect. The dongle normally sends a telegram every second.
+	pub fn read_timeout(mut self, timeout: Duration) -> Self {
+		self.read_timeout = Some(timeout);
+		self
+	}
+
+	/// Create a new WebSocket connection to a Homey Energy Dongle with the configured settings.
+	///
+	/// See [WebsocketEnergyDongle::connect()] for details.
+	pub async fn connect(self) -> Result<WebsocketEnergyDongle, ConnectError> {
+		match self.addrs.as_slice() {
+			[] => Err(ConnectError::NoAddresses),
+			&[addr] => self.connect_addr(addr).await,
+			addrs => {
+				let this = &self;
+				let mut attempts = addrs
+					.iter()
+					.enumerate()
+					.map(|(i, &addr)| async move {
+						if i > 0 {
+							async_timer::new_timer(this.happy_eyeballs_delay * i as u32).await;
+						}
+						(addr, this.connect_addr(addr).await)
+					})
+					.collect::<FuturesUnordered<_>>();
+				let mut last_err = ConnectError::NoAddresses;
+				while let Some((addr, res)) = attempts.next().await {
+					match res {
+						Ok(dongle) => return Ok(dongle),
+						Err(err) => {
+							trace!("Connection to Homey Energy Dongle at {addr} failed: {err}");
+							last_err = err;
+						}
+					}
+				}
+				Err(last_err)
+			}
+		}
+	}
+
+	async fn connect_addr(&self, addr: SocketAddr) -> Result<WebsocketEnergyDongle, ConnectError> {
+		let path = self.path.strip_prefix('/').unwrap_or(&self.path);
 		let url = format!("ws://{addr}/{path}");
 		trace!("Connecting to Homey Energy Dongle at {url}...");
-		let res = Client::new().get(url).upgrade().send().await?;
-		res.error_for_status_ref()?;
-		let mut websocket = res.into_websocket().await?;
-		websocket.send(Message::Ping(Bytes::new())).await?;
-		let mut next_pong_or_close = (&mut websocket).filter(|msg| {
-			ready(match msg {
-				Err(_) | Ok(Message::Pong(_) | Message::Close { .. }) => true,
-				Ok(Message::Text(_) | Message::Binary(_) | Message::Ping(_)) => false,
-			})
-		});
-		let Some(pong) = next_pong_or_close.next().await else {
-			return Err(ConnectError::DongleIsNotResponding);
+		let mut websocket = with_timeout(self.connect_timeout, upgrade(url.clone(), self.max_message_size))
+			.await
+			.ok_or(ConnectError::ConnectTimeout)??;
+		let pending = self.probe(&mut websocket).await?;
+		let mut out = WebsocketEnergyDongle {
+			websocket,
+			pending,
+			pong: None,
+			pong_unflushed: false,
+			last_pong: None,
+			peer_addr: addr,
+			url,
+			connected_at: SystemTime::now(),
+			max_message_size: self.max_message_size,
+			read_timeout: self.read_timeout,
+			read_timer: None,
 		};
-		match pong? {
-			Message::Pong(_) => { /* aok */ }
-			Message::Text(_) | Message::Binary(_) | Message::Ping(_) => {
-				error!("Unexpected message received, should've been filtered")
+		out.restart_read_timer();
+		Ok(out)
+	}
+
+	async fn probe(&self, websocket: &mut WebSocket) -> Result<Option<Received>, ConnectError> {
+		if self.liveness_probe == LivenessProbe::Disabled {
+			return Ok(None);
+		}
+		for attempt in 1..=self.probe_attempts {
+			websocket.send(Message::Ping(Bytes::new())).await?;
+			match with_timeout(self.handshake_timeout, wait_for_pong(websocket, self.liveness_probe)).await {
+				Some(res) => return res,
+				None => trace!("No response to ping, attempt {attempt} of {}", self.probe_attempts),
+			}
+		}
+		Err(ConnectError::HandshakeTimeout)
+	}
+}
+
+/// Liveness probe performed by [WebsocketEnergyDongleBuilder::connect()].
+///
+/// The probe sends a ping to the dongle and waits for the response. Some proxies and older firmware versions reply slowly or not
+/// at all, for those cases the probe can be relaxed or disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LivenessProbe {
+	/// Don't probe, the connection is considered established right after the HTTP upgrade
+	Disabled,
+	/// Wait for the pong, the data messages received in the meantime are discarded
+	#[default]
+	Pong,
+	/// Wait for the pong or the first data message, whichever comes first, the data message is yielded by the stream
+	AnyMessage,
+}
+
+impl Sink<Message> for WebsocketEnergyDongle {
+	type Error = StreamError;
+
+	fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		// the queued pong must go out first, otherwise it could be delayed indefinitely by the user messages
+		ready!(self.poll_send_pong(cx)).map_err(StreamError::WebSocket)?;
+		Pin::new(&mut self.websocket).poll_ready(cx).map_err(StreamError::WebSocket)
+	}
+
+	fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+		Pin::new(&mut self.websocket).start_send(item).map_err(StreamError::WebSocket)
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		ready!(Pin::new(&mut self.websocket).poll_flush(cx)).map_err(StreamError::WebSocket)?;
+		self.pong_unflushed = false;
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		Pin::new(&mut self.websocket).poll_close(cx).map_err(StreamError::WebSocket)
+	}
+}
+
+/// Data message received from the dongle.
+enum Received {
+	Binary(Bytes),
+	Text(String),
+}
+
+/// Stream of the binary messages from the dongle created by [WebsocketEnergyDongle::demux()].
+///
+/// Yields the telegram data and all connection errors.
+pub struct TelegramDataStream {
+	demux: Arc<Mutex<Demux>>,
+}
+
+impl Stream for TelegramDataStream {
+	type Item = Result<Bytes, StreamError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		self.demux.lock().unwrap_or_else(PoisonError::into_inner).poll_data(cx)
+	}
+}
+
+impl Drop for TelegramDataStream {
+	fn drop(&mut self) {
+		self.demux.lock().unwrap_or_else(PoisonError::into_inner).drop_data();
+	}
+}
+
+/// Stream of the text messages from the dongle created by [WebsocketEnergyDongle::demux()].
+///
+/// The text messages are yielded as is, the stream ends when the connection is closed.
+pub struct TextMessageStream {
+	demux: Arc<Mutex<Demux>>,
+}
+
+impl Stream for TextMessageStream {
+	type Item = String;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		self.demux.lock().unwrap_or_else(PoisonError::into_inner).poll_text(cx)
+	}
+}
+
+impl Drop for TextMessageStream {
+	fn drop(&mut self) {
+		self.demux.lock().unwrap_or_else(PoisonError::into_inner).drop_text();
+	}
+}
+
+/// Source of the data messages for [Demux].
+trait ReceivedSource {
+	fn poll_received(&mut self, cx: &mut Context) -> Poll<Option<Result<Received, StreamError>>>;
+}
+
+impl ReceivedSource for WebsocketEnergyDongle {
+	fn poll_received(&mut self, cx: &mut Context) -> Poll<Option<Result<Received, StreamError>>> {
+		WebsocketEnergyDongle::poll_received(self, cx)
+	}
+}
+
+/// Connection shared by [TelegramDataStream] and [TextMessageStream], whichever is polled reads from the connection and routes
+/// the messages to the queue of the respective stream.
+///
+/// The connection only keeps the waker of the stream that polled it last, so the other stream is woken up whenever it might
+/// need to take over the reading, i.e. when a message for it is routed or when the polling stream is dropped.
+struct Demux<S = WebsocketEnergyDongle> {
+	dongle: S,
+	data: VecDeque<Result<Bytes, StreamError>>,
+	text: VecDeque<String>,
+	data_waker: Option<Waker>,
+	text_waker: Option<Waker>,
+	data_dropped: bool,
+	text_dropped: bool,
+	terminated: bool,
+}
+
+impl<S: ReceivedSource> Demux<S> {
+	fn poll_data(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, StreamError>>> {
+		loop {
+			if let Some(item) = self.data.pop_front() {
+				return Poll::Ready(Some(item));
+			}
+			if self.terminated {
+				return Poll::Ready(None);
+			}
+			self.data_waker = Some(cx.waker().clone());
+			ready!(self.poll_route(cx));
+		}
+	}
+
+	fn poll_text(&mut self, cx: &mut Context) -> Poll<Option<String>> {
+		loop {
+			if let Some(txt) = self.text.pop_front() {
+				return Poll::Ready(Some(txt));
+			}
+			if self.terminated {
+				return Poll::Ready(None);
 			}
+			self.text_waker = Some(cx.waker().clone());
+			ready!(self.poll_route(cx));
+		}
+	}
+
+	/// Reads the next message from the connection and puts it into the respective queue.
+	fn poll_route(&mut self, cx: &mut Context) -> Poll<()> {
+		match ready!(self.dongle.poll_received(cx)) {
+			Some(Ok(Received::Binary(bin))) => self.push_data(Ok(bin)),
+			Some(Ok(Received::Text(txt))) => {
+				if !self.text_dropped {
+					self.text.push_back(txt);
+					wake(&mut self.text_waker);
+				}
+			}
+			Some(Err(err)) => self.push_data(Err(err)),
+			None => {
+				self.terminated = true;
+				wake(&mut self.data_waker);
+				wake(&mut self.text_waker);
+			}
+		}
+		Poll::Ready(())
+	}
+
+	fn drop_data(&mut self) {
+		self.data_dropped = true;
+		self.data.clear();
+		self.data_waker = None;
+		// the connection could have the waker of the dropped stream registered, the text stream must poll it again
+		wake(&mut self.text_waker);
+	}
+
+	fn drop_text(&mut self) {
+		self.text_dropped = true;
+		self.text.clear();
+		self.text_waker = None;
+		wake(&mut self.data_waker);
+	}
+
+	fn push_data(&mut self, item: Result<Bytes, StreamError>) {
+		if self.data_dropped {
+			if let Err(err) = item {
+				trace!("Discarding the error, the telegram data stream is dropped: {err}");
+			}
+		} else {
+			self.data.push_back(item);
+			wake(&mut self.data_waker);
+		}
+	}
+}
+
+fn wake(waker: &mut Option<Waker>) {
+	if let Some(waker) = waker.take() {
+		waker.wake();
+	}
+}
+
+/// Number of telegrams buffered by [WebsocketEnergyDongle::spawn_reader()] before the reading is paused.
+#[cfg(feature = "tokio")]
+const SPAWN_CHANNEL_CAPACITY: usize = 16;
+
+/// Default delay between the connection attempts recommended by RFC 8305.
+const DEFAULT_HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+/// Orders the addresses alternating between IPv6 and IPv4, starting with IPv6 and keeping the relative order within the family.
+fn interleave_address_families(addrs: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+	let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+	let mut out = Vec::with_capacity(v6.len() + v4.len());
+	let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+	loop {
+		match (v6.next(), v4.next()) {
+			(None, None) => break,
+			(v6, v4) => out.extend(v6.into_iter().chain(v4)),
+		}
+	}
+	out
+}
+
+/// Returns `None` if the `timeout` is exceeded.
+async fn with_timeout<F: Future>(timeout: Option<Duration>, fut: F) -> Option<F::Output> {
+	let fut = pin!(fut);
+	match timeout {
+		Some(timeout) => Timed::platform_new(fut, timeout).await.ok(),
+		None => Some(fut.await),
+	}
+}
+
+async fn upgrade(url: String, max_message_size: Option<usize>) -> Result<WebSocket, ConnectError> {
+	let mut request = Client::new().get(url).upgrade();
+	if let Some(max_message_size) = max_message_size {
+		request = request.web_socket_config(
+			WebSocketConfig::default()
+				.max_message_size(Some(max_message_size))
+				.max_frame_size(Some(max_message_size)),
+		);
+	}
+	let res = request.send().await?;
+	res.error_for_status_ref()?;
+	Ok(res.into_websocket().await?)
+}
+
+async fn wait_for_pong(websocket: &mut WebSocket, probe: LivenessProbe) -> Result<Option<Received>, ConnectError> {
+	while let Some(msg) = websocket.next().await {
+		match msg? {
+			Message::Pong(_) => return Ok(None),
+			Message::Text(txt) if probe == LivenessProbe::AnyMessage => return Ok(Some(Received::Text(txt))),
+			Message::Binary(bin) if probe == LivenessProbe::AnyMessage => return Ok(Some(Received::Binary(bin))),
+			Message::Ping(payload) => websocket.send(Message::Pong(payload)).await?,
+			Message::Text(_) | Message::Binary(_) => {}
 			Message::Close { code, reason } => {
 				return Err(ConnectError::DongleError(DongleError::from_code_and_reason(code, reason)));
 			}
 		}
-
-		Ok(Self { websocket })
 	}
+	Err(ConnectError::DongleIsNotResponding)
 }
 
 impl Stream for WebsocketEnergyDongle {
 	type Item = Result<Bytes, StreamError>;
 
 	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-		let Some(msg_res) = ready!(Pin::new(&mut self.websocket).poll_next(cx)) else {
-			return Poll::Ready(None);
-		};
-		let msg = match msg_res {
-			Ok(msg) => msg,
-			Err(err) => return Poll::Ready(Some(Err(StreamError::WebSocket(err)))),
-		};
-		match msg {
-			Message::Text(txt) => Poll::Ready(Some(Ok(Bytes::from(txt)))),
-			Message::Binary(bin) => Poll::Ready(Some(Ok(bin))),
-			Message::Ping(payload) => {
-				warn!("Ignoring spurious ping with payload: {payload:?}");
-				cx.waker().wake_by_ref();
-				Poll::Pending
-			}
-			Message::Pong(payload) => {
-				warn!("Ignoring spurious pong with payload: {payload:?}");
-				cx.waker().wake_by_ref();
-				Poll::Pending
-			}
-			Message::Close { code, reason } => Poll::Ready(Some(Err(StreamError::DongleError(DongleError::from_code_and_reason(
-				code, reason,
-			))))),
+		self.poll_received(cx).map_ok(|received| match received {
+			Received::Binary(bin) => bin,
+			Received::Text(txt) => Bytes::from(txt),
+		})
+	}
+}
+
+/// Class of the error returned by this module, see [ConnectError::kind()], [StreamError::kind()] and [DongleError::kind()].
+///
+/// Unlike the error enums themselves, the kinds and their numeric codes are stable across the crate releases, so they are suitable
+/// for matching in the applications and for passing over FFI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+	/// Dongle is not responding to the messages
+	NotResponding,
+	/// Dongle rejected the connection because the connection limit is reached
+	ConnectionLimitReached,
+	/// Dongle rejected the connection because the local API is disabled
+	LocalApiDisabled,
+	/// Other error reported by the dongle
+	Dongle,
+	/// WebSocket client or protocol error
+	WebSocket,
+	/// HTTP client error
+	Http,
+	/// Dongle is going away or restarting, the connection can be retried later
+	Restarting,
+	/// Dongle reported a protocol violation
+	Protocol,
+	/// Operation timed out
+	Timeout,
+}
+
+impl ErrorKind {
+	/// Returns the stable numeric code of the error kind.
+	pub fn code(self) -> u16 {
+		match self {
+			Self::NotResponding => 1,
+			Self::ConnectionLimitReached => 2,
+			Self::LocalApiDisabled => 3,
+			Self::Dongle => 4,
+			Self::WebSocket => 5,
+			Self::Http => 6,
+			Self::Restarting => 7,
+			Self::Protocol => 8,
+			Self::Timeout => 9,
+		}
+	}
+}
+
+impl fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::NotResponding => write!(f, "not responding"),
+			Self::ConnectionLimitReached => write!(f, "connection limit reached"),
+			Self::LocalApiDisabled => write!(f, "local API disabled"),
+			Self::Dongle => write!(f, "dongle error"),
+			Self::WebSocket => write!(f, "WebSocket error"),
+			Self::Http => write!(f, "HTTP error"),
+			Self::Restarting => write!(f, "restarting"),
+			Self::Protocol => write!(f, "protocol error"),
+			Self::Timeout => write!(f, "timeout"),
 		}
 	}
 }
 
 /// Possible error scenarios for [WebsocketEnergyDongle::connect()].
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ConnectError {
 	/// Dongle is not responding to the messages
 	DongleIsNotResponding,
+	/// Empty list of addresses was passed to [WebsocketEnergyDongle::builder_with_addresses()]
+	NoAddresses,
+	/// Connection or HTTP upgrade didn't complete within the configured timeout
+	ConnectTimeout,
+	/// Dongle didn't respond to the initial ping within the configured timeout
+	HandshakeTimeout,
 	/// Dongle-specific error
 	DongleError(DongleError),
 	/// WebSocket client error
@@ -119,6 +896,9 @@ impl fmt::Display for ConnectError {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self {
 			Self::DongleIsNotResponding => write!(f, "Homey Energy Dongle is not responding"),
+			Self::NoAddresses => write!(f, "No addresses to connect to"),
+			Self::ConnectTimeout => write!(f, "Timed out connecting to Homey Energy Dongle"),
+			Self::HandshakeTimeout => write!(f, "Timed out waiting for Homey Energy Dongle to respond"),
 			Self::DongleError(err) => write!(f, "Homey Energy Dongle error: {err}, details: {err:?}"),
 			Self::WebSocket(err) => write!(f, "WebSocket error: {err}, details: {err:?}"),
 			Self::Http(err) => write!(f, "HTTP error: {err}, details: {err:?}"),
@@ -126,15 +906,52 @@ impl fmt::Display for ConnectError {
 	}
 }
 
-impl std::error::Error for ConnectError {}
+impl ConnectError {
+	/// Returns the class of this error.
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Self::DongleIsNotResponding | Self::NoAddresses => ErrorKind::NotResponding,
+			Self::ConnectTimeout | Self::HandshakeTimeout => ErrorKind::Timeout,
+			Self::DongleError(err) => err.kind(),
+			Self::WebSocket(_) => ErrorKind::WebSocket,
+			Self::Http(_) => ErrorKind::Http,
+		}
+	}
+
+	/// Returns the stable numeric code of this error, shortcut for `self.kind().code()`.
+	pub fn code(&self) -> u16 {
+		self.kind().code()
+	}
+}
+
+impl std::error::Error for ConnectError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::DongleIsNotResponding | Self::NoAddresses | Self::ConnectTimeout | Self::HandshakeTimeout => None,
+			Self::DongleError(err) => Some(err),
+			Self::WebSocket(err) => Some(err),
+			Self::Http(err) => Some(err),
+		}
+	}
+}
 
 /// Possible error scenarios for [Stream] implementation of [WebsocketEnergyDongle].
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum StreamError {
 	/// Dongle-specific error
 	DongleError(DongleError),
 	/// WebSocket client error
 	WebSocket(reqwest_websocket::Error),
+	/// No message was received within the configured read timeout
+	ReadTimeout(Duration),
+	/// Received message exceeded the configured maximum size, it was discarded
+	MessageTooBig {
+		/// Size of the received message in bytes
+		size: usize,
+		/// Configured maximum message size in bytes
+		limit: usize,
+	},
 }
 
 impl fmt::Display for StreamError {
@@ -142,21 +959,82 @@ impl fmt::Display for StreamError {
 		match self {
 			Self::DongleError(err) => write!(f, "Homey Energy Dongle error: {err}, details: {err:?}"),
 			Self::WebSocket(err) => write!(f, "WebSocket error: {err}, details: {err:?}"),
+			Self::ReadTimeout(timeout) => write!(f, "No message received from Homey Energy Dongle in {timeout:?}"),
+			Self::MessageTooBig { size, limit } => write!(f, "Message of {size} bytes exceeds the limit of {limit} bytes"),
 		}
 	}
 }
 
-impl std::error::Error for StreamError {}
+impl StreamError {
+	/// Returns the class of this error.
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Self::DongleError(err) => err.kind(),
+			Self::WebSocket(_) => ErrorKind::WebSocket,
+			Self::ReadTimeout(_) => ErrorKind::Timeout,
+			Self::MessageTooBig { .. } => ErrorKind::Protocol,
+		}
+	}
+
+	/// Returns the stable numeric code of this error, shortcut for `self.kind().code()`.
+	pub fn code(&self) -> u16 {
+		self.kind().code()
+	}
+}
+
+impl std::error::Error for StreamError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::DongleError(err) => Some(err),
+			Self::WebSocket(err) => Some(err),
+			Self::ReadTimeout(_) | Self::MessageTooBig { .. } => None,
+		}
+	}
+}
 
 /// Specific errors returned by the Homey Energy Dongle API.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DongleError {
 	/// Connection limit reached
 	ConnectionLimitReached,
 	/// Local API disabled
 	LocalApiDisabled,
+	/// Dongle is going away, e.g. it's shutting down (close code 1001)
+	GoingAway {
+		/// Raw close code of the WebSocket connection
+		code: CloseCode,
+		/// Close reason provided by the dongle
+		reason: String,
+	},
+	/// Dongle is restarting or temporarily overloaded, the connection can be retried later (close codes 1012 and 1013)
+	Restarting {
+		/// Raw close code of the WebSocket connection
+		code: CloseCode,
+		/// Close reason provided by the dongle
+		reason: String,
+	},
+	/// Dongle has detected a protocol error or received invalid data (close codes 1002, 1003 and 1007)
+	ProtocolError {
+		/// Raw close code of the WebSocket connection
+		code: CloseCode,
+		/// Close reason provided by the dongle
+		reason: String,
+	},
+	/// Message sent to the dongle was too big (close code 1009)
+	MessageTooBig {
+		/// Raw close code of the WebSocket connection
+		code: CloseCode,
+		/// Close reason provided by the dongle
+		reason: String,
+	},
 	/// Other errors
-	Other(String),
+	Other {
+		/// Raw close code of the WebSocket connection
+		code: CloseCode,
+		/// Close reason provided by the dongle
+		reason: String,
+	},
 }
 
 impl DongleError {
@@ -165,11 +1043,31 @@ impl DongleError {
 			CloseCode::Policy => match reason.as_str() {
 				"Connection limit reached" => Self::ConnectionLimitReached,
 				"Local API disabled" => Self::LocalApiDisabled,
-				_ => Self::Other(reason),
+				_ => Self::Other { code, reason },
 			},
-			_ => Self::Other(reason),
+			CloseCode::Away => Self::GoingAway { code, reason },
+			CloseCode::Restart | CloseCode::Again => Self::Restarting { code, reason },
+			CloseCode::Protocol | CloseCode::Unsupported | CloseCode::Invalid => Self::ProtocolError { code, reason },
+			CloseCode::Size => Self::MessageTooBig { code, reason },
+			_ => Self::Other { code, reason },
+		}
+	}
+
+	/// Returns the class of this error.
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Self::ConnectionLimitReached => ErrorKind::ConnectionLimitReached,
+			Self::LocalApiDisabled => ErrorKind::LocalApiDisabled,
+			Self::GoingAway { .. } | Self::Restarting { .. } => ErrorKind::Restarting,
+			Self::ProtocolError { .. } | Self::MessageTooBig { .. } => ErrorKind::Protocol,
+			Self::Other { .. } => ErrorKind::Dongle,
 		}
 	}
+
+	/// Returns the stable numeric code of this error, shortcut for `self.kind().code()`.
+	pub fn code(&self) -> u16 {
+		self.kind().code()
+	}
 }
 
 impl fmt::Display for DongleError {
@@ -177,7 +1075,173 @@ impl fmt::Display for DongleError {
 		match self {
 			Self::ConnectionLimitReached => write!(f, "Connection limit reached"),
 			Self::LocalApiDisabled => write!(f, "Local API disabled"),
-			Self::Other(err) => f.write_str(err),
+			Self::GoingAway { code, reason } => write_close(f, "Dongle is going away", *code, reason),
+			Self::Restarting { code, reason } => write_close(f, "Dongle is restarting", *code, reason),
+			Self::ProtocolError { code, reason } => write_close(f, "Protocol error", *code, reason),
+			Self::MessageTooBig { code, reason } => write_close(f, "Message too big", *code, reason),
+			Self::Other { code, reason } if reason.is_empty() => write!(f, "Connection closed with code {code}"),
+			Self::Other { code, reason } => write!(f, "{reason} (close code {code})"),
+		}
+	}
+}
+
+fn write_close(f: &mut fmt::Formatter, message: &str, code: CloseCode, reason: &str) -> fmt::Result {
+	if reason.is_empty() {
+		write!(f, "{message} (close code {code})")
+	} else {
+		write!(f, "{message}: {reason} (close code {code})")
+	}
+}
+
+impl std::error::Error for DongleError {}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+	use std::error::Error;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::task::{Context, Poll, Wake, Waker};
+
+	use reqwest_websocket::CloseCode;
+
+	use super::{ConnectError, Demux, DongleError, ErrorKind, Received, ReceivedSource, StreamError, interleave_address_families};
+
+	/// Connection that never receives anything.
+	struct Silent;
+
+	impl ReceivedSource for Silent {
+		fn poll_received(&mut self, _cx: &mut Context) -> Poll<Option<Result<Received, StreamError>>> {
+			Poll::Pending
+		}
+	}
+
+	#[derive(Default)]
+	struct WakeCounter(AtomicUsize);
+
+	impl Wake for WakeCounter {
+		fn wake(self: Arc<Self>) {
+			self.0.fetch_add(1, Ordering::SeqCst);
 		}
 	}
+
+	fn silent_demux() -> Demux<Silent> {
+		Demux {
+			dongle: Silent,
+			data: VecDeque::new(),
+			text: VecDeque::new(),
+			data_waker: None,
+			text_waker: None,
+			data_dropped: false,
+			text_dropped: false,
+			terminated: false,
+		}
+	}
+
+	#[test]
+	fn test_demux_drop_wakes_other_stream() {
+		let data_wakes = Arc::new(WakeCounter::default());
+		let text_wakes = Arc::new(WakeCounter::default());
+		let data_waker = Waker::from(Arc::clone(&data_wakes));
+		let text_waker = Waker::from(Arc::clone(&text_wakes));
+
+		// the text stream polled last, so the connection only knows its waker
+		let mut demux = silent_demux();
+		assert!(demux.poll_data(&mut Context::from_waker(&data_waker)).is_pending());
+		assert!(demux.poll_text(&mut Context::from_waker(&text_waker)).is_pending());
+		demux.drop_text();
+		assert_eq!(1, data_wakes.0.load(Ordering::SeqCst));
+		assert_eq!(0, text_wakes.0.load(Ordering::SeqCst));
+
+		let mut demux = silent_demux();
+		assert!(demux.poll_text(&mut Context::from_waker(&text_waker)).is_pending());
+		assert!(demux.poll_data(&mut Context::from_waker(&data_waker)).is_pending());
+		demux.drop_data();
+		assert_eq!(1, text_wakes.0.load(Ordering::SeqCst));
+		assert_eq!(1, data_wakes.0.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn test_error_kind() {
+		let err = ConnectError::DongleError(DongleError::from_code_and_reason(
+			CloseCode::Policy,
+			"Connection limit reached".to_string(),
+		));
+		assert_eq!(ErrorKind::ConnectionLimitReached, err.kind());
+		assert_eq!(2, err.code());
+		assert_eq!("Connection limit reached", err.source().unwrap().to_string());
+
+		let err = StreamError::DongleError(DongleError::from_code_and_reason(
+			CloseCode::Policy,
+			"Local API disabled".to_string(),
+		));
+		assert_eq!(ErrorKind::LocalApiDisabled, err.kind());
+		assert!(err.source().is_some());
+
+		let err = ConnectError::DongleIsNotResponding;
+		assert_eq!(ErrorKind::NotResponding, err.kind());
+		assert!(err.source().is_none());
+	}
+
+	#[test]
+	fn test_close_code_mapping() {
+		let err = |code: CloseCode, reason: &str| DongleError::from_code_and_reason(code, reason.to_string());
+		assert!(matches!(
+			err(CloseCode::Away, "Shutting down"),
+			DongleError::GoingAway { code: CloseCode::Away, reason } if reason == "Shutting down"
+		));
+		assert!(matches!(err(CloseCode::Restart, ""), DongleError::Restarting { .. }));
+		assert!(matches!(
+			err(CloseCode::Again, ""),
+			DongleError::Restarting {
+				code: CloseCode::Again,
+				..
+			}
+		));
+		assert!(matches!(
+			err(CloseCode::Invalid, "Bad frame"),
+			DongleError::ProtocolError { code: CloseCode::Invalid, reason } if reason == "Bad frame"
+		));
+		assert!(matches!(err(CloseCode::Size, ""), DongleError::MessageTooBig { .. }));
+		assert_eq!(
+			"Dongle is going away: Shutting down (close code 1001)",
+			err(CloseCode::Away, "Shutting down").to_string()
+		);
+		assert_eq!("Message too big (close code 1009)", err(CloseCode::Size, "").to_string());
+		assert_eq!(ErrorKind::Restarting, err(CloseCode::Away, "").kind());
+		assert_eq!(ErrorKind::Protocol, err(CloseCode::Size, "").kind());
+		assert!(matches!(
+			err(CloseCode::Policy, "Unknown policy"),
+			DongleError::Other { code: CloseCode::Policy, reason } if reason == "Unknown policy"
+		));
+		assert!(matches!(
+			err(CloseCode::Error, "Boom"),
+			DongleError::Other {
+				code: CloseCode::Error,
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn test_interleave_address_families() {
+		let addr = |addr: &str| addr.parse().unwrap();
+		assert_eq!(
+			vec![
+				addr("[fe80::1]:80"),
+				addr("192.168.1.10:80"),
+				addr("[fe80::2]:80"),
+				addr("192.168.1.11:80"),
+				addr("192.168.1.12:80"),
+			],
+			interleave_address_families([
+				addr("192.168.1.10:80"),
+				addr("192.168.1.11:80"),
+				addr("[fe80::1]:80"),
+				addr("192.168.1.12:80"),
+				addr("[fe80::2]:80"),
+			])
+		);
+		assert!(interleave_address_families([]).is_empty());
+	}
 }